@@ -1,49 +1,249 @@
-extern crate gcc;
+use std::collections::BTreeSet;
 use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+// The RAM set aside for the stack, at the top of RAM.
+const STACK_SIZE: u32 = 4 * 1024;
+
+// The region between the heap and the stack which the MPU makes inaccessible, to catch the stack
+// overflowing. This must be a power of two of at least 32 bytes.
+const STACK_GUARD_SIZE: u32 = 256;
+
+// The least RAM the heap can have. The heap gets everything between bss and the stack, and the
+// link fails if that's less than this.
+const MIN_HEAP_SIZE: u32 = 12 * 1024;
+
+// How far a build may grow past the flash it used in its saved baseline
+// (size/<board>/<profile>.json, from `rake size:baseline`). The link fails if the code, read-only
+// data and initial values of the data section grow more than this. Keep it in step with
+// `release_flash_margin` in rakefile.rb.
+const FLASH_MARGIN: u32 = 1024;
+
+// The boards, by the cargo feature which selects them, with their name (as in size/) and the
+// linker script with their memory layout.
+const BOARDS: &'static [(&'static str, &'static str, &'static str)] = &[
+    ("CARGO_FEATURE_EK_TM4C123GXL", "ek-tm4c123gxl", "src/board/ek_tm4c123gxl.ld"),
+    ("CARGO_FEATURE_EK_TM4C1294XL", "ek-tm4c1294xl", "src/board/ek_tm4c1294xl.ld"),
+    ("CARGO_FEATURE_CUSTOM_BOARD", "custom-board", "src/board/custom.ld"),
+];
+
+// The name and linker script of the board selected by the cargo features.
+fn board() -> (&'static str, &'static str) {
+    let selected: Vec<(&'static str, &'static str)> = BOARDS.iter()
+        .filter(|&&(feature, _, _)| env::var(feature).is_ok())
+        .map(|&(_, name, script)| (name, script))
+        .collect();
+    match selected.len() {
+        1 => selected[0],
+        0 => panic!("Select a board with a cargo feature, such as ek-tm4c123gxl."),
+        _ => panic!("Select only one board feature."),
+    }
+}
+
+// The flash the build used when its baseline was saved, from the FLASH region of the report.
+// None when there's no baseline for this board and profile yet.
+fn baseline_flash_used(board: &str, profile: &str) -> Option<u32> {
+    let path = format!("size/{}/{}.json", board, profile);
+    println!("cargo:rerun-if-changed={}", path);
+    let mut report = String::new();
+    if File::open(&path).and_then(|mut file| file.read_to_string(&mut report)).is_err() {
+        return None;
+    }
+    let region = match report.find("\"name\": \"FLASH\"") {
+        Some(at) => &report[at..],
+        None => panic!("{} has no FLASH region", path),
+    };
+    let used = match region.find("\"used\": ") {
+        Some(at) => &region[at + "\"used\": ".len()..],
+        None => panic!("{} has no flash used", path),
+    };
+    let digits: String = used.chars().take_while(|c| c.is_digit(10)).collect();
+    match digits.parse() {
+        Ok(used) => Some(used),
+        Err(_) => panic!("{} has a bad flash used", path),
+    }
+}
+
+// Write the memory layout settings for the linker script (layout.ld includes memory.ld), along
+// with the board's own linker script.
+fn write_memory_layout() {
+    let (board, linker_script) = board();
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let mut file = File::create(out_dir.join("memory.ld")).unwrap();
+    writeln!(file, "/* Generated by build.rs. */").unwrap();
+    writeln!(file, "INCLUDE {}", linker_script).unwrap();
+    writeln!(file, "_stack_size = {:#x};", STACK_SIZE).unwrap();
+    writeln!(file, "_stack_guard_size = {:#x};", STACK_GUARD_SIZE).unwrap();
+    writeln!(file, "_min_heap_size = {:#x};", MIN_HEAP_SIZE).unwrap();
+    let profile = env::var("PROFILE").unwrap();
+    match baseline_flash_used(board, &profile) {
+        Some(used) => writeln!(file, "_flash_budget = {:#x};", used + FLASH_MARGIN).unwrap(),
+        None => {
+            // Nothing to compare against, so the build only has to fit.
+            println!("cargo:warning=No size/{}/{}.json baseline, so the flash budget is all of \
+                      flash. Save one with `rake size:baseline`.", board, profile);
+            writeln!(file, "_flash_budget = LENGTH(FLASH);").unwrap();
+        }
+    }
+
+    // Let the linker find it.
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+}
+
+// The logging macros, which the binary_log messages are found by.
+const LOG_MACROS: &'static [&'static str] = &["error!(", "warn!(", "info!(", "debug!("];
+
+// The firmware's compiler can't size a static from a string literal, so for the binary_log
+// feature this finds the format string of every message in src, and writes log_messages.rs (which
+// src/log/mod.rs includes) with a macro making the static for each one.
+fn write_log_messages() {
+    let mut formats = BTreeSet::new();
+    for path in rust_files(Path::new("src")) {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let mut source = String::new();
+        File::open(&path).and_then(|mut file| file.read_to_string(&mut source)).unwrap();
+        formats.extend(format_strings(&source));
+    }
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let mut file = File::create(out_dir.join("log_messages.rs")).unwrap();
+    writeln!(file, "// Generated by build.rs from the log messages in src. Don't edit.").unwrap();
+    writeln!(file, "macro_rules! log_message {{").unwrap();
+    for literal in &formats {
+        let mut format = unescape(literal);
+        if format.contains(&0) {
+            panic!("The log format string {} has a zero in it, which ends it in the ELF.", literal);
+        }
+        format.push(0);
+        writeln!(file, "    ({}) => {{", literal).unwrap();
+        writeln!(file, "        #[link_section = \".log_strings\"]").unwrap();
+        writeln!(file, "        static MESSAGE: ::log::Message<[u8; {}]> = ::log::Message {{", format.len()).unwrap();
+        writeln!(file, "            module: module_path!(),").unwrap();
+        writeln!(file, "            format: {:?},", format).unwrap();
+        writeln!(file, "        }};").unwrap();
+        writeln!(file, "    }};").unwrap();
+    }
+    writeln!(file, "}}").unwrap();
+}
+
+// The Rust files in a directory and the directories inside it.
+fn rust_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(rust_files(&path));
+        } else if path.extension().map_or(false, |extension| extension == "rs") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+// The format strings of the log messages in some source, as they're written there: quotes,
+// escapes and all, to match the tokens the macros are given.
+fn format_strings(source: &str) -> Vec<String> {
+    let mut formats = Vec::new();
+    for &name in LOG_MACROS {
+        let mut rest = source;
+        while let Some(at) = rest.find(name) {
+            // Not the end of some longer name, like `try_info!(`.
+            let whole = match rest[..at].chars().next_back() {
+                Some(c) => !(c.is_alphanumeric() || c == '_'),
+                None => true,
+            };
+            rest = rest[at + name.len()..].trim_left();
+            if whole && rest.starts_with('"') {
+                if let Some(length) = literal_length(rest) {
+                    formats.push(rest[..length].to_string());
+                }
+            }
+        }
+    }
+    formats
+}
+
+// The length of the string literal at the start of some text, up to and including its closing
+// quote. None if it has none.
+fn literal_length(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (at, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(at + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+// The UTF-8 a string literal stands for.
+fn unescape(literal: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut chars = literal[1..literal.len() - 1].chars().peekable();
+    while let Some(c) = chars.next() {
+        let c = if c != '\\' {
+            c
+        } else {
+            match chars.next() {
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some(c @ '\\') | Some(c @ '\'') | Some(c @ '"') => c,
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    u8::from_str_radix(&hex, 16).ok().map(char::from)
+                        .unwrap_or_else(|| panic!("Bad escape in the log format string {}", literal))
+                }
+                Some('u') => {
+                    let hex: String = chars.by_ref().take_while(|&c| c != '}').skip(1).collect();
+                    u32::from_str_radix(&hex, 16).ok().and_then(::std::char::from_u32)
+                        .unwrap_or_else(|| panic!("Bad escape in the log format string {}", literal))
+                }
+                Some('\n') => {
+                    // The string carries on after the indentation of the next line.
+                    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+                        chars.next();
+                    }
+                    continue;
+                }
+                _ => panic!("Bad escape in the log format string {}", literal),
+            }
+        };
+        let mut utf8 = [0; 4];
+        bytes.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+    }
+    bytes
+}
 
 // Build the application for the target.
 fn build_for_target () {
     
-    // Set the target C compiler to the ARM cross compiler.
-    env::set_var("TARGET_CC", "arm-none-eabi-gcc");
-    env::set_var("TARGET_CFLAGS", "-O0 -mthumb -mcpu=cortex-m4 -mfpu=fpv4-sp-d16 -mfloat-abi=softfp --specs=nano.specs");
-    
-    // Compile some C libraries so we can use some C code.
-    // Note that we new to use the "advanced" Config syntax so that we set the "pic" option to
-    // false. The prevents compilation with from using -fPIC option for position independent code
-    // (PIC). We do not want PIC, that's for dynamic libraries. Without this option we end up with
-    // .got and .got.ld sections in the binary, which are the sign that something is wrong.
-    // See https://github.com/alexcrichton/gcc-rs/pull/67 for details.
-    
-    // Compile my syscalls for the hardware, so we can use newlib as the C standard library.
-    gcc::Config::new()
-        .file("src/syscalls.c")
-        .pic(false)
-        .compile("libsyscalls.a");
+    write_memory_layout();
     
-    gcc::Config::new()
-        .file("src/startup.c")
-        .pic(false)
-        .compile("libstartup.a");
-    
-    // Link to the pre-compiled TivaWare library.
-    println!("cargo:rustc-link-search=native=lib/TivaWare/driverlib/gcc");
-    println!("cargo:rustc-link-lib=static=driver");
+    // The tivaware crate builds and links driverlib.
     
     // Link to the C standard library.
     println!("cargo:rustc-link-lib=static=c");
 }
 
-// Build for running the tests on the host.
-fn build_tests_for_host() {
-    println!("*********************************************");
-}
-
 fn main() {
+    // Check the board features whichever way we're building.
+    board();
+
+    // The binary_log messages are needed by the tests too.
+    if env::var("CARGO_FEATURE_BINARY_LOG").is_ok() {
+        write_log_messages();
+    }
+
+    // The tests run on the host, which needs no memory layout.
     if let Ok(target) = env::var("TARGET") {
         if let Ok(host) = env::var("HOST"){
             if target == host {
-                build_tests_for_host();
                 return;
             }
         }
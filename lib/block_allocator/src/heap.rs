@@ -0,0 +1,277 @@
+/*
+    The size-class heap behind the allocator, kept apart from the global so it can be tested on
+    the host with any piece of memory.
+*/
+
+use core::ptr;
+
+// The smallest block holds the free list link. Each class doubles the size of the one before.
+const MIN_BLOCK_SIZE: usize = 8;
+const NUM_CLASSES: usize = 10;
+
+// The largest single allocation (and alignment) the heap can hand out.
+pub const MAX_BLOCK_SIZE: usize = MIN_BLOCK_SIZE << (NUM_CLASSES - 1);
+
+// An empty free list.
+const EMPTY: usize = 0;
+
+pub struct Heap {
+    // The first free block of each size class. Each free block holds the address of the next.
+    free_lists: [usize; NUM_CLASSES],
+    start: usize,
+    // The start of the part of the heap which has never been handed out.
+    top: usize,
+    end: usize,
+}
+
+impl Heap {
+    // An empty heap. Nothing can be allocated until it's given memory with `init`.
+    pub const fn new() -> Heap {
+        Heap { free_lists: [EMPTY; NUM_CLASSES], start: 0, top: 0, end: 0 }
+    }
+
+    /// Give the heap the memory from `start` up to (not including) `end`.
+    ///
+    /// # Safety
+    ///
+    /// The memory must be readable and writable, and unused by anything else for as long as the
+    /// heap is. Blocks are handed out from it as they are.
+    pub unsafe fn init(&mut self, start: usize, end: usize) {
+        self.free_lists = [EMPTY; NUM_CLASSES];
+        self.start = start;
+        self.top = round_up(start, MIN_BLOCK_SIZE);
+        self.end = end;
+    }
+
+    // Returns null when there's no block of the right size left, or the request is bigger than
+    // MAX_BLOCK_SIZE.
+    pub fn allocate(&mut self, size: usize, align: usize) -> *mut u8 {
+        let class = match class_of(size, align) {
+            Some(class) => class,
+            None => return ptr::null_mut(),
+        };
+
+        let block = self.free_lists[class];
+        if block != EMPTY {
+            self.free_lists[class] = unsafe { ptr::read(block as *const usize) };
+            return block as *mut u8;
+        }
+
+        self.carve(class)
+    }
+
+    // Put a block back on the free list for its size class. The size and alignment must be the
+    // ones it was allocated with.
+    pub fn deallocate(&mut self, block: *mut u8, size: usize, align: usize) {
+        if let Some(class) = class_of(size, align) {
+            self.push(class, block as usize);
+        }
+    }
+
+    // The size of the block handed out for a request, all of which can be used.
+    pub fn usable_size(size: usize, align: usize) -> usize {
+        match class_of(size, align) {
+            Some(class) => block_size(class),
+            None => size,
+        }
+    }
+
+    // Whether a block allocated for one size also serves another, so it can be resized in place.
+    pub fn same_block_size(old_size: usize, size: usize, align: usize) -> bool {
+        let old_class = class_of(old_size, align);
+        old_class.is_some() && old_class == class_of(size, align)
+    }
+
+    // The number of bytes which have never been handed out.
+    pub fn unused(&self) -> usize {
+        self.end - self.top
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.end - self.start
+    }
+
+    // The largest block which could be allocated right now, either from a free list or carved
+    // from the unused part of the heap.
+    pub fn largest_free_block(&self) -> usize {
+        for class in (0 .. NUM_CLASSES).rev() {
+            let size = block_size(class);
+            let start = round_up(self.top, size);
+            if self.free_lists[class] != EMPTY || (start <= self.end && self.end - start >= size) {
+                return size;
+            }
+        }
+        0
+    }
+
+    // Cut a new block off the unused part of the heap. Every block is aligned to its own size,
+    // and the space skipped to get there goes to the free lists of the smaller classes.
+    fn carve(&mut self, class: usize) -> *mut u8 {
+        let size = block_size(class);
+        let start = round_up(self.top, size);
+        if start > self.end || self.end - start < size {
+            return ptr::null_mut();
+        }
+
+        while self.top < start {
+            // The biggest block that fits is given by the lowest bit set in the address.
+            let piece = self.top & self.top.wrapping_neg();
+            let top = self.top;
+            self.push(class_of(piece, 1).unwrap(), top);
+            self.top += piece;
+        }
+
+        self.top = start + size;
+        start as *mut u8
+    }
+
+    fn push(&mut self, class: usize, block: usize) {
+        unsafe { ptr::write(block as *mut usize, self.free_lists[class]); }
+        self.free_lists[class] = block;
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Heap {
+        Heap::new()
+    }
+}
+
+// The smallest size class which fits the size and alignment.
+fn class_of(size: usize, align: usize) -> Option<usize> {
+    let needed = if size > align { size } else { align };
+    (0 .. NUM_CLASSES).find(|&class| block_size(class) >= needed)
+}
+
+fn block_size(class: usize) -> usize {
+    MIN_BLOCK_SIZE << class
+}
+
+fn round_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    // Memory for a test heap, aligned to the largest block.
+    fn with_heap<F>(size: usize, test: F)
+        where F: FnOnce(&mut Heap)
+    {
+        let memory: Vec<u8> = vec![0; size + MAX_BLOCK_SIZE];
+        let start = round_up(memory.as_ptr() as usize, MAX_BLOCK_SIZE);
+        let mut heap = Heap::new();
+        unsafe { heap.init(start, start + size); }
+        test(&mut heap);
+    }
+
+    #[test]
+    fn requests_are_rounded_up_to_their_block_size() {
+        assert_eq!(8, Heap::usable_size(1, 1));
+        assert_eq!(16, Heap::usable_size(9, 4));
+        assert_eq!(64, Heap::usable_size(8, 64));
+        assert_eq!(MAX_BLOCK_SIZE, Heap::usable_size(MAX_BLOCK_SIZE, 8));
+    }
+
+    #[test]
+    fn blocks_are_aligned_as_requested() {
+        with_heap(64 * 1024, |heap| {
+            let mut align = 1;
+            while align <= MAX_BLOCK_SIZE {
+                for &size in [1, 7, 24, 100].iter() {
+                    let block = heap.allocate(size, align);
+                    assert!(!block.is_null());
+                    assert_eq!(0, block as usize % align, "size {} align {}", size, align);
+                }
+                align *= 2;
+            }
+        });
+    }
+
+    #[test]
+    fn a_freed_block_is_reused_for_the_same_size_class() {
+        with_heap(1024, |heap| {
+            let first = heap.allocate(20, 4);
+            heap.deallocate(first, 20, 4);
+            assert_eq!(first, heap.allocate(30, 4));
+        });
+    }
+
+    #[test]
+    fn allocation_fails_when_the_heap_is_used_up() {
+        with_heap(64, |heap| {
+            assert!(!heap.allocate(32, 4).is_null());
+            assert!(!heap.allocate(32, 4).is_null());
+            assert!(heap.allocate(32, 4).is_null());
+        });
+    }
+
+    #[test]
+    fn requests_bigger_than_the_largest_block_fail() {
+        with_heap(2 * MAX_BLOCK_SIZE, |heap| {
+            assert!(heap.allocate(MAX_BLOCK_SIZE + 1, 4).is_null());
+        });
+    }
+
+    #[test]
+    fn space_skipped_for_alignment_is_used_by_smaller_blocks() {
+        with_heap(1024, |heap| {
+            let small = heap.allocate(8, 8);
+            let big = heap.allocate(256, 256);
+            assert_eq!(small as usize + 256, big as usize);
+
+            // The 8, 16, 32, 64 and 128 byte pieces in between are all free.
+            let mut in_gap = 0;
+            for &size in [8, 16, 32, 64, 128].iter() {
+                let block = heap.allocate(size, 8) as usize;
+                if block > small as usize && block < big as usize {
+                    in_gap += 1;
+                }
+            }
+            assert_eq!(5, in_gap);
+        });
+    }
+
+    #[test]
+    fn the_largest_free_block_shrinks_as_the_heap_fills() {
+        with_heap(2 * MAX_BLOCK_SIZE, |heap| {
+            assert_eq!(MAX_BLOCK_SIZE, heap.largest_free_block());
+            let first = heap.allocate(MAX_BLOCK_SIZE, 8);
+            heap.allocate(MAX_BLOCK_SIZE / 2, 8);
+            assert_eq!(MAX_BLOCK_SIZE / 2, heap.largest_free_block());
+
+            heap.deallocate(first, MAX_BLOCK_SIZE, 8);
+            assert_eq!(MAX_BLOCK_SIZE, heap.largest_free_block());
+        });
+    }
+
+    #[test]
+    fn blocks_never_overlap() {
+        with_heap(8 * 1024, |heap| {
+            // Live blocks as (address, requested size).
+            let mut blocks: Vec<(usize, usize)> = Vec::new();
+            for i in 0 .. 200 {
+                let size = 1 + (i * 37) % 300;
+                let block = heap.allocate(size, 8);
+                if block.is_null() {
+                    break;
+                }
+                blocks.push((block as usize, size));
+
+                // Free some along the way so blocks get reused.
+                if i % 3 == 0 {
+                    let (freed, freed_size) = blocks.remove(0);
+                    heap.deallocate(freed as *mut u8, freed_size, 8);
+                }
+            }
+
+            blocks.sort();
+            for pair in blocks.windows(2) {
+                let (block, size) = pair[0];
+                assert!(block + Heap::usable_size(size, 8) <= pair[1].0);
+            }
+        });
+    }
+}
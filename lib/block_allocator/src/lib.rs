@@ -0,0 +1,162 @@
+// A fixed-block allocator written in Rust, as an alternative to libc_allocator. It doesn't need
+// newlib's malloc or _sbrk, and it allocates from the heap region the linker script sets aside
+// between `_heap_start` and `_heap_end`.
+//
+// Memory is handed out in blocks of a few fixed sizes (size classes). Each class keeps a list of
+// freed blocks, and new blocks are carved off the top of the unused part of the heap. Allocating
+// and freeing never search, so they take the same (short) time no matter what state the heap is
+// in, which makes them safe to use from interrupts. The cost is that a request is rounded up to
+// its block size, and freed blocks are only ever reused for requests of the same class.
+
+// The compiler needs to be instructed that this crate is an allocator in order
+// to realize that when this is linked in another allocator like jemalloc should
+// not be linked in. Only the firmware's nightly knows about this kind of allocator; the tests run
+// on the host allocator. That nightly also needs const_fn, which the firmware turns on with the
+// const-fn feature. Current compilers have const fn built in and reject the feature.
+#![cfg_attr(target_os = "none", feature(allocator))]
+#![cfg_attr(feature = "const-fn", feature(const_fn))]
+#![cfg_attr(all(target_os = "none", not(test)), allocator)]
+
+// Allocators are not allowed to depend on the standard library which in turn
+// requires an allocator in order to avoid circular dependencies.
+#![no_std]
+
+// Use this critical section package to make memory allocations safe from interrupts.
+extern crate critical_section_arm;
+
+extern crate heap_stats;
+pub use heap_stats::{HeapStats, LowMemoryHandler};
+
+#[cfg(test)]
+#[macro_use]
+extern crate std;
+
+mod heap;
+pub use heap::{Heap, MAX_BLOCK_SIZE};
+
+#[cfg(target_os = "none")]
+pub use global::{stats, set_low_memory_handler};
+
+#[cfg(target_os = "none")]
+mod global {
+    use core::cell::RefCell;
+    use core::{cmp, ptr};
+    use critical_section_arm::{self, CriticalSection, Mutex};
+    use heap::Heap;
+    use heap_stats::{HeapStats, LowMemoryHandler, Recorder};
+
+    extern "C" {
+        // The bounds of the heap, from the linker script.
+        static _heap_start: u8;
+        static _heap_end: u8;
+    }
+
+    // The heap is set up the first time anything is allocated.
+    static HEAP: Mutex<RefCell<Heap>> = Mutex::lazy(new_heap);
+
+    fn new_heap() -> RefCell<Heap> {
+        let mut heap = Heap::new();
+        unsafe {
+            heap.init(&_heap_start as *const u8 as usize, &_heap_end as *const u8 as usize);
+        }
+        RefCell::new(heap)
+    }
+
+    // Heap usage, counted in whole blocks.
+    static RECORDER: Mutex<RefCell<Recorder>> = Mutex::lazy(new_recorder);
+
+    fn new_recorder() -> RefCell<Recorder> {
+        let mut recorder = Recorder::new();
+        unsafe {
+            recorder.set_capacity(&_heap_end as *const u8 as usize - &_heap_start as *const u8 as usize);
+        }
+        RefCell::new(recorder)
+    }
+
+    // Heap usage since boot.
+    pub fn stats() -> HeapStats {
+        critical_section_arm::free(|cs| {
+            let largest_free_block = HEAP.borrow(cs).borrow().largest_free_block();
+            let stats = RECORDER.borrow(cs).borrow().stats(0, Some(largest_free_block));
+            // The recorder counts whole blocks, so everything else is free.
+            HeapStats { free: stats.capacity.saturating_sub(stats.in_use), ..stats }
+        })
+    }
+
+    // Call `handler` when an allocation fails, or leaves fewer than `reserve` bytes free. It's
+    // called once per shortage, outside of the allocator, so it may allocate.
+    pub fn set_low_memory_handler(reserve: usize, handler: LowMemoryHandler) {
+        critical_section_arm::free(|cs| {
+            RECORDER.borrow(cs).borrow_mut().set_low_memory_handler(reserve, handler)
+        })
+    }
+
+    fn record_allocation(cs: &CriticalSection, ptr: *mut u8, size: usize, align: usize)
+        -> Option<LowMemoryHandler>
+    {
+        let mut recorder = RECORDER.borrow(cs).borrow_mut();
+        if ptr.is_null() {
+            recorder.failed()
+        } else {
+            recorder.allocated(Heap::usable_size(size, align))
+        }
+    }
+
+    // Listed below are the five allocation functions currently required by custom
+    // allocators. Their signatures and symbol names are not currently typechecked
+    // by the compiler, but this is a future extension and are required to match
+    // what is found below.
+
+    #[no_mangle]
+    pub extern "C" fn __rust_allocate(size: usize, align: usize) -> *mut u8 {
+        let (ptr, low_memory) = critical_section_arm::free(|cs| {
+            let ptr = HEAP.borrow(cs).borrow_mut().allocate(size, align);
+            (ptr, record_allocation(cs, ptr, size, align))
+        });
+
+        // Now that the heap is free again, let the handler know memory is running out.
+        if let Some(handler) = low_memory {
+            handler();
+        }
+        ptr
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __rust_deallocate(ptr: *mut u8, old_size: usize, align: usize) {
+        critical_section_arm::free(|cs| {
+            HEAP.borrow(cs).borrow_mut().deallocate(ptr, old_size, align);
+            RECORDER.borrow(cs).borrow_mut().deallocated(Heap::usable_size(old_size, align));
+        })
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __rust_reallocate(ptr: *mut u8, old_size: usize, size: usize,
+                                    align: usize) -> *mut u8 {
+        if Heap::same_block_size(old_size, size, align) {
+            // It already fits in the block it has.
+            return ptr;
+        }
+
+        let new_ptr = __rust_allocate(size, align);
+        if !new_ptr.is_null() {
+            unsafe { ptr::copy_nonoverlapping(ptr, new_ptr, cmp::min(old_size, size)); }
+            __rust_deallocate(ptr, old_size, align);
+        }
+        new_ptr
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __rust_reallocate_inplace(_ptr: *mut u8, old_size: usize,
+                                            size: usize, align: usize) -> usize {
+        if Heap::same_block_size(old_size, size, align) {
+            Heap::usable_size(size, align)
+        } else {
+            old_size
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __rust_usable_size(size: usize, align: usize) -> usize {
+        Heap::usable_size(size, align)
+    }
+}
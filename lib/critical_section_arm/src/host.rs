@@ -0,0 +1,224 @@
+/*
+    Host backend for running tests off the target.
+
+    The interrupt mask (PRIMASK) and base priority (BASEPRI) are simulated for each thread, as
+    though each test had its own processor. The Mutex statics they protect are shared by every
+    thread though, so a thread entering a critical section also takes a lock for the whole process,
+    which it holds until its outermost section ends. Sections on different threads (tests running
+    in parallel) take turns, like on the one processor of the target.
+
+    Tests can check whether they're inside a critical section, and simulate an interrupt with
+    `interrupt`, which runs on the test's own thread. An interrupt which is masked when it fires
+    stays pending until the mask is lifted, just like on the target.
+*/
+
+use std::boxed::Box;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::vec::Vec;
+
+// Written for the firmware's nightly, which builds this for its own tests and predates `dyn`.
+#[allow(unknown_lints, bare_trait_objects)]
+struct Pending {
+    priority: u8,
+    handler: Box<FnMut()>,
+}
+
+// Held by the thread which is in a critical section.
+static SECTION_LOCK: AtomicBool = AtomicBool::new(false);
+
+// Whether this thread holds SECTION_LOCK. A thread which ends inside a critical section gives it
+// back, so one failed test doesn't hold up the rest.
+struct LockHolder(Cell<bool>);
+
+impl Drop for LockHolder {
+    fn drop(&mut self) {
+        if self.0.get() {
+            SECTION_LOCK.store(false, Ordering::Release);
+        }
+    }
+}
+
+// The firmware's nightly has no const initializers for thread locals, so these start out default
+// (unmasked, nothing pending) instead.
+thread_local! {
+    static PRIMASK: Cell<u32> = Cell::default();
+    static BASEPRI: Cell<u8> = Cell::default();
+    static PENDING: RefCell<Vec<Pending>> = RefCell::default();
+    static HOLDS_LOCK: LockHolder = LockHolder(Cell::default());
+}
+
+// True when every interrupt is masked (inside a CriticalSection).
+pub fn interrupts_masked() -> bool {
+    PRIMASK.with(|p| p.get()) & super::PRIMASK_MASKED != 0
+}
+
+// The current base priority. Zero means no interrupts are masked by priority.
+pub fn basepri() -> u8 {
+    BASEPRI.with(|b| b.get())
+}
+
+// True when an interrupt at the given priority couldn't run right now.
+pub fn priority_masked(priority: u8) -> bool {
+    let basepri = basepri();
+    interrupts_masked() || (basepri != 0 && priority >= basepri)
+}
+
+// True when the code calling this is inside any kind of critical section.
+pub fn in_critical_section() -> bool {
+    interrupts_masked() || basepri() != 0
+}
+
+// Fire an interrupt at the given priority. The handler runs straight away unless the priority is
+// masked, in which case it runs as soon as the critical section masking it ends.
+pub fn interrupt<F>(priority: u8, handler: F)
+    where F: FnMut() + 'static
+{
+    PENDING.with(|p| p.borrow_mut().push(Pending { priority, handler: Box::new(handler) }));
+    run_pending();
+}
+
+// The number of interrupts waiting for a critical section to end.
+pub fn pending_interrupts() -> usize {
+    PENDING.with(|p| p.borrow().len())
+}
+
+// Set PRIMASK, returning the value from before.
+pub fn set_primask(primask: u32) -> u32 {
+    let previous = PRIMASK.with(|p| {
+        let previous = p.get();
+        p.set(primask);
+        previous
+    });
+    update_lock();
+    run_pending();
+    previous
+}
+
+// Behaves like writing BASEPRI_MAX: the new priority only takes effect if it masks more.
+pub fn raise_basepri(ceiling: u8) -> u8 {
+    let previous = basepri();
+    if ceiling != 0 && (previous == 0 || ceiling < previous) {
+        BASEPRI.with(|b| b.set(ceiling));
+    }
+    update_lock();
+    previous
+}
+
+pub fn set_basepri(basepri: u8) {
+    BASEPRI.with(|b| b.set(basepri));
+    update_lock();
+    run_pending();
+}
+
+// Take SECTION_LOCK when this thread enters its outermost critical section, waiting for any other
+// thread's section to end, and give it back when that section ends.
+fn update_lock() {
+    let masked = in_critical_section();
+    HOLDS_LOCK.with(|holder| {
+        if masked && !holder.0.get() {
+            while SECTION_LOCK.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                              .is_err() {
+                thread::yield_now();
+            }
+            holder.0.set(true);
+        } else if !masked && holder.0.get() {
+            SECTION_LOCK.store(false, Ordering::Release);
+            holder.0.set(false);
+        }
+    });
+}
+
+// Run every pending interrupt which is no longer masked, most urgent first.
+fn run_pending() {
+    loop {
+        let next = PENDING.with(|p| {
+            let mut pending = p.borrow_mut();
+            let runnable = pending.iter()
+                .enumerate()
+                .filter(|&(_, i)| !priority_masked(i.priority))
+                .min_by_key(|&(_, i)| i.priority)
+                .map(|(index, _)| index);
+            runnable.map(|index| pending.remove(index))
+        });
+
+        match next {
+            Some(mut interrupt) => (interrupt.handler)(),
+            None => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use std::sync::atomic::AtomicUsize;
+    use {free, with_ceiling, CriticalSection, PriorityCeiling};
+
+    #[test]
+    fn an_unmasked_interrupt_runs_straight_away() {
+        let ran = Rc::new(Cell::new(false));
+        let r = ran.clone();
+        interrupt(0x20, move || r.set(true));
+        assert!(ran.get());
+    }
+
+    #[test]
+    fn an_interrupt_in_a_critical_section_runs_when_the_section_ends() {
+        let ran = Rc::new(Cell::new(false));
+        {
+            let _cs = CriticalSection::new();
+            let r = ran.clone();
+            interrupt(0x20, move || r.set(true));
+            assert!(!ran.get());
+            assert_eq!(1, pending_interrupts());
+        }
+        assert!(ran.get());
+        assert_eq!(0, pending_interrupts());
+    }
+
+    #[test]
+    fn an_interrupt_above_the_priority_ceiling_is_not_held_off() {
+        let ran = Rc::new(Cell::new(false));
+        let _ceiling = PriorityCeiling::new(0x40);
+        let r = ran.clone();
+        interrupt(0x20, move || r.set(true));
+        assert!(ran.get());
+    }
+
+    #[test]
+    fn the_handler_does_not_run_in_the_interrupted_critical_section() {
+        let masked_in_handler = Rc::new(Cell::new(true));
+        {
+            let _cs = CriticalSection::new();
+            let m = masked_in_handler.clone();
+            interrupt(0x20, move || m.set(in_critical_section()));
+        }
+        assert!(!masked_in_handler.get());
+    }
+
+    #[test]
+    fn sections_on_different_threads_take_turns() {
+        static INSIDE: AtomicUsize = AtomicUsize::new(0);
+        let threads = (0..4).map(|n| thread::spawn(move || {
+            for _ in 0..100 {
+                let enter = || {
+                    assert_eq!(0, INSIDE.fetch_add(1, Ordering::SeqCst));
+                    thread::yield_now();
+                    INSIDE.fetch_sub(1, Ordering::SeqCst);
+                };
+                if n % 2 == 0 {
+                    free(|_| enter());
+                } else {
+                    with_ceiling(0x40, |_| enter());
+                }
+            }
+        })).collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+}
@@ -1,70 +1,268 @@
 /*
     Control for enabling and disabling critical sections.
     
-    Disable calls are refernce counted to allow for nesting. Uses RAII like is done in Zinc:
+    Each critical section saves the interrupt state on entry and restores it on exit, which
+    allows for nesting. Uses RAII like is done in Zinc:
     https://github.com/hackndev/zinc/blob/master/src/hal/cortex_common/irq.rs
+
+    There are two kinds of critical section. CriticalSection masks every interrupt.
+    PriorityCeiling only masks interrupts at or below a priority ceiling (using BASEPRI), so more
+    urgent interrupts still get serviced. Both can be nested with each other.
+
+    Sections are only handed out to closures, by `free` and `with_ceiling`, so they always end in
+    the reverse order they began and a token can't outlive its section. A section which could be
+    dropped before one nested inside it would unmask interrupts while the inner token still lives.
 */
 
 #![no_std]
-#![feature(asm)]
+
+// The firmware's nightly still needs these: asm for the target, and const_fn wherever it builds
+// this (the firmware turns on the const-fn feature). Current compilers have const fn built in and
+// reject the feature.
+#![cfg_attr(target_arch = "arm", feature(asm))]
+#![cfg_attr(feature = "const-fn", feature(const_fn))]
+
+// The host backend simulates the interrupt mask with thread local state, which needs std.
+#[cfg(not(target_arch = "arm"))]
+#[macro_use]
+extern crate std;
+
+#[cfg(not(target_arch = "arm"))]
+pub mod host;
+
+mod mutex;
+pub use mutex::Mutex;
 
 use core::ops::Drop;
 
+// A critical section with every interrupt masked. The state of PRIMASK is saved on entry and
+// restored when the section is dropped, so nesting works and a section created with interrupts
+// already disabled (in a fault handler or during boot) leaves them disabled.
+//
+// A reference to a CriticalSection is also a token proving that interrupts are masked for as
+// long as the reference lives. Get one with `free`.
 pub struct CriticalSection {
-    #[allow(dead_code)]
-    contents: ()
+    primask: u32,
 }
 
 impl CriticalSection {
-    pub fn new() -> CriticalSection {
-        unsafe { disable_interrupts(); }
-        CriticalSection { contents: () }
+    fn new() -> CriticalSection {
+        let primask = unsafe { disable_interrupts() };
+        CriticalSection { primask }
     }
 }
 
-// When the critical section goes out of scope, re-enable interrupts.
+// When the critical section goes out of scope, put interrupts back the way they were.
 impl Drop for CriticalSection {
     fn drop(&mut self) {
-        unsafe { enable_interrupts(); }
+        unsafe { restore_interrupts(self.primask); }
+    }
+}
+
+// Run the closure with every interrupt masked, handing it the critical section as a token.
+//
+//     let value = critical_section_arm::free(|cs| SHARED.borrow(cs).get());
+pub fn free<F, R>(f: F) -> R
+    where F: FnOnce(&CriticalSection) -> R
+{
+    let cs = CriticalSection::new();
+    f(&cs)
+}
+
+// Proof that interrupts are masked, for as long as the token is borrowed. Only this crate's
+// sections can be tokens (the trait is sealed), since Mutex trusts the ceiling a token reports.
+pub trait Token: sealed::Sealed {
+    // The BASEPRI value of the most urgent priority which is masked. Zero means every interrupt is
+    // masked.
+    fn ceiling(&self) -> u8;
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::CriticalSection {}
+    impl Sealed for super::PriorityCeiling {}
+}
+
+impl Token for CriticalSection {
+    fn ceiling(&self) -> u8 {
+        0
+    }
+}
+
+impl Token for PriorityCeiling {
+    fn ceiling(&self) -> u8 {
+        self.ceiling
     }
 }
 
-static mut critical_section_nest_level: usize = 0;
+// PRIMASK is 1 when interrupts are masked.
+const PRIMASK_MASKED: u32 = 1;
 
+// Mask interrupts, returning the PRIMASK from before.
 #[cfg(target_arch = "arm")]
-unsafe fn disable_interrupts() {
-    asm!("cpsid i" :::: "volatile");
-    critical_section_nest_level += 1;
+unsafe fn disable_interrupts() -> u32 {
+    let primask: u32;
+    asm!("mrs $0, PRIMASK" : "=r"(primask) ::: "volatile");
+    asm!("cpsid i" ::: "memory" : "volatile");
+    primask
 }
 
 #[cfg(not(target_arch = "arm"))]
-unsafe fn disable_interrupts() {
-    unimplemented!();
+unsafe fn disable_interrupts() -> u32 {
+    host::set_primask(PRIMASK_MASKED)
 }
 
-
 #[cfg(target_arch = "arm")]
-unsafe fn enable_interrupts() {
-    if critical_section_nest_level == 0 {
-        // This is an error. Attempting to enable interrupts without them being disabled.
-        panic!();
+unsafe fn restore_interrupts(primask: u32) {
+    if primask & PRIMASK_MASKED == 0 {
+        // Interrupts were enabled when this section was entered. Re-enable them now.
+        asm!("cpsie i" ::: "memory" : "volatile");
     }
-    
-    critical_section_nest_level -= 1;
-    if critical_section_nest_level == 0 {
-        // We've gotten back to the first place interrupts were disabled. Re-enable them now.
-        asm!("cpsie i" :::: "volatile");
+}
+
+#[cfg(not(target_arch = "arm"))]
+unsafe fn restore_interrupts(primask: u32) {
+    host::set_primask(primask);
+}
+
+// A critical section which only masks interrupts whose priority is at or below the ceiling. The
+// ceiling is a priority as written to the NVIC priority registers, so lower values are more urgent
+// and only the implemented upper bits count. Use this to protect data shared with a known set of
+// interrupts: set the ceiling to the most urgent priority of those interrupts.
+//
+// Nesting only ever raises the ceiling, and dropping restores the ceiling that was in place
+// before, so sections can be nested in any order.
+pub struct PriorityCeiling {
+    ceiling: u8,
+    previous: u8,
+}
+
+impl PriorityCeiling {
+    fn new(ceiling: u8) -> PriorityCeiling {
+        // A BASEPRI of 0 masks nothing, so a ceiling of 0 can't be expressed. Interrupts at the
+        // highest priority need a full CriticalSection.
+        assert!(ceiling != 0);
+        let previous = unsafe { raise_base_priority(ceiling) };
+        PriorityCeiling { ceiling, previous }
+    }
+}
+
+impl Drop for PriorityCeiling {
+    fn drop(&mut self) {
+        unsafe { restore_base_priority(self.previous); }
     }
 }
 
+// Run the closure with the interrupts at or below the ceiling masked, handing it the section as a
+// token.
+//
+//     let event = critical_section_arm::with_ceiling(CEILING, |cs| QUEUE.borrow(cs).borrow_mut().pop());
+pub fn with_ceiling<F, R>(ceiling: u8, f: F) -> R
+    where F: FnOnce(&PriorityCeiling) -> R
+{
+    let cs = PriorityCeiling::new(ceiling);
+    f(&cs)
+}
+
+// Raise the base priority to the ceiling (if that's more restrictive than it already is),
+// returning the base priority from before.
+#[cfg(target_arch = "arm")]
+unsafe fn raise_base_priority(ceiling: u8) -> u8 {
+    let previous: u32;
+    asm!("mrs $0, BASEPRI" : "=r"(previous) ::: "volatile");
+    // BASEPRI_MAX only takes the new value if it masks more than the current one.
+    asm!("msr BASEPRI_MAX, $0" :: "r"(ceiling as u32) : "memory" : "volatile");
+    previous as u8
+}
+
+#[cfg(not(target_arch = "arm"))]
+unsafe fn raise_base_priority(ceiling: u8) -> u8 {
+    host::raise_basepri(ceiling)
+}
+
+#[cfg(target_arch = "arm")]
+unsafe fn restore_base_priority(previous: u8) {
+    asm!("msr BASEPRI, $0" :: "r"(previous as u32) : "memory" : "volatile");
+}
+
 #[cfg(not(target_arch = "arm"))]
-unsafe fn enable_interrupts() {
-    unimplemented!();
+unsafe fn restore_base_priority(previous: u8) {
+    host::set_basepri(previous);
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use host;
+
+    #[test]
+    fn a_critical_section_masks_interrupts_until_it_is_dropped() {
+        {
+            let _cs = CriticalSection::new();
+            assert!(host::interrupts_masked());
+        }
+        assert!(!host::interrupts_masked());
+    }
+
+    #[test]
+    fn nested_critical_sections_only_unmask_when_the_outermost_is_dropped() {
+        let outer = CriticalSection::new();
+        {
+            let _inner = CriticalSection::new();
+        }
+        assert!(host::interrupts_masked());
+        drop(outer);
+        assert!(!host::interrupts_masked());
+    }
+
+    #[test]
+    fn a_critical_section_entered_with_interrupts_disabled_leaves_them_disabled() {
+        host::set_primask(PRIMASK_MASKED);
+        {
+            let _cs = CriticalSection::new();
+        }
+        assert!(host::interrupts_masked());
+        host::set_primask(0);
+    }
+
+    #[test]
+    fn free_runs_the_closure_in_a_critical_section() {
+        let masked = free(|_cs| host::interrupts_masked());
+        assert!(masked);
+        assert!(!host::interrupts_masked());
+    }
+
+    #[test]
+    fn with_ceiling_runs_the_closure_under_the_ceiling() {
+        let basepri = with_ceiling(0x40, |cs| {
+            assert_eq!(0x40, cs.ceiling());
+            host::basepri()
+        });
+        assert_eq!(0x40, basepri);
+        assert_eq!(0, host::basepri());
+    }
+
+    #[test]
+    fn a_priority_ceiling_only_masks_interrupts_at_or_below_the_ceiling() {
+        let _ceiling = PriorityCeiling::new(0x40);
+        assert!(host::priority_masked(0x40));
+        assert!(host::priority_masked(0x60));
+        assert!(!host::priority_masked(0x20));
+    }
+
     #[test]
-    fn it_works() {
+    fn nested_priority_ceilings_restore_the_previous_ceiling() {
+        let outer = PriorityCeiling::new(0x60);
+        {
+            let _inner = PriorityCeiling::new(0x20);
+            assert_eq!(0x20, host::basepri());
+            // A lower ceiling doesn't loosen the mask.
+            let _lower = PriorityCeiling::new(0x80);
+            assert_eq!(0x20, host::basepri());
+        }
+        assert_eq!(0x60, host::basepri());
+        drop(outer);
+        assert_eq!(0, host::basepri());
     }
 }
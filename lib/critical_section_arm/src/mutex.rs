@@ -0,0 +1,152 @@
+/*
+    A global which is shared with interrupts.
+
+    The contents can only be borrowed with a token proving a critical section is held, so they
+    can't be touched by an interrupt in the middle of being used. Like a static RefCell, wrap the
+    contents in a Cell or RefCell to change them:
+
+        static COUNT: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+
+        critical_section_arm::free(|cs| {
+            let count = COUNT.borrow(cs);
+            count.set(count.get() + 1);
+        });
+
+    Contents which can't be built in a constant (like anything which allocates) are created by an
+    initialization function the first time they're borrowed.
+
+    By default only a full CriticalSection will do. A mutex which is only ever used from main and
+    from interrupts at a known priority can be given a ceiling, and then a PriorityCeiling at least
+    that high will also do:
+
+        let event = critical_section_arm::with_ceiling(CEILING, |cs| QUEUE.borrow(cs).borrow_mut().pop());
+*/
+
+use core::cell::UnsafeCell;
+use Token;
+
+enum State<T> {
+    Uninitialized(fn() -> T),
+    Initialized(T),
+}
+
+pub struct Mutex<T> {
+    // The BASEPRI value of the most urgent interrupt using the contents, or 0 to only allow a
+    // full critical section.
+    ceiling: u8,
+    state: UnsafeCell<State<T>>,
+}
+
+// The contents are only ever reached inside a critical section, so they can be shared with
+// interrupts as long as they can be sent between them.
+unsafe impl<T> Sync for Mutex<T> where T: Send {}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Mutex<T> {
+        Mutex { ceiling: 0, state: UnsafeCell::new(State::Initialized(value)) }
+    }
+
+    // The contents are created by `init` when they're first borrowed.
+    pub const fn lazy(init: fn() -> T) -> Mutex<T> {
+        Mutex { ceiling: 0, state: UnsafeCell::new(State::Uninitialized(init)) }
+    }
+
+    // Like lazy, but also allows borrowing with a PriorityCeiling of `ceiling` or higher (lower
+    // values are more urgent). Every interrupt using the contents must run at `ceiling` or lower.
+    pub const fn lazy_with_ceiling(ceiling: u8, init: fn() -> T) -> Mutex<T> {
+        Mutex { ceiling, state: UnsafeCell::new(State::Uninitialized(init)) }
+    }
+
+    // Borrow the contents for as long as the critical section is held.
+    pub fn borrow<'cs, C>(&'cs self, token: &'cs C) -> &'cs T
+        where C: Token
+    {
+        assert!(self.masked_by(token.ceiling()));
+
+        unsafe {
+            if let State::Initialized(ref value) = *self.state.get() {
+                return value;
+            }
+
+            // Nothing has borrowed the contents before, so nothing can be holding a reference to
+            // them, and the token keeps anything else from getting in while they're created.
+            let init = match *self.state.get() {
+                State::Uninitialized(init) => init,
+                State::Initialized(_) => unreachable!(),
+            };
+            *self.state.get() = State::Initialized(init());
+
+            match *self.state.get() {
+                State::Initialized(ref value) => value,
+                State::Uninitialized(_) => unreachable!(),
+            }
+        }
+    }
+
+    // Whether a section masking interrupts from `ceiling` down keeps out every user of the mutex.
+    fn masked_by(&self, ceiling: u8) -> bool {
+        ceiling == 0 || (self.ceiling != 0 && ceiling <= self.ceiling)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::{Cell, RefCell};
+    use std::vec::Vec;
+    use {free, CriticalSection, PriorityCeiling};
+
+    static COUNT: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+
+    fn new_list() -> RefCell<Vec<u32>> {
+        RefCell::new(vec![1])
+    }
+
+    #[test]
+    fn the_contents_can_be_borrowed_in_a_critical_section() {
+        free(|cs| {
+            let count = COUNT.borrow(cs);
+            count.set(count.get() + 1);
+            assert!(count.get() > 0);
+        });
+    }
+
+    #[test]
+    fn lazy_contents_are_created_on_first_borrow() {
+        let list: Mutex<RefCell<Vec<u32>>> = Mutex::lazy(new_list);
+        free(|cs| {
+            list.borrow(cs).borrow_mut().push(2);
+            assert_eq!(vec![1, 2], *list.borrow(cs).borrow());
+        });
+    }
+
+    #[test]
+    fn a_priority_ceiling_at_or_above_the_mutex_ceiling_can_borrow() {
+        let list: Mutex<RefCell<Vec<u32>>> = Mutex::lazy_with_ceiling(0x40, new_list);
+        let ceiling = PriorityCeiling::new(0x20);
+        assert_eq!(1, list.borrow(&ceiling).borrow().len());
+    }
+
+    #[test]
+    #[should_panic]
+    fn a_priority_ceiling_below_the_mutex_ceiling_cannot_borrow() {
+        let list: Mutex<RefCell<Vec<u32>>> = Mutex::lazy_with_ceiling(0x40, new_list);
+        let ceiling = PriorityCeiling::new(0x60);
+        list.borrow(&ceiling);
+    }
+
+    #[test]
+    #[should_panic]
+    fn a_mutex_without_a_ceiling_needs_a_full_critical_section() {
+        let list: Mutex<RefCell<Vec<u32>>> = Mutex::lazy(new_list);
+        let ceiling = PriorityCeiling::new(0x20);
+        list.borrow(&ceiling);
+    }
+
+    #[test]
+    fn a_full_critical_section_can_always_borrow() {
+        let list: Mutex<RefCell<Vec<u32>>> = Mutex::lazy_with_ceiling(0x40, new_list);
+        let cs = CriticalSection::new();
+        assert_eq!(1, list.borrow(&cs).borrow().len());
+    }
+}
@@ -0,0 +1,199 @@
+/*
+    Heap usage statistics, shared by the allocators.
+
+    An allocator keeps a Recorder and tells it about every allocation, free and failure. The
+    recorder tracks how much of the heap is in use and the most that has ever been in use. It
+    also decides when the low memory handler should be called: when an allocation fails, or when
+    the free space drops below the reserve given with the handler. The handler is only called
+    once per shortage, and is called again once memory has been freed back above the reserve.
+
+    The allocator must call the handler after it has finished with the heap, since the handler
+    will most likely allocate.
+*/
+
+#![no_std]
+
+// The firmware's nightly still needs const_fn for Recorder::new, so the firmware turns on the
+// const-fn feature. Current compilers have it built in (and reject the feature).
+#![cfg_attr(feature = "const-fn", feature(const_fn))]
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HeapStats {
+    // The size of the heap.
+    pub capacity: usize,
+    // Bytes currently allocated.
+    pub in_use: usize,
+    // The most bytes ever allocated at once.
+    pub peak: usize,
+    // Blocks currently allocated.
+    pub live_allocations: usize,
+    // Allocations made since boot.
+    pub allocations: usize,
+    // Allocations which failed since boot.
+    pub failed_allocations: usize,
+    // Bytes free to allocate, though not necessarily in one piece. Unlike in_use, this accounts
+    // for the allocator's own overhead.
+    pub free: usize,
+    // The largest single allocation which could succeed right now, if the allocator can tell.
+    pub largest_free_block: Option<usize>,
+}
+
+// Called when the heap is about to run out.
+pub type LowMemoryHandler = fn();
+
+pub struct Recorder {
+    stats: HeapStats,
+    reserve: usize,
+    handler: Option<LowMemoryHandler>,
+    notified: bool,
+}
+
+impl Recorder {
+    pub const fn new() -> Recorder {
+        Recorder {
+            stats: HeapStats {
+                capacity: 0,
+                in_use: 0,
+                peak: 0,
+                live_allocations: 0,
+                allocations: 0,
+                failed_allocations: 0,
+                free: 0,
+                largest_free_block: None,
+            },
+            reserve: 0,
+            handler: None,
+            notified: false,
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.stats.capacity = capacity;
+    }
+
+    // Call `handler` when an allocation fails, or leaves fewer than `reserve` bytes free.
+    pub fn set_low_memory_handler(&mut self, reserve: usize, handler: LowMemoryHandler) {
+        self.reserve = reserve;
+        self.handler = Some(handler);
+        self.notified = false;
+    }
+
+    // The statistics so far. The allocator fills in what's free, which only it knows.
+    pub fn stats(&self, free: usize, largest_free_block: Option<usize>) -> HeapStats {
+        HeapStats { free, largest_free_block, ..self.stats }
+    }
+
+    // Record an allocation of `size` bytes. Returns the handler if it should be called.
+    pub fn allocated(&mut self, size: usize) -> Option<LowMemoryHandler> {
+        self.stats.in_use += size;
+        self.stats.live_allocations += 1;
+        self.stats.allocations += 1;
+        if self.stats.in_use > self.stats.peak {
+            self.stats.peak = self.stats.in_use;
+        }
+
+        let free = self.stats.capacity.saturating_sub(self.stats.in_use);
+        if free < self.reserve {
+            self.notify()
+        } else {
+            None
+        }
+    }
+
+    pub fn deallocated(&mut self, size: usize) {
+        self.stats.in_use = self.stats.in_use.saturating_sub(size);
+        self.stats.live_allocations = self.stats.live_allocations.saturating_sub(1);
+
+        if self.stats.capacity.saturating_sub(self.stats.in_use) >= self.reserve {
+            self.notified = false;
+        }
+    }
+
+    // Record an allocation which couldn't be satisfied. Returns the handler if it should be
+    // called.
+    pub fn failed(&mut self) -> Option<LowMemoryHandler> {
+        self.stats.failed_allocations += 1;
+        self.notify()
+    }
+
+    fn notify(&mut self) -> Option<LowMemoryHandler> {
+        if self.notified {
+            return None;
+        }
+        self.notified = self.handler.is_some();
+        self.handler
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handler() {}
+
+    fn recorder(capacity: usize) -> Recorder {
+        let mut r = Recorder::new();
+        r.set_capacity(capacity);
+        r
+    }
+
+    #[test]
+    fn it_tracks_the_bytes_in_use_and_the_peak() {
+        let mut r = recorder(1000);
+        r.allocated(100);
+        r.allocated(50);
+        r.deallocated(100);
+        let stats = r.stats(0, None);
+        assert_eq!(50, stats.in_use);
+        assert_eq!(150, stats.peak);
+        assert_eq!(1, stats.live_allocations);
+        assert_eq!(2, stats.allocations);
+    }
+
+    #[test]
+    fn it_counts_failed_allocations() {
+        let mut r = recorder(1000);
+        r.failed();
+        r.failed();
+        assert_eq!(2, r.stats(0, None).failed_allocations);
+    }
+
+    #[test]
+    fn there_is_nothing_to_call_without_a_handler() {
+        let mut r = recorder(100);
+        assert!(r.failed().is_none());
+    }
+
+    #[test]
+    fn the_handler_is_called_when_an_allocation_fails() {
+        let mut r = recorder(100);
+        r.set_low_memory_handler(0, handler);
+        assert!(r.failed().is_some());
+    }
+
+    #[test]
+    fn the_handler_is_called_when_free_memory_drops_below_the_reserve() {
+        let mut r = recorder(100);
+        r.set_low_memory_handler(30, handler);
+        assert!(r.allocated(60).is_none());
+        assert!(r.allocated(20).is_some());
+    }
+
+    #[test]
+    fn the_handler_is_only_called_once_until_memory_is_freed() {
+        let mut r = recorder(100);
+        r.set_low_memory_handler(30, handler);
+        assert!(r.allocated(80).is_some());
+        assert!(r.allocated(10).is_none());
+        assert!(r.failed().is_none());
+
+        r.deallocated(80);
+        assert!(r.allocated(80).is_some());
+    }
+}
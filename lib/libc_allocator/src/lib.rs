@@ -4,9 +4,12 @@
 
 // The compiler needs to be instructed that this crate is an allocator in order
 // to realize that when this is linked in another allocator like jemalloc should
-// not be linked in
-#![feature(allocator)]
-#![allocator]
+// not be linked in. Only the firmware's nightly knows about this kind of allocator; the tests run
+// on the host allocator. That nightly also needs const_fn, which the firmware turns on with the
+// const-fn feature. Current compilers have const fn built in and reject the feature.
+#![cfg_attr(target_os = "none", feature(allocator))]
+#![cfg_attr(feature = "const-fn", feature(const_fn))]
+#![cfg_attr(target_os = "none", allocator)]
 
 // Allocators are not allowed to depend on the standard library which in turn
 // requires an allocator in order to avoid circular dependencies. This crate,
@@ -17,59 +20,264 @@
 // This is not safe for multi-threading (as a fully re-entrant version of malloc would be),
 //but it works for a single thread with interrupts.
 extern crate critical_section_arm;
-use critical_section_arm::CriticalSection;
+use critical_section_arm::{CriticalSection, Mutex};
 
-//Define some types for our 32-bit processor.
-type usize = u32;
+extern crate heap_stats;
+use heap_stats::Recorder;
+pub use heap_stats::{HeapStats, LowMemoryHandler};
+
+use core::cell::RefCell;
+use core::{cmp, ptr};
+
+// The C names, to match the declarations below to malloc.h.
+#[allow(non_camel_case_types)]
 type c_void = u8;
+#[allow(non_camel_case_types)]
+type size_t = usize;
 
-extern {
-    pub fn malloc(size: u32) -> *mut c_void;
-    pub fn realloc(p: *mut c_void, size: u32) -> *mut c_void;
+extern "C" {
+    pub fn malloc(size: size_t) -> *mut c_void;
+    pub fn memalign(align: size_t, size: size_t) -> *mut c_void;
+    pub fn realloc(p: *mut c_void, size: size_t) -> *mut c_void;
     pub fn free(p: *mut c_void);	
 }
 
+// newlib's heap summary, from malloc.h.
+#[cfg(target_os = "none")]
+#[repr(C)]
+struct Mallinfo {
+    // Bytes claimed from _sbrk.
+    arena: size_t,
+    ordblks: size_t,
+    smblks: size_t,
+    hblks: size_t,
+    hblkhd: size_t,
+    usmblks: size_t,
+    fsmblks: size_t,
+    uordblks: size_t,
+    // Bytes in free chunks, including their headers.
+    fordblks: size_t,
+    keepcost: size_t,
+}
+
+#[cfg(target_os = "none")]
+extern "C" {
+    fn mallinfo() -> Mallinfo;
+}
+
+// The alignment of every block malloc hands out. newlib aligns to 8 bytes, like glibc on 32-bit
+// systems. glibc on 64-bit systems aligns to 16.
+#[cfg(target_pointer_width = "32")]
+const MIN_ALIGN: usize = 8;
+#[cfg(target_pointer_width = "64")]
+const MIN_ALIGN: usize = 16;
+
+// Heap usage, counted in bytes requested (not including malloc's own overhead).
+static RECORDER: Mutex<RefCell<Recorder>> = Mutex::lazy(new_recorder);
+
+fn new_recorder() -> RefCell<Recorder> {
+    let mut recorder = Recorder::new();
+    recorder.set_capacity(heap_capacity());
+    RefCell::new(recorder)
+}
+
+// Heap usage since boot.
+pub fn stats() -> HeapStats {
+    // malloc doesn't say how big its free chunks are, so the largest free block is unknown.
+    critical_section_arm::free(|cs| RECORDER.borrow(cs).borrow().stats(heap_free(), None))
+}
+
+// Call `handler` when an allocation fails, or leaves fewer than `reserve` bytes free. It's
+// called once per shortage, outside of the allocator, so it may allocate.
+pub fn set_low_memory_handler(reserve: usize, handler: LowMemoryHandler) {
+    critical_section_arm::free(|cs| {
+        RECORDER.borrow(cs).borrow_mut().set_low_memory_handler(reserve, handler)
+    })
+}
+
+// The heap for malloc, from the linker script.
+#[cfg(target_os = "none")]
+mod sbrk;
+
+#[cfg(target_os = "none")]
+fn heap_capacity() -> usize {
+    sbrk::heap_size()
+}
+
+// What's free in the part of the heap malloc has claimed from _sbrk, and all of the part it
+// hasn't. Call this with the allocator's critical section held.
+#[cfg(target_os = "none")]
+fn heap_free() -> usize {
+    let info = unsafe { mallinfo() };
+    heap_capacity() - info.arena + info.fordblks
+}
+
+// On the host malloc has the whole machine to work with.
+#[cfg(not(target_os = "none"))]
+fn heap_capacity() -> usize {
+    usize::max_value()
+}
+
+#[cfg(not(target_os = "none"))]
+fn heap_free() -> usize {
+    usize::max_value()
+}
+
 // Listed below are the five allocation functions currently required by custom
 // allocators. Their signatures and symbol names are not currently typechecked
 // by the compiler, but this is a future extension and are required to match
 // what is found below.
 //
-// Note that the standard `malloc` and `realloc` functions do not provide a way
-// to communicate alignment so this implementation would need to be improved
-// with respect to alignment in that aspect.
+// The standard `malloc` and `realloc` functions do not provide a way to
+// communicate alignment, so anything needing more than malloc already gives
+// goes through `memalign` instead. Blocks from `memalign` are released with
+// `free` like any other.
 
 #[no_mangle]
-pub extern fn __rust_allocate(size: usize, _align: usize) -> *mut u8 {
-    unsafe {
-        let _cs = CriticalSection::new();
-        malloc(size) as *mut u8
-    }
+pub extern "C" fn __rust_allocate(size: usize, align: usize) -> *mut u8 {
+    let (ptr, low_memory) = critical_section_arm::free(|cs| {
+        let ptr = unsafe { allocate(size, align) };
+        (ptr, record_allocation(cs, ptr, size))
+    });
+    notify(low_memory);
+    ptr
 }
 
 #[no_mangle]
-pub extern fn __rust_deallocate(ptr: *mut u8, _old_size: usize, _align: usize) {
-    unsafe {
-        let _cs = CriticalSection::new();
-        free(ptr as *mut c_void)
-    }
+pub extern "C" fn __rust_deallocate(ptr: *mut u8, old_size: usize, _align: usize) {
+    critical_section_arm::free(|cs| {
+        unsafe { free(ptr as *mut c_void); }
+        RECORDER.borrow(cs).borrow_mut().deallocated(old_size);
+    });
 }
 
+/// # Safety
+///
+/// `ptr` has to be a block from this allocator, `old_size` bytes long and aligned to `align`.
 #[no_mangle]
-pub extern fn __rust_reallocate(ptr: *mut u8, _old_size: usize, size: usize,
-                                _align: usize) -> *mut u8 {
-    unsafe {
-        let _cs = CriticalSection::new();
-        realloc(ptr as *mut c_void, size) as *mut u8
-    }
+pub unsafe extern "C" fn __rust_reallocate(ptr: *mut u8, old_size: usize, size: usize,
+                                       align: usize) -> *mut u8 {
+    let (new_ptr, low_memory) = critical_section_arm::free(|cs| {
+        let new_ptr = unsafe {
+            if align <= MIN_ALIGN {
+                realloc(ptr as *mut c_void, size)
+            } else {
+                // realloc could move the block somewhere without the right alignment, so move it
+                // ourselves.
+                let new_ptr = allocate(size, align);
+                if !new_ptr.is_null() {
+                    ptr::copy_nonoverlapping(ptr, new_ptr, cmp::min(old_size, size));
+                    free(ptr as *mut c_void);
+                }
+                new_ptr
+            }
+        };
+
+        // When reallocation fails the old block is left as it was.
+        if !new_ptr.is_null() {
+            RECORDER.borrow(cs).borrow_mut().deallocated(old_size);
+        }
+        (new_ptr, record_allocation(cs, new_ptr, size))
+    });
+    notify(low_memory);
+    new_ptr
 }
 
 #[no_mangle]
-pub extern fn __rust_reallocate_inplace(_ptr: *mut u8, old_size: usize,
+pub extern "C" fn __rust_reallocate_inplace(_ptr: *mut u8, old_size: usize,
                                         _size: usize, _align: usize) -> usize {
     old_size // this api is not supported by libc
 }
 
+// malloc rounds every request up to its own alignment, so at least that much is usable.
 #[no_mangle]
-pub extern fn __rust_usable_size(size: usize, _align: usize) -> usize {
-    size
+pub extern "C" fn __rust_usable_size(size: usize, _align: usize) -> usize {
+    round_up(size, MIN_ALIGN)
+}
+
+fn record_allocation(cs: &CriticalSection, ptr: *mut u8, size: usize) -> Option<LowMemoryHandler> {
+    let mut recorder = RECORDER.borrow(cs).borrow_mut();
+    if ptr.is_null() {
+        recorder.failed()
+    } else {
+        recorder.allocated(size)
+    }
+}
+
+// Call the low memory handler, once the allocator is done with the heap.
+fn notify(low_memory: Option<LowMemoryHandler>) {
+    if let Some(handler) = low_memory {
+        handler();
+    }
+}
+
+unsafe fn allocate(size: usize, align: usize) -> *mut u8 {
+    if align <= MIN_ALIGN {
+        malloc(size)
+    } else {
+        memalign(align, size)
+    }
+}
+
+fn round_up(size: usize, align: usize) -> usize {
+    (size + align - 1) & !(align - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [usize; 8] = [1, 3, 8, 15, 64, 100, 1000, 4096];
+    const ALIGNS: [usize; 9] = [1, 2, 4, 8, 16, 32, 64, 512, 4096];
+
+    fn is_aligned(ptr: *mut u8, align: usize) -> bool {
+        ptr as usize % align == 0
+    }
+
+    #[test]
+    fn allocations_are_aligned_for_every_size() {
+        for &size in SIZES.iter() {
+            for &align in ALIGNS.iter() {
+                let ptr = __rust_allocate(size, align);
+                assert!(!ptr.is_null());
+                assert!(is_aligned(ptr, align), "size {} align {}", size, align);
+                __rust_deallocate(ptr, size, align);
+            }
+        }
+    }
+
+    #[test]
+    fn reallocations_stay_aligned_and_keep_their_contents() {
+        for &align in ALIGNS.iter() {
+            let mut size = 1;
+            let mut ptr = __rust_allocate(size, align);
+            unsafe { *ptr = 0xA5; }
+
+            for &new_size in SIZES.iter() {
+                ptr = unsafe { __rust_reallocate(ptr, size, new_size, align) };
+                size = new_size;
+                assert!(!ptr.is_null());
+                assert!(is_aligned(ptr, align), "size {} align {}", size, align);
+                assert_eq!(0xA5, unsafe { *ptr });
+            }
+
+            __rust_deallocate(ptr, size, align);
+        }
+    }
+
+    #[test]
+    fn allocations_are_counted_in_the_stats() {
+        let before = stats().allocations;
+        let ptr = __rust_allocate(10, 1);
+        __rust_deallocate(ptr, 10, 1);
+        assert!(stats().allocations > before);
+    }
+
+    #[test]
+    fn the_usable_size_is_never_less_than_the_requested_size() {
+        for &size in SIZES.iter() {
+            assert!(__rust_usable_size(size, 1) >= size);
+        }
+        assert_eq!(MIN_ALIGN, __rust_usable_size(1, 1));
+    }
 }
@@ -0,0 +1,51 @@
+/*
+    Heap memory for newlib's malloc.
+
+    malloc grows its heap with _sbrk. The heap is the region between `_heap_start` and `_heap_end`
+    laid out by the linker script, which makes sure it doesn't overlap bss or the stack.
+*/
+
+use core::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+extern "C" {
+    static _heap_start: u8;
+    static _heap_end: u8;
+
+    // newlib's errno.
+    fn __errno() -> *mut i32;
+}
+
+const ENOMEM: i32 = 12;
+
+// How much of the heap has been handed to malloc.
+static CLAIMED: AtomicUsize = ATOMIC_USIZE_INIT;
+
+fn heap_start() -> usize {
+    unsafe { &_heap_start as *const u8 as usize }
+}
+
+fn heap_end() -> usize {
+    unsafe { &_heap_end as *const u8 as usize }
+}
+
+// The size of the heap.
+pub fn heap_size() -> usize {
+    heap_end() - heap_start()
+}
+
+// Grow (or shrink) the heap by `increment` bytes, returning the previous end of it. malloc only
+// calls this from inside the allocator's critical section.
+#[no_mangle]
+pub extern "C" fn _sbrk(increment: isize) -> *mut u8 {
+    let claimed = CLAIMED.load(Ordering::Relaxed) as isize;
+    let new_claimed = claimed + increment;
+
+    if new_claimed < 0 || new_claimed as usize > heap_size() {
+        // The next increment will blow the heap. Don't allow it.
+        unsafe { *__errno() = ENOMEM; }
+        return !0usize as *mut u8;
+    }
+
+    CLAIMED.store(new_claimed as usize, Ordering::Relaxed);
+    (heap_start() + claimed as usize) as *mut u8
+}
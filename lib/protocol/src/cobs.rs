@@ -0,0 +1,143 @@
+/*
+    Consistent Overhead Byte Stuffing: rewrites a block so it has no zero bytes, so that zeros
+    can mark where frames start and end. Each run of non-zero bytes is prefixed with its length
+    plus one, standing in for the zero after it. A run of 254 bytes is prefixed with 0xFF and has
+    no zero after it. The encoded block is at most one byte longer per 254, plus one.
+*/
+
+// The longest a block of `length` bytes can be once encoded.
+pub fn max_encoded_length(length: usize) -> usize {
+    length + length / 254 + 1
+}
+
+// Encode `data` into `out`, returning the encoded length, or None if `out` is shorter than the
+// longest the encoding could be.
+pub fn encode(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    if out.len() < max_encoded_length(data.len()) {
+        return None;
+    }
+    // Where the length of the current run goes.
+    let mut code_index = 0;
+    let mut code = 1u8;
+    let mut length = 1;
+    for (i, &byte) in data.iter().enumerate() {
+        if byte != 0 {
+            out[length] = byte;
+            length += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            out[code_index] = code;
+            code_index = length;
+            code = 1;
+            // A run of 254 at the very end needs no empty run after it.
+            if byte == 0 || i + 1 < data.len() {
+                length += 1;
+            }
+        }
+    }
+    if code_index < length {
+        out[code_index] = code;
+    }
+    Some(length)
+}
+
+// Decode `data` into `out`, returning the decoded length, or None if `data` isn't a valid encoding
+// or doesn't fit.
+pub fn decode(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let mut index = 0;
+    let mut length = 0;
+    while index < data.len() {
+        let code = data[index] as usize;
+        if code == 0 || index + code > data.len() {
+            return None;
+        }
+        for &byte in &data[index + 1..index + code] {
+            if byte == 0 || length >= out.len() {
+                return None;
+            }
+            out[length] = byte;
+            length += 1;
+        }
+        index += code;
+        // Every run but the last, and the 254 byte runs, stand for a zero after them.
+        if code != 0xFF && index < data.len() {
+            if length >= out.len() {
+                return None;
+            }
+            out[length] = 0;
+            length += 1;
+        }
+    }
+    Some(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    fn encoded(data: &[u8]) -> Vec<u8> {
+        let mut out = [0; 300];
+        let length = encode(data, &mut out).unwrap();
+        assert!(length <= max_encoded_length(data.len()));
+        out[..length].to_vec()
+    }
+
+    fn decoded(data: &[u8]) -> Option<Vec<u8>> {
+        let mut out = [0; 300];
+        decode(data, &mut out).map(|length| out[..length].to_vec())
+    }
+
+    // The examples from the COBS paper and Wikipedia.
+    #[test]
+    fn blocks_are_encoded_without_zeros() {
+        let examples: &[(&[u8], &[u8])] = &[
+            (&[], &[0x01]),
+            (&[0x00], &[0x01, 0x01]),
+            (&[0x00, 0x00], &[0x01, 0x01, 0x01]),
+            (&[0x11, 0x22, 0x00, 0x33], &[0x03, 0x11, 0x22, 0x02, 0x33]),
+            (&[0x11, 0x22, 0x33, 0x44], &[0x05, 0x11, 0x22, 0x33, 0x44]),
+            (&[0x11, 0x00, 0x00, 0x00], &[0x02, 0x11, 0x01, 0x01, 0x01]),
+        ];
+        for &(data, expected) in examples {
+            assert_eq!(expected, &encoded(data)[..]);
+            assert_eq!(Some(data.to_vec()), decoded(expected));
+        }
+    }
+
+    #[test]
+    fn long_runs_are_split() {
+        let run: Vec<u8> = (1..255).map(|b| b as u8).collect();
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&run);
+        assert_eq!(expected, encoded(&run));
+
+        let mut longer = run.clone();
+        longer.push(0xFF);
+        expected.extend_from_slice(&[0x02, 0xFF]);
+        assert_eq!(expected, encoded(&longer));
+        assert_eq!(Some(longer), decoded(&expected));
+
+        let mut with_zero = run.clone();
+        with_zero.push(0x00);
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&run);
+        expected.extend_from_slice(&[0x01, 0x01]);
+        assert_eq!(expected, encoded(&with_zero));
+        assert_eq!(Some(with_zero), decoded(&expected));
+    }
+
+    #[test]
+    fn bad_encodings_are_rejected() {
+        assert_eq!(None, decoded(&[0x00]));
+        assert_eq!(None, decoded(&[0x03, 0x11]));
+        assert_eq!(None, decoded(&[0x03, 0x11, 0x00]));
+    }
+
+    #[test]
+    fn blocks_which_dont_fit_are_rejected() {
+        assert_eq!(None, encode(&[1, 2, 3], &mut [0; 3]));
+        assert_eq!(None, decode(&[0x04, 1, 2, 3], &mut [0; 2]));
+    }
+}
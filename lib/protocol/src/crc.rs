@@ -0,0 +1,42 @@
+/*
+    CRC-16, the same one TivaWare's Crc16 computes (CRC-16/ARC: polynomial 0x8005, reflected,
+    starting from 0). It's worked out bit by bit here so the host can compute it too.
+*/
+
+// The reflected polynomial.
+const POLYNOMIAL: u16 = 0xA001;
+
+// Carry on a CRC over more data. Start from 0, like Crc16.
+pub fn crc16(crc: u16, data: &[u8]) -> u16 {
+    let mut crc = crc;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ POLYNOMIAL } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_check_value_matches_crc16_arc() {
+        assert_eq!(0xBB3D, crc16(0, b"123456789"));
+    }
+
+    #[test]
+    fn crcs_can_be_worked_out_in_pieces() {
+        assert_eq!(crc16(0, b"123456789"), crc16(crc16(0, b"1234"), b"56789"));
+    }
+
+    // From TivaWare's g_pui16Crc16 table, which is the CRC of each single byte.
+    #[test]
+    fn single_bytes_match_the_tivaware_table() {
+        assert_eq!(0xC0C1, crc16(0, &[0x01]));
+        assert_eq!(0xC741, crc16(0, &[0x0B]));
+        assert_eq!(0x0440, crc16(0, &[0x0F]));
+    }
+}
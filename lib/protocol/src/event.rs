@@ -0,0 +1,192 @@
+/*
+    The wire encoding of the firmware's events (see src/event.rs), so they can be sent off the chip
+    and back. An encoded event is:
+
+        [version] [tag] [fields...]
+
+    The version is the encoding's, VERSION below. The tag says which event it is, and the fields
+    follow in the order they're declared, each a little endian u32.
+
+    The encoding is fixed once released. Tags are never changed or reused, and a new event gets the
+    next unused tag. Changing the fields of an event means a new VERSION, and decode must keep
+    reading the old ones. The tests below hold the bytes of every event so that any change to them
+    is caught.
+*/
+
+use message::{Error, Message, Reader, Writer};
+
+// The version of the encoding written.
+pub const VERSION: u8 = 1;
+
+// The longest encoded event: FlashLed.
+pub const MAX_LENGTH: usize = 14;
+
+// The events of the firmware's state machine.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+    ButtonPress,
+    TimeTick,
+    LedTurnOn,
+    LedTurnOff,
+    FlashLed { count: u32, on_time: u32, off_time: u32 },
+    FlashLedDone,
+    LowMemory,
+    SerialReceived,
+    SerialError,
+}
+
+const BUTTON_PRESS: u8 = 0;
+const TIME_TICK: u8 = 1;
+const LED_TURN_ON: u8 = 2;
+const LED_TURN_OFF: u8 = 3;
+const FLASH_LED: u8 = 4;
+const FLASH_LED_DONE: u8 = 5;
+const LOW_MEMORY: u8 = 6;
+const SERIAL_RECEIVED: u8 = 7;
+const SERIAL_ERROR: u8 = 8;
+
+impl Message for Event {
+    fn write(&self, writer: &mut Writer) -> Result<(), Error> {
+        writer.u8(VERSION)?;
+        match *self {
+            Event::ButtonPress => writer.u8(BUTTON_PRESS),
+            Event::TimeTick => writer.u8(TIME_TICK),
+            Event::LedTurnOn => writer.u8(LED_TURN_ON),
+            Event::LedTurnOff => writer.u8(LED_TURN_OFF),
+            Event::FlashLed { count, on_time, off_time } => {
+                writer.u8(FLASH_LED)?;
+                writer.u32(count)?;
+                writer.u32(on_time)?;
+                writer.u32(off_time)
+            },
+            Event::FlashLedDone => writer.u8(FLASH_LED_DONE),
+            Event::LowMemory => writer.u8(LOW_MEMORY),
+            Event::SerialReceived => writer.u8(SERIAL_RECEIVED),
+            Event::SerialError => writer.u8(SERIAL_ERROR),
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Event, Error> {
+        match reader.u8()? {
+            VERSION => {},
+            version => return Err(Error::Version(version)),
+        }
+        match reader.u8()? {
+            BUTTON_PRESS => Ok(Event::ButtonPress),
+            TIME_TICK => Ok(Event::TimeTick),
+            LED_TURN_ON => Ok(Event::LedTurnOn),
+            LED_TURN_OFF => Ok(Event::LedTurnOff),
+            FLASH_LED => {
+                let count = reader.u32()?;
+                let on_time = reader.u32()?;
+                let off_time = reader.u32()?;
+                Ok(Event::FlashLed { count, on_time, off_time })
+            },
+            FLASH_LED_DONE => Ok(Event::FlashLedDone),
+            LOW_MEMORY => Ok(Event::LowMemory),
+            SERIAL_RECEIVED => Ok(Event::SerialReceived),
+            SERIAL_ERROR => Ok(Event::SerialError),
+            _ => Err(Error::Unknown),
+        }
+    }
+}
+
+// Encode an event into `out`, returning how many bytes it took. MAX_LENGTH bytes is always enough.
+pub fn encode(event: &Event, out: &mut [u8]) -> Result<usize, Error> {
+    let mut writer = Writer::new(out);
+    event.write(&mut writer)?;
+    Ok(writer.len())
+}
+
+// Decode an event, which must take all of `bytes`.
+pub fn decode(bytes: &[u8]) -> Result<Event, Error> {
+    ::message::read(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use message::Error;
+
+    // The released encoding of every event. These must never change: a test failing here means
+    // devices and tools already in use would misread the new encoding.
+    fn released(event: Event) -> &'static [u8] {
+        // Matching every event means a new one can't be added without its bytes here.
+        match event {
+            Event::ButtonPress => &[0x01, 0x00],
+            Event::TimeTick => &[0x01, 0x01],
+            Event::LedTurnOn => &[0x01, 0x02],
+            Event::LedTurnOff => &[0x01, 0x03],
+            Event::FlashLed { .. } => &[0x01, 0x04, 0x03, 0x00, 0x00, 0x00, 0x2C, 0x01, 0x00, 0x00,
+                                        0x04, 0x03, 0x02, 0x01],
+            Event::FlashLedDone => &[0x01, 0x05],
+            Event::LowMemory => &[0x01, 0x06],
+            Event::SerialReceived => &[0x01, 0x07],
+            Event::SerialError => &[0x01, 0x08],
+        }
+    }
+
+    const EVENTS: &[Event] = &[
+        Event::ButtonPress,
+        Event::TimeTick,
+        Event::LedTurnOn,
+        Event::LedTurnOff,
+        Event::FlashLed { count: 3, on_time: 300, off_time: 0x01020304 },
+        Event::FlashLedDone,
+        Event::LowMemory,
+        Event::SerialReceived,
+        Event::SerialError,
+    ];
+
+    #[test]
+    fn events_encode_to_their_released_bytes() {
+        for &event in EVENTS {
+            let mut out = [0; MAX_LENGTH];
+            let length = encode(&event, &mut out).unwrap();
+            assert_eq!(released(event), &out[..length], "{:?}", event);
+        }
+    }
+
+    #[test]
+    fn released_bytes_decode_to_their_events() {
+        for &event in EVENTS {
+            assert_eq!(Ok(event), decode(released(event)));
+        }
+    }
+
+    #[test]
+    fn every_tag_is_different() {
+        for &event in EVENTS {
+            let tag = released(event)[1];
+            assert_eq!(1, EVENTS.iter().filter(|&&other| released(other)[1] == tag).count());
+        }
+    }
+
+    #[test]
+    fn the_longest_event_fits() {
+        let longest = EVENTS.iter().map(|&event| released(event).len()).max();
+        assert_eq!(Some(MAX_LENGTH), longest);
+        let mut out = [0; MAX_LENGTH - 1];
+        assert_eq!(Err(Error::Length), encode(&EVENTS[4], &mut out));
+    }
+
+    #[test]
+    fn field_values_are_kept() {
+        for &(count, on_time, off_time) in &[(0, 0, 0), (1, 2, 3), (0xFFFFFFFF, 0x80000000, 0x7FFFFFFF)] {
+            let event = Event::FlashLed { count, on_time, off_time };
+            let mut out = [0; MAX_LENGTH];
+            let length = encode(&event, &mut out).unwrap();
+            assert_eq!(Ok(event), decode(&out[..length]));
+        }
+    }
+
+    #[test]
+    fn other_encodings_are_rejected() {
+        // A later version, an unused tag, missing and extra bytes.
+        assert_eq!(Err(Error::Version(2)), decode(&[0x02, 0x00]));
+        assert_eq!(Err(Error::Unknown), decode(&[0x01, 0x09]));
+        assert_eq!(Err(Error::Length), decode(&[0x01]));
+        assert_eq!(Err(Error::Length), decode(&[0x01, 0x04, 0x03, 0x00, 0x00, 0x00]));
+        assert_eq!(Err(Error::Length), decode(&[0x01, 0x00, 0x00]));
+    }
+}
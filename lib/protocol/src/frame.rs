@@ -0,0 +1,304 @@
+/*
+    Frames, which carry one message each between the host and the device. Before encoding a frame
+    is:
+
+        [version] [id] [message...] [CRC-16, little endian]
+
+    The version is the protocol's, so either end can tell when it's talking to something newer. The
+    host picks the id for each request and the device's response has the same one. The CRC covers
+    everything before it.
+
+    The frame is COBS encoded so it has no zeros, and sent between two zeros. The serial port also
+    carries the shell and log text, which has no zeros, so anything outside the zeros isn't part of
+    a frame.
+
+    Binary log records (see src/log.rs) are framed the same way, so they don't upset the frames
+    around them, but start with LOG_RECORD where a frame has its version.
+*/
+
+use cobs;
+use crc::crc16;
+use message::{self, Message, Writer};
+
+// The protocol version this crate speaks.
+pub const VERSION: u8 = 1;
+
+// The first byte of a frame holding a binary log record instead of a message. No version will
+// ever be this high.
+pub const LOG_RECORD: u8 = 0xFF;
+
+// The longest message.
+pub const MAX_MESSAGE: usize = 32;
+
+// The longest frame before and after encoding, the encoded one with its zeros.
+const MAX_FRAME: usize = MAX_MESSAGE + 4;
+pub const MAX_ENCODED: usize = MAX_FRAME + MAX_FRAME / 254 + 3;
+
+// The bytes around the message: the version and id before, and the CRC after.
+const OVERHEAD: usize = 4;
+
+// A frame as received.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Frame<'a> {
+    pub version: u8,
+    pub id: u8,
+    pub message: &'a [u8],
+}
+
+// Write the frame for a message into `out`, returning how many bytes it took. `out` must hold
+// MAX_ENCODED bytes.
+pub fn encode<M: Message>(version: u8, id: u8, message: &M, out: &mut [u8]) -> Result<usize, message::Error> {
+    let mut frame = [0; MAX_FRAME];
+    let length = {
+        let mut writer = Writer::new(&mut frame[..MAX_FRAME - 2]);
+        writer.u8(version)?;
+        writer.u8(id)?;
+        message.write(&mut writer)?;
+        writer.len()
+    };
+    if out.len() < MAX_ENCODED {
+        return Err(message::Error::Length);
+    }
+    seal(&mut frame, length, out).ok_or(message::Error::Length)
+}
+
+// How long a frame of `length` bytes (without its CRC) can be once sealed.
+pub fn encoded_length(length: usize) -> usize {
+    cobs::max_encoded_length(length + 2) + 2
+}
+
+// Add the CRC after the first `length` bytes of `frame`, which must have room for it, and write
+// the frame into `out` encoded and between zeros. Returns how many bytes that took, or None if
+// `out` is shorter than encoded_length(length).
+pub fn seal(frame: &mut [u8], length: usize, out: &mut [u8]) -> Option<usize> {
+    if frame.len() < length + 2 || out.len() < encoded_length(length) {
+        return None;
+    }
+    let crc = crc16(0, &frame[..length]);
+    frame[length] = crc as u8;
+    frame[length + 1] = (crc >> 8) as u8;
+
+    out[0] = 0;
+    cobs::encode(&frame[..length + 2], &mut out[1..]).map(|encoded| {
+        out[encoded + 1] = 0;
+        encoded + 2
+    })
+}
+
+// What a byte pushed into a Deframer was.
+#[derive(PartialEq, Debug)]
+pub enum Status<'a> {
+    // Outside a frame, so for something else.
+    Unframed,
+    // Part of a frame which isn't finished yet.
+    Pending,
+    // The end of a frame.
+    Frame(Frame<'a>),
+    // The end of a frame which was too long, badly encoded or had the wrong CRC.
+    Dropped,
+    // The end of a binary log record, which is skipped rather than checked.
+    LogRecord,
+}
+
+// Picks frames out of the bytes received.
+pub struct Deframer {
+    encoded: [u8; MAX_ENCODED],
+    length: usize,
+    in_frame: bool,
+    // The frame started with LOG_RECORD, so the rest of it is skipped.
+    log_record: bool,
+    // The frame is too long, so the rest of it is skipped and then dropped.
+    too_long: bool,
+    decoded: [u8; MAX_FRAME],
+}
+
+impl Deframer {
+    pub fn new() -> Deframer {
+        Deframer {
+            encoded: [0; MAX_ENCODED],
+            length: 0,
+            in_frame: false,
+            log_record: false,
+            too_long: false,
+            decoded: [0; MAX_FRAME],
+        }
+    }
+
+    pub fn push<'a>(&'a mut self, byte: u8) -> Status<'a> {
+        if !self.in_frame {
+            if byte != 0 {
+                return Status::Unframed;
+            }
+            self.in_frame = true;
+            self.log_record = false;
+            self.too_long = false;
+            self.length = 0;
+            return Status::Pending;
+        }
+        if byte != 0 {
+            if self.log_record || self.too_long {
+                return Status::Pending;
+            }
+            // The second encoded byte is the first of the frame, unless the first said it's zero.
+            if self.length == 1 && self.encoded[0] != 1 && byte == LOG_RECORD {
+                self.log_record = true;
+                return Status::Pending;
+            }
+            if self.length == self.encoded.len() {
+                // Too long to be a frame. The rest of it is skipped up to the zero which ends it.
+                self.too_long = true;
+                return Status::Pending;
+            }
+            self.encoded[self.length] = byte;
+            self.length += 1;
+            return Status::Pending;
+        }
+        if self.length == 0 && !self.too_long {
+            // Two zeros in a row: the first was the end of something that wasn't a frame.
+            return Status::Pending;
+        }
+        self.in_frame = false;
+        if self.log_record {
+            return Status::LogRecord;
+        }
+        if self.too_long {
+            return Status::Dropped;
+        }
+        self.decode()
+    }
+
+    fn decode<'a>(&'a mut self) -> Status<'a> {
+        let length = match cobs::decode(&self.encoded[..self.length], &mut self.decoded) {
+            Some(length) if length > OVERHEAD => length,
+            _ => return Status::Dropped,
+        };
+        let crc = self.decoded[length - 2] as u16 | (self.decoded[length - 1] as u16) << 8;
+        if crc16(0, &self.decoded[..length - 2]) != crc {
+            return Status::Dropped;
+        }
+        Status::Frame(Frame {
+            version: self.decoded[0],
+            id: self.decoded[1],
+            message: &self.decoded[2..length - 2],
+        })
+    }
+}
+
+impl Default for Deframer {
+    fn default() -> Deframer {
+        Deframer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use message::{self, Request};
+    use std::vec::Vec;
+
+    fn encoded(id: u8, request: Request) -> Vec<u8> {
+        let mut out = [0; MAX_ENCODED];
+        let length = encode(VERSION, id, &request, &mut out).unwrap();
+        out[..length].to_vec()
+    }
+
+    // The requests in the frames ended by `bytes`, and how many bytes were outside frames.
+    fn deframed(bytes: &[u8]) -> (Vec<Result<Request, message::Error>>, usize, usize) {
+        let mut deframer = Deframer::new();
+        let mut requests = Vec::new();
+        let mut unframed = 0;
+        let mut dropped = 0;
+        for &byte in bytes {
+            match deframer.push(byte) {
+                Status::Unframed => unframed += 1,
+                Status::Pending => {},
+                Status::Frame(frame) => {
+                    assert_eq!(VERSION, frame.version);
+                    requests.push(message::read(frame.message));
+                },
+                Status::Dropped => dropped += 1,
+                Status::LogRecord => {},
+            }
+        }
+        (requests, unframed, dropped)
+    }
+
+    #[test]
+    fn frames_are_zero_delimited_cobs_with_a_crc() {
+        // Version 1, id 0, Ping, then the CRC 0x0090, whose zero is encoded away.
+        assert_eq!(vec![0x00, 0x02, 0x01, 0x03, 0x01, 0x90, 0x01, 0x00], encoded(0, Request::Ping));
+    }
+
+    #[test]
+    fn frames_are_picked_out_from_text() {
+        let mut bytes = b"log line\r\n".to_vec();
+        bytes.extend(encoded(5, Request::Ping));
+        bytes.extend_from_slice(b"> ");
+        bytes.extend(encoded(6, Request::SetParameter(message::Parameter::WaitTime, 0)));
+        let (requests, unframed, dropped) = deframed(&bytes);
+        assert_eq!(vec![Ok(Request::Ping), Ok(Request::SetParameter(message::Parameter::WaitTime, 0))], requests);
+        assert_eq!(12, unframed);
+        assert_eq!(0, dropped);
+    }
+
+    #[test]
+    fn corrupted_frames_are_dropped() {
+        let mut bytes = encoded(1, Request::GetParameter(message::Parameter::FlashCount));
+        bytes[3] ^= 0x10;
+        let (requests, _, dropped) = deframed(&bytes);
+        assert!(requests.is_empty());
+        assert_eq!(1, dropped);
+    }
+
+    #[test]
+    fn log_records_between_frames_are_skipped() {
+        // Longer than a frame can be, and with zeros in it before encoding.
+        let mut record = vec![LOG_RECORD];
+        record.extend((0..60).map(|i| i % 3));
+        let length = record.len();
+        record.extend_from_slice(&[0, 0]);
+        let mut sealed = [0; 80];
+        let sealed_length = seal(&mut record, length, &mut sealed).unwrap();
+
+        let mut bytes = sealed[..sealed_length].to_vec();
+        bytes.extend(encoded(2, Request::Ping));
+        bytes.extend_from_slice(&sealed[..sealed_length]);
+        bytes.extend_from_slice(b"ok");
+
+        let mut deframer = Deframer::new();
+        let statuses: Vec<_> = bytes.iter().filter_map(|&byte| match deframer.push(byte) {
+            Status::Frame(frame) => Some(frame.id),
+            Status::LogRecord => Some(0xFF),
+            _ => None,
+        }).collect();
+        assert_eq!(vec![0xFF, 2, 0xFF], statuses);
+    }
+
+    #[test]
+    fn sealed_frames_are_checked_like_encoded_ones() {
+        let mut frame = [VERSION, 3, 0x01, 0, 0];
+        let mut out = [0; MAX_ENCODED];
+        let length = seal(&mut frame, 3, &mut out).unwrap();
+        assert_eq!(encoded(3, Request::Ping), out[..length].to_vec());
+        // The frame needs room for its CRC, and `out` for the encoding.
+        assert_eq!(None, seal(&mut frame[..4], 3, &mut out));
+        assert_eq!(None, seal(&mut frame, 3, &mut out[..encoded_length(3) - 1]));
+    }
+
+    #[test]
+    fn frames_which_are_too_long_are_dropped() {
+        let mut bytes = vec![0];
+        bytes.extend((0..MAX_ENCODED + 1).map(|_| 0x55));
+        let (_, unframed, dropped) = deframed(&bytes);
+        assert_eq!(0, dropped);
+        assert_eq!(0, unframed);
+
+        // It's dropped at the zero which ends it, and the text after that reaches the shell.
+        bytes.push(0);
+        bytes.extend(b"help\r");
+        let (requests, unframed, dropped) = deframed(&bytes);
+        assert!(requests.is_empty());
+        assert_eq!(1, dropped);
+        assert_eq!(5, unframed);
+    }
+}
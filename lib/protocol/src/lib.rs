@@ -0,0 +1,28 @@
+/*
+    The binary protocol between the device and a host, for test rigs and other tools which need
+    to drive the firmware without a person at the shell.
+
+    The host sends requests and the device answers each with a response. Every message travels in
+    a frame (see frame.rs) with an id, which the response shares with its request, the protocol
+    version and a CRC. Frames are sent on the serial port alongside the shell and log text.
+
+    Both ends use this crate: the firmware through Server, and tools/device_client on the host.
+*/
+
+#![no_std]
+
+#[cfg(test)]
+#[macro_use]
+extern crate std;
+
+pub mod cobs;
+pub mod crc;
+pub mod event;
+pub mod frame;
+pub mod message;
+pub mod server;
+
+pub use frame::VERSION;
+pub use event::Event;
+pub use message::{ErrorCode, Parameter, Request, Response};
+pub use server::{Handler, Server};
@@ -0,0 +1,300 @@
+/*
+    The messages: requests from the host and the device's responses. Each is a kind byte followed
+    by its fields. Numbers are little endian u32s, parameters are a byte, and events are in their
+    own encoding (see event.rs).
+*/
+
+use event::Event;
+
+// Why a message couldn't be read or written.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Error {
+    // The message ended early, or didn't fit.
+    Length,
+    // An unknown message kind, or an unknown event or parameter.
+    Unknown,
+    // An encoding version this crate can't read.
+    Version(u8),
+}
+
+// The settings of the firmware's state machine. Times are in ticks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Parameter {
+    FlashCount = 0,
+    LedOnTime = 1,
+    LedOffTime = 2,
+    WaitTime = 3,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Request {
+    Ping,
+    RaiseEvent(Event),
+    GetParameter(Parameter),
+    SetParameter(Parameter, u32),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Response {
+    Pong,
+    // The request was carried out.
+    Done,
+    Value(u32),
+    Error(ErrorCode),
+}
+
+// Why the device didn't carry out a request.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ErrorCode {
+    // The frame's protocol version, or the version of an event in it, isn't one the device
+    // understands.
+    UnsupportedVersion = 0,
+    // The device doesn't know the request, or its event or parameter.
+    UnknownRequest = 1,
+    // The request was the wrong length.
+    BadRequest = 2,
+    // The value given can't be used.
+    BadValue = 3,
+}
+
+const PING: u8 = 0x01;
+const RAISE_EVENT: u8 = 0x02;
+const GET_PARAMETER: u8 = 0x03;
+const SET_PARAMETER: u8 = 0x04;
+
+const PONG: u8 = 0x81;
+const DONE: u8 = 0x82;
+const VALUE: u8 = 0x83;
+const ERROR: u8 = 0xFF;
+
+// Something which goes in a frame.
+pub trait Message: Sized {
+    fn write(&self, writer: &mut Writer) -> Result<(), Error>;
+    fn read(reader: &mut Reader) -> Result<Self, Error>;
+}
+
+impl Message for Request {
+    fn write(&self, writer: &mut Writer) -> Result<(), Error> {
+        match *self {
+            Request::Ping => writer.u8(PING),
+            Request::RaiseEvent(event) => {
+                writer.u8(RAISE_EVENT)?;
+                event.write(writer)
+            },
+            Request::GetParameter(parameter) => {
+                writer.u8(GET_PARAMETER)?;
+                writer.u8(parameter as u8)
+            },
+            Request::SetParameter(parameter, value) => {
+                writer.u8(SET_PARAMETER)?;
+                writer.u8(parameter as u8)?;
+                writer.u32(value)
+            },
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Request, Error> {
+        match reader.u8()? {
+            PING => Ok(Request::Ping),
+            RAISE_EVENT => Ok(Request::RaiseEvent(Event::read(reader)?)),
+            GET_PARAMETER => Ok(Request::GetParameter(Parameter::read(reader)?)),
+            SET_PARAMETER => {
+                let parameter = Parameter::read(reader)?;
+                Ok(Request::SetParameter(parameter, reader.u32()?))
+            },
+            _ => Err(Error::Unknown),
+        }
+    }
+}
+
+impl Message for Response {
+    fn write(&self, writer: &mut Writer) -> Result<(), Error> {
+        match *self {
+            Response::Pong => writer.u8(PONG),
+            Response::Done => writer.u8(DONE),
+            Response::Value(value) => {
+                writer.u8(VALUE)?;
+                writer.u32(value)
+            },
+            Response::Error(code) => {
+                writer.u8(ERROR)?;
+                writer.u8(code as u8)
+            },
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Response, Error> {
+        match reader.u8()? {
+            PONG => Ok(Response::Pong),
+            DONE => Ok(Response::Done),
+            VALUE => Ok(Response::Value(reader.u32()?)),
+            ERROR => {
+                let code = match reader.u8()? {
+                    0 => ErrorCode::UnsupportedVersion,
+                    1 => ErrorCode::UnknownRequest,
+                    2 => ErrorCode::BadRequest,
+                    3 => ErrorCode::BadValue,
+                    _ => return Err(Error::Unknown),
+                };
+                Ok(Response::Error(code))
+            },
+            _ => Err(Error::Unknown),
+        }
+    }
+}
+
+impl Message for Parameter {
+    fn write(&self, writer: &mut Writer) -> Result<(), Error> {
+        writer.u8(*self as u8)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Parameter, Error> {
+        match reader.u8()? {
+            0 => Ok(Parameter::FlashCount),
+            1 => Ok(Parameter::LedOnTime),
+            2 => Ok(Parameter::LedOffTime),
+            3 => Ok(Parameter::WaitTime),
+            _ => Err(Error::Unknown),
+        }
+    }
+}
+
+// Writes a message's bytes into a buffer.
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    length: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Writer<'a> {
+        Writer { buffer, length: 0 }
+    }
+
+    // The bytes written so far.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn u8(&mut self, value: u8) -> Result<(), Error> {
+        self.bytes(&[value])
+    }
+
+    pub fn u32(&mut self, value: u32) -> Result<(), Error> {
+        self.bytes(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8])
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.length + bytes.len();
+        if end > self.buffer.len() {
+            return Err(Error::Length);
+        }
+        self.buffer[self.length..end].copy_from_slice(bytes);
+        self.length = end;
+        Ok(())
+    }
+}
+
+// Reads a message's fields from its bytes.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes }
+    }
+
+    // Whether every byte has been read.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        match self.bytes.split_first() {
+            Some((&byte, rest)) => {
+                self.bytes = rest;
+                Ok(byte)
+            },
+            None => Err(Error::Length),
+        }
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Error> {
+        if self.bytes.len() < 4 {
+            return Err(Error::Length);
+        }
+        let b = self.bytes;
+        self.bytes = &b[4..];
+        Ok(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+    }
+}
+
+// Read a whole message, which must use every byte.
+pub fn read<M: Message>(bytes: &[u8]) -> Result<M, Error> {
+    let mut reader = Reader::new(bytes);
+    let message = M::read(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(Error::Length);
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written<M: Message>(message: &M) -> ([u8; 16], usize) {
+        let mut buffer = [0; 16];
+        let length = {
+            let mut writer = Writer::new(&mut buffer);
+            message.write(&mut writer).unwrap();
+            writer.len()
+        };
+        (buffer, length)
+    }
+
+    fn round_trip<M: Message + PartialEq + ::core::fmt::Debug>(message: M, expected: &[u8]) {
+        let (buffer, length) = written(&message);
+        assert_eq!(expected, &buffer[..length]);
+        assert_eq!(Ok(message), read(expected));
+    }
+
+    #[test]
+    fn requests_are_written_and_read() {
+        round_trip(Request::Ping, &[0x01]);
+        round_trip(Request::RaiseEvent(Event::ButtonPress), &[0x02, 0x01, 0x00]);
+        round_trip(Request::RaiseEvent(Event::FlashLed { count: 3, on_time: 4, off_time: 0x01020304 }),
+                   &[0x02, 0x01, 0x04, 3, 0, 0, 0, 4, 0, 0, 0, 0x04, 0x03, 0x02, 0x01]);
+        round_trip(Request::GetParameter(Parameter::WaitTime), &[0x03, 0x03]);
+        round_trip(Request::SetParameter(Parameter::LedOnTime, 300), &[0x04, 0x01, 0x2C, 0x01, 0, 0]);
+    }
+
+    #[test]
+    fn responses_are_written_and_read() {
+        round_trip(Response::Pong, &[0x81]);
+        round_trip(Response::Done, &[0x82]);
+        round_trip(Response::Value(7), &[0x83, 7, 0, 0, 0]);
+        round_trip(Response::Error(ErrorCode::BadValue), &[0xFF, 0x03]);
+    }
+
+    #[test]
+    fn bad_messages_are_rejected() {
+        assert_eq!(Err(Error::Unknown), read::<Request>(&[0x7F]));
+        assert_eq!(Err(Error::Unknown), read::<Request>(&[0x02, 0x01, 0x20]));
+        assert_eq!(Err(Error::Version(9)), read::<Request>(&[0x02, 0x09, 0x00]));
+        assert_eq!(Err(Error::Length), read::<Request>(&[]));
+        assert_eq!(Err(Error::Length), read::<Request>(&[0x04, 0x01, 0x2C]));
+        assert_eq!(Err(Error::Length), read::<Request>(&[0x01, 0x00]));
+    }
+
+    #[test]
+    fn messages_which_dont_fit_are_not_written() {
+        let mut buffer = [0; 3];
+        let mut writer = Writer::new(&mut buffer);
+        assert_eq!(Err(Error::Length), Request::SetParameter(Parameter::FlashCount, 1).write(&mut writer));
+    }
+}
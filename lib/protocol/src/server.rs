@@ -0,0 +1,151 @@
+/*
+    The device's end: takes the bytes received, answers the requests framed in them with a
+    Handler, and passes back everything else.
+*/
+
+use frame::{self, Deframer, Frame, Status, MAX_ENCODED, VERSION};
+use message::{self, ErrorCode, Request, Response};
+
+// Carries out requests.
+pub trait Handler {
+    fn handle(&mut self, request: Request) -> Response;
+}
+
+pub struct Server {
+    deframer: Deframer,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server { deframer: Deframer::new() }
+    }
+
+    // Take a byte from the host, calling `send` with the response frame when it finishes a
+    // request. Returns false if the byte wasn't part of a frame, so is for something else.
+    pub fn receive<H, S>(&mut self, byte: u8, handler: &mut H, send: &mut S) -> bool
+        where H: Handler, S: FnMut(&[u8])
+    {
+        let (id, response) = match self.deframer.push(byte) {
+            Status::Unframed => return false,
+            Status::Pending | Status::Dropped | Status::LogRecord => return true,
+            Status::Frame(frame) => (frame.id, respond(frame, handler)),
+        };
+        let mut out = [0; MAX_ENCODED];
+        // Every response fits in a frame.
+        if let Ok(length) = frame::encode(VERSION, id, &response, &mut out) {
+            send(&out[..length]);
+        }
+        true
+    }
+}
+
+impl Default for Server {
+    fn default() -> Server {
+        Server::new()
+    }
+}
+
+fn respond<H: Handler>(frame: Frame, handler: &mut H) -> Response {
+    if frame.version != VERSION {
+        return Response::Error(ErrorCode::UnsupportedVersion);
+    }
+    match message::read(frame.message) {
+        Ok(request) => handler.handle(request),
+        Err(message::Error::Unknown) => Response::Error(ErrorCode::UnknownRequest),
+        Err(message::Error::Length) => Response::Error(ErrorCode::BadRequest),
+        // An event in an encoding the device doesn't know.
+        Err(message::Error::Version(_)) => Response::Error(ErrorCode::UnsupportedVersion),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use event::Event;
+    use message::Parameter;
+    use std::vec::Vec;
+
+    struct Recorder {
+        requests: Vec<Request>,
+    }
+
+    impl Handler for Recorder {
+        fn handle(&mut self, request: Request) -> Response {
+            self.requests.push(request);
+            match request {
+                Request::Ping => Response::Pong,
+                Request::GetParameter(_) => Response::Value(4),
+                _ => Response::Done,
+            }
+        }
+    }
+
+    // Feed bytes to a server, returning the requests handled, the responses (with their ids) and
+    // the bytes passed back.
+    fn serve(bytes: &[u8]) -> (Vec<Request>, Vec<(u8, Response)>, Vec<u8>) {
+        let mut server = Server::new();
+        let mut handler = Recorder { requests: Vec::new() };
+        let mut sent = Vec::new();
+        let mut passed = Vec::new();
+        for &byte in bytes {
+            if !server.receive(byte, &mut handler, &mut |frame| sent.extend_from_slice(frame)) {
+                passed.push(byte);
+            }
+        }
+
+        let mut deframer = Deframer::new();
+        let mut responses = Vec::new();
+        for byte in sent {
+            if let Status::Frame(frame) = deframer.push(byte) {
+                assert_eq!(VERSION, frame.version);
+                responses.push((frame.id, message::read(frame.message).unwrap()));
+            }
+        }
+        (handler.requests, responses, passed)
+    }
+
+    fn framed(version: u8, id: u8, request: Request) -> Vec<u8> {
+        let mut out = [0; MAX_ENCODED];
+        let length = frame::encode(version, id, &request, &mut out).unwrap();
+        out[..length].to_vec()
+    }
+
+    #[test]
+    fn requests_are_answered_with_their_id() {
+        let mut bytes = framed(VERSION, 9, Request::Ping);
+        bytes.extend(framed(VERSION, 10, Request::RaiseEvent(Event::LedTurnOn)));
+        bytes.extend(framed(VERSION, 11, Request::GetParameter(Parameter::LedOffTime)));
+        let (requests, responses, _) = serve(&bytes);
+        assert_eq!(vec![Request::Ping, Request::RaiseEvent(Event::LedTurnOn),
+                        Request::GetParameter(Parameter::LedOffTime)], requests);
+        assert_eq!(vec![(9, Response::Pong), (10, Response::Done), (11, Response::Value(4))], responses);
+    }
+
+    #[test]
+    fn bytes_outside_frames_are_passed_back() {
+        let mut bytes = b"heap\r".to_vec();
+        bytes.extend(framed(VERSION, 1, Request::Ping));
+        bytes.extend_from_slice(b"help\r");
+        let (_, responses, passed) = serve(&bytes);
+        assert_eq!(vec![(1, Response::Pong)], responses);
+        assert_eq!(b"heap\rhelp\r".to_vec(), passed);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let (requests, responses, _) = serve(&framed(VERSION + 1, 3, Request::Ping));
+        assert!(requests.is_empty());
+        assert_eq!(vec![(3, Response::Error(ErrorCode::UnsupportedVersion))], responses);
+    }
+
+    #[test]
+    fn bad_requests_are_refused() {
+        // A response sent as a request.
+        let mut bytes = framed(VERSION, 1, Request::Ping);
+        let mut out = [0; MAX_ENCODED];
+        let length = frame::encode(VERSION, 2, &Response::Done, &mut out).unwrap();
+        bytes.extend_from_slice(&out[..length]);
+        let (_, responses, _) = serve(&bytes);
+        assert_eq!(vec![(1, Response::Pong), (2, Response::Error(ErrorCode::UnknownRequest))], responses);
+    }
+}
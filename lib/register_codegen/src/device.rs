@@ -0,0 +1,485 @@
+/*
+    The registers and bit fields of a device, worked out from the header's blocks.
+
+    Each register block becomes a peripheral named after the prefix its registers share
+    (GPIO_PORTF_DIR_R and GPIO_PORTF_DATA_R are the DIR and DATA registers of GPIO_PORTF). Each
+    bit field block becomes a group of fields, which is attached to every register it describes:
+    SYSCTL_RCGCGPIO to the RCGCGPIO register of SYSCTL, and the shared GPIO_O_IM to the IM
+    register of every GPIO port.
+
+    Within a group, `X_M` is the mask of a multi-bit field X (with its shift in `X_S`), and
+    `X_<something>` inside X's mask is one of X's values. Anything else with bits set is a field
+    of its own, usually a single bit.
+*/
+
+use header::{self, Block, Define};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Device {
+    pub peripherals: Vec<Peripheral>,
+    pub groups: Vec<FieldGroup>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Peripheral {
+    // The prefix of the register names, like GPIO_PORTF.
+    pub name: String,
+    // The kind of peripheral, shared by every instance, like GPIO.
+    pub kind: String,
+    pub description: String,
+    pub registers: Vec<Register>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Register {
+    // The name within the peripheral, like DIR.
+    pub name: String,
+    pub address: u32,
+    // In bits: 8, 16 or 32.
+    pub width: u8,
+    // The index into Device::groups of the register's bit fields.
+    pub group: Option<usize>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct FieldGroup {
+    // The kind of peripheral and the register name, like GPIO and IM.
+    pub kind: String,
+    pub register: String,
+    pub fields: Vec<Field>,
+    pub values: Vec<Value>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Field {
+    pub name: String,
+    pub mask: u32,
+    pub shift: u8,
+    pub description: String,
+}
+
+// A named value of a multi-bit field, already shifted down to the bottom of the field.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Value {
+    pub name: String,
+    pub field: String,
+    pub value: u32,
+    pub description: String,
+}
+
+// Bit field blocks name some peripherals differently from their registers.
+const KIND_ALIASES: &[(&str, &str)] = &[
+    ("WDT", "WATCHDOG"),
+    ("TIMER", "WTIMER"),
+];
+
+impl Device {
+    pub fn from_header(text: &str) -> Device {
+        let blocks = header::blocks(text);
+        let mut device = Device { peripherals: Vec::new(), groups: Vec::new() };
+
+        for block in &blocks {
+            if let Block::Registers(ref title, ref defines) = *block {
+                device.add_peripheral(title, defines);
+            }
+        }
+        for block in &blocks {
+            if let Block::Fields(ref name, ref defines) = *block {
+                device.add_fields(name, defines);
+            }
+        }
+        device.attach_groups();
+        device
+    }
+
+    pub fn peripheral(&self, name: &str) -> Option<&Peripheral> {
+        self.peripherals.iter().find(|p| p.name == name)
+    }
+
+    pub fn group(&self, kind: &str, register: &str) -> Option<&FieldGroup> {
+        self.groups.iter().find(|g| g.kind == kind && g.register == register)
+    }
+
+    #[allow(deprecated)]
+    fn add_peripheral(&mut self, title: &str, defines: &[Define]) {
+        let registers: Vec<(String, u32, u8)> = defines.iter()
+            .filter(|d| d.name.ends_with("_R"))
+            .filter_map(|d| {
+                header::register_address(&d.value)
+                    .map(|(address, width)| (d.name[..d.name.len() - 2].to_string(), address, width))
+            })
+            .collect();
+        if registers.is_empty() {
+            return;
+        }
+
+        let prefix = common_prefix(registers.iter().map(|r| r.0.as_str()));
+        let name = prefix.trim_right_matches('_').to_string();
+        let kind = name.split('_').next().unwrap().trim_right_matches(|c: char| c.is_digit(10)).to_string();
+
+        let registers = registers.into_iter()
+            .map(|(full_name, address, width)| Register {
+                name: full_name[prefix.len()..].to_string(),
+                address,
+                width,
+                group: None,
+            });
+
+        match self.peripherals.iter().position(|p| p.name == name) {
+            Some(i) => self.peripherals[i].registers.extend(registers),
+            None => self.peripherals.push(Peripheral {
+                name,
+                kind,
+                description: title.to_string(),
+                registers: registers.collect(),
+            }),
+        }
+    }
+
+    fn add_fields(&mut self, name: &str, defines: &[Define]) {
+        // Shared blocks are named KIND_O_REGISTER, and their defines KIND_REGISTER_FIELD.
+        let (kind, register, prefix) = match name.find("_O_") {
+            Some(at) => {
+                let kind = &name[..at];
+                let register = &name[at + 3..];
+                (kind.to_string(), register.to_string(), format!("{}_{}_", kind, register))
+            }
+            None => {
+                // Otherwise the block is named after the register itself.
+                let found = self.peripherals.iter()
+                    .filter_map(|p| {
+                        p.registers.iter()
+                            .find(|r| format!("{}_{}", p.name, r.name) == name)
+                            .map(|r| (p.kind.clone(), r.name.clone()))
+                    })
+                    .next();
+                match found {
+                    Some((kind, register)) => (kind, register, format!("{}_", name)),
+                    None => return,
+                }
+            }
+        };
+
+        let defines: Vec<(String, u32, String)> = defines.iter()
+            .filter(|d| d.name.starts_with(&prefix) && d.name.len() > prefix.len())
+            .filter_map(|d| {
+                header::number(&d.value)
+                    .map(|n| (d.name[prefix.len()..].to_string(), n, d.description.clone()))
+            })
+            .collect();
+        let (fields, values) = classify(&defines);
+        if fields.is_empty() {
+            return;
+        }
+
+        match self.groups.iter().position(|g| g.kind == kind && g.register == register) {
+            Some(i) => {
+                let group = &mut self.groups[i];
+                for field in fields {
+                    if !group.fields.iter().any(|f| f.name == field.name) {
+                        group.fields.push(field);
+                    }
+                }
+                for value in values {
+                    if !group.values.iter().any(|v| v.name == value.name) {
+                        group.values.push(value);
+                    }
+                }
+            }
+            None => self.groups.push(FieldGroup {
+                kind,
+                register,
+                fields,
+                values,
+            }),
+        }
+    }
+
+    fn attach_groups(&mut self) {
+        let groups = &self.groups;
+        for peripheral in self.peripherals.iter_mut() {
+            let kind = &peripheral.kind;
+            for register in peripheral.registers.iter_mut() {
+                register.group = groups.iter().position(|g| {
+                    g.register == register.name && kinds_match(&g.kind, kind)
+                });
+            }
+        }
+    }
+}
+
+fn kinds_match(group_kind: &str, peripheral_kind: &str) -> bool {
+    group_kind == peripheral_kind ||
+        KIND_ALIASES.iter().any(|&(g, p)| g == group_kind && p == peripheral_kind)
+}
+
+// Sort a group's defines (with the group prefix removed) into fields and values.
+fn classify(defines: &[(String, u32, String)]) -> (Vec<Field>, Vec<Value>) {
+    let mut fields: Vec<Field> = Vec::new();
+    let mut values = Vec::new();
+
+    // Multi-bit fields first, since they decide which of the rest are values.
+    for &(ref name, mask, ref description) in defines {
+        if name.ends_with("_M") && mask != 0 {
+            let field = &name[..name.len() - 2];
+            let shift_name = format!("{}_S", field);
+            let shift = defines.iter()
+                .find(|d| d.0 == shift_name)
+                .map(|d| d.1 as u8)
+                .unwrap_or(mask.trailing_zeros() as u8);
+            fields.push(Field {
+                name: field.to_string(),
+                mask,
+                shift,
+                description: description.clone(),
+            });
+        }
+    }
+
+    for &(ref name, value, ref description) in defines {
+        let is_shift = name.ends_with("_S") && fields.iter().any(|f| f.name == name[..name.len() - 2]);
+        if name.ends_with("_M") || is_shift {
+            continue;
+        }
+
+        // The longest multi-bit field this could be a value of.
+        let parent = fields.iter()
+            .filter(|f| f.mask.count_ones() > 1 && name.starts_with(&format!("{}_", f.name)))
+            .filter(|f| value & !f.mask == 0)
+            .max_by_key(|f| f.name.len())
+            .cloned();
+        match parent {
+            Some(field) => values.push(Value {
+                name: name.clone(),
+                field: field.name.clone(),
+                value: (value & field.mask) >> field.shift,
+                description: description.clone(),
+            }),
+            None => {
+                if value != 0 && !fields.iter().any(|f| f.name == *name) {
+                    fields.push(Field {
+                        name: name.clone(),
+                        mask: value,
+                        shift: value.trailing_zeros() as u8,
+                        description: description.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    (fields, values)
+}
+
+// The longest prefix, in whole `_` separated words, of every name. For a single name, everything
+// but the last word.
+fn common_prefix<'a, I>(names: I) -> String
+    where I: Iterator<Item = &'a str>
+{
+    let names: Vec<Vec<&str>> = names.map(|n| n.split('_').collect()).collect();
+    let shortest = names.iter().map(|n| n.len()).min().unwrap_or(0);
+    let mut words = 0;
+    // Always leave at least one word for the register name.
+    while words + 1 < shortest && names.iter().all(|n| n[words] == names[0][words]) {
+        words += 1;
+    }
+    let mut prefix = names[0][..words].join("_");
+    prefix.push('_');
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "\
+//*****************************************************************************
+//
+// GPIO registers (PORTF)
+//
+//*****************************************************************************
+#define GPIO_PORTF_DATA_BITS_R  ((volatile uint32_t *)0x40025000)
+#define GPIO_PORTF_DATA_R       (*((volatile uint32_t *)0x400253FC))
+#define GPIO_PORTF_IM_R         (*((volatile uint32_t *)0x40025410))
+
+//*****************************************************************************
+//
+// GPIO registers (PORTA AHB)
+//
+//*****************************************************************************
+#define GPIO_PORTA_AHB_DATA_R   (*((volatile uint32_t *)0x400583FC))
+#define GPIO_PORTA_AHB_IM_R     (*((volatile uint32_t *)0x40058410))
+
+//*****************************************************************************
+//
+// Watchdog Timer registers (WATCHDOG0)
+//
+//*****************************************************************************
+#define WATCHDOG0_LOAD_R        (*((volatile uint32_t *)0x40000000))
+#define WATCHDOG0_VALUE_R       (*((volatile uint32_t *)0x40000004))
+
+//*****************************************************************************
+//
+// Univeral Serial Bus registers (USB0)
+//
+//*****************************************************************************
+#define USB0_FADDR_R            (*((volatile uint8_t *)0x40050000))
+#define USB0_TXIS_R             (*((volatile uint16_t *)0x40050002))
+
+//*****************************************************************************
+//
+// System Control registers (SYSCTL)
+//
+//*****************************************************************************
+#define SYSCTL_RCC_R            (*((volatile uint32_t *)0x400FE060))
+#define SYSCTL_RCGCGPIO_R       (*((volatile uint32_t *)0x400FE608))
+
+//*****************************************************************************
+//
+// The following are defines for the bit fields in the GPIO_O_IM register.
+//
+//*****************************************************************************
+#define GPIO_IM_DMAIME          0x00000100  // GPIO uDMA Done Interrupt Mask
+                                            // Enable
+#define GPIO_IM_GPIO_M          0x000000FF  // GPIO Interrupt Mask Enable
+#define GPIO_IM_GPIO_S          0
+
+//*****************************************************************************
+//
+// The following are defines for the bit fields in the WDT_O_LOAD register.
+//
+//*****************************************************************************
+#define WDT_LOAD_M              0xFFFFFFFF  // Watchdog Load Value
+#define WDT_LOAD_S              0
+
+//*****************************************************************************
+//
+// The following are defines for the bit fields in the SYSCTL_RCC register.
+//
+//*****************************************************************************
+#define SYSCTL_RCC_ACG          0x08000000  // Auto Clock Gating
+#define SYSCTL_RCC_XTAL_M       0x000007C0  // Crystal Value
+#define SYSCTL_RCC_XTAL_16MHZ   0x00000540  // 16 MHz
+#define SYSCTL_RCC_OSCSRC_M     0x00000030  // Oscillator Source
+#define SYSCTL_RCC_OSCSRC_MAIN  0x00000000  // MOSC
+#define SYSCTL_RCC_OSCSRC_INT4  0x00000020  // IOSC/4
+#define SYSCTL_RCC_MOSCDIS      0x00000001  // Main Oscillator Disable
+#define SYSCTL_RCC_XTAL_S       6
+
+//*****************************************************************************
+//
+// The following are defines for the bit fields in the GPIO_PCTL register for
+// port A.
+//
+//*****************************************************************************
+#define GPIO_PCTL_PA0_M         0x0000000F  // PA0 Mask
+";
+
+    fn device() -> Device {
+        Device::from_header(HEADER)
+    }
+
+    #[test]
+    fn peripherals_are_named_after_the_prefix_of_their_registers() {
+        let names = device().peripherals.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+        assert_eq!(vec!["GPIO_PORTF", "GPIO_PORTA_AHB", "WATCHDOG0", "USB0", "SYSCTL"], names);
+    }
+
+    #[test]
+    fn peripherals_know_their_kind() {
+        let device = device();
+        assert_eq!("GPIO", device.peripheral("GPIO_PORTA_AHB").unwrap().kind);
+        assert_eq!("WATCHDOG", device.peripheral("WATCHDOG0").unwrap().kind);
+        assert_eq!("USB", device.peripheral("USB0").unwrap().kind);
+    }
+
+    #[test]
+    fn registers_have_their_address_and_width() {
+        let device = device();
+        let portf = device.peripheral("GPIO_PORTF").unwrap();
+        // The DATA_BITS pointer isn't a register.
+        assert_eq!(2, portf.registers.len());
+        assert_eq!(("IM", 0x40025410, 32), (portf.registers[1].name.as_str(), portf.registers[1].address, portf.registers[1].width));
+
+        let usb = device.peripheral("USB0").unwrap();
+        assert_eq!(vec![8, 16], usb.registers.iter().map(|r| r.width).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shared_fields_are_attached_to_every_instance() {
+        let device = device();
+        let group = device.group("GPIO", "IM").unwrap();
+        for name in &["GPIO_PORTF", "GPIO_PORTA_AHB"] {
+            let im = device.peripheral(name).unwrap().registers.iter().find(|r| r.name == "IM").unwrap();
+            assert_eq!(Some(group), im.group.map(|i| &device.groups[i]));
+        }
+    }
+
+    #[test]
+    fn shared_fields_can_use_another_name_for_the_kind() {
+        let device = device();
+        let load = &device.peripheral("WATCHDOG0").unwrap().registers[0];
+        assert_eq!("WDT", device.groups[load.group.unwrap()].kind);
+    }
+
+    #[test]
+    fn fields_named_after_a_register_are_attached_to_it() {
+        let device = device();
+        let rcc = &device.peripheral("SYSCTL").unwrap().registers[0];
+        assert_eq!("RCC", device.groups[rcc.group.unwrap()].register);
+    }
+
+    #[test]
+    fn registers_without_fields_have_no_group() {
+        let device = device();
+        assert_eq!(None, device.peripheral("SYSCTL").unwrap().registers[1].group);
+        assert_eq!(None, device.peripheral("GPIO_PORTF").unwrap().registers[0].group);
+    }
+
+    #[test]
+    fn field_blocks_for_something_other_than_a_register_are_left_out() {
+        assert!(device().groups.iter().all(|g| g.register != "PCTL"));
+    }
+
+    #[test]
+    fn multi_bit_fields_have_a_mask_and_shift() {
+        let device = device();
+        let group = device.group("GPIO", "IM").unwrap();
+        let gpio = group.fields.iter().find(|f| f.name == "GPIO").unwrap();
+        assert_eq!((0xFF, 0), (gpio.mask, gpio.shift));
+        assert_eq!("GPIO Interrupt Mask Enable", gpio.description);
+
+        let rcc = device.group("SYSCTL", "RCC").unwrap();
+        let xtal = rcc.fields.iter().find(|f| f.name == "XTAL").unwrap();
+        assert_eq!((0x7C0, 6), (xtal.mask, xtal.shift));
+    }
+
+    #[test]
+    fn single_bits_are_fields_too() {
+        let device = device();
+        let group = device.group("GPIO", "IM").unwrap();
+        let dmaime = group.fields.iter().find(|f| f.name == "DMAIME").unwrap();
+        assert_eq!((0x100, 8), (dmaime.mask, dmaime.shift));
+        assert_eq!("GPIO uDMA Done Interrupt Mask Enable", dmaime.description);
+    }
+
+    #[test]
+    fn values_of_a_field_are_shifted_down() {
+        let device = device();
+        let rcc = device.group("SYSCTL", "RCC").unwrap();
+        let value = |name: &str| rcc.values.iter().find(|v| v.name == name).map(|v| (v.field.as_str(), v.value));
+        assert_eq!(Some(("XTAL", 0x15)), value("XTAL_16MHZ"));
+        assert_eq!(Some(("OSCSRC", 0)), value("OSCSRC_MAIN"));
+        assert_eq!(Some(("OSCSRC", 2)), value("OSCSRC_INT4"));
+        // Shifts aren't fields or values.
+        assert!(rcc.fields.iter().all(|f| f.name != "XTAL_S"));
+        assert_eq!(None, value("XTAL_S"));
+    }
+
+    #[test]
+    fn the_prefix_is_made_of_whole_words() {
+        assert_eq!("GPIO_PORTF_", common_prefix(vec!["GPIO_PORTF_DATA", "GPIO_PORTF_DIR"].into_iter()));
+        assert_eq!("NVIC_", common_prefix(vec!["NVIC_ST_CTRL", "NVIC_ST_RELOAD", "NVIC_EN0"].into_iter()));
+        assert_eq!("COMP_", common_prefix(vec!["COMP_ACMIS"].into_iter()));
+    }
+}
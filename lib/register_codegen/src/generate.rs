@@ -0,0 +1,237 @@
+/*
+    Writes out a device as Rust, for the tiva_registers crate to include. Every peripheral is a
+    module of registers, and every group of bit fields a module under `fields`:
+
+        pub mod gpio_portf {
+            pub const IM: ::Register<::fields::gpio::im::Fields> = ::Register::new(0x40025410);
+        }
+
+        pub mod fields {
+            pub mod gpio {
+                pub mod im {
+                    pub enum Fields {}
+                    pub const GPIO: ::Field<Fields> = ::Field::new(0x000000FF, 0);
+                }
+            }
+        }
+
+    The `Fields` marker ties a register to its own fields, so a field can't be used with the wrong
+    register. Registers without any fields are `Register<::NoFields>`.
+*/
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use device::{Device, FieldGroup};
+
+pub fn rust(device: &Device) -> String {
+    let mut out = String::new();
+    writeln!(out, "// Generated by register_codegen from the device header. Don't edit.").unwrap();
+
+    for peripheral in &device.peripherals {
+        out.push('\n');
+        writeln!(out, "/// {}", peripheral.description).unwrap();
+        writeln!(out, "pub mod {} {{", module_name(&peripheral.name)).unwrap();
+        let mut names = Vec::new();
+        for register in &peripheral.registers {
+            let name = constant_name(&register.name);
+            if names.contains(&name) {
+                continue;
+            }
+            let fields = match register.group {
+                Some(i) => format!("{}::Fields", group_path(&device.groups[i])),
+                None => "::NoFields".to_string(),
+            };
+            let ty = match register.width {
+                32 => format!("::Register<{}>", fields),
+                width => format!("::Register<{}, u{}>", fields, width),
+            };
+            writeln!(out, "    pub const {}: {} = ::Register::new({:#010X});", name, ty, register.address)
+                .unwrap();
+            names.push(name);
+        }
+        writeln!(out, "}}").unwrap();
+    }
+
+    // Group the field modules by kind.
+    let kinds: BTreeSet<String> = device.groups.iter().map(|group| module_name(&group.kind)).collect();
+
+    out.push('\n');
+    writeln!(out, "/// The bit fields of each register, by kind of peripheral.").unwrap();
+    writeln!(out, "pub mod fields {{").unwrap();
+    for kind in &kinds {
+        writeln!(out, "    pub mod {} {{", kind).unwrap();
+        for group in device.groups.iter().filter(|group| module_name(&group.kind) == *kind) {
+            write_group(&mut out, group);
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn write_group(out: &mut String, group: &FieldGroup) {
+    writeln!(out, "        pub mod {} {{", module_name(&group.register)).unwrap();
+    writeln!(out, "            pub enum Fields {{}}").unwrap();
+    let mut names = Vec::new();
+    for field in &group.fields {
+        let name = constant_name(&field.name);
+        if names.contains(&name) {
+            continue;
+        }
+        doc(out, &field.description);
+        writeln!(out, "            pub const {}: ::Field<Fields> = ::Field::new({:#010X}, {});",
+                 name, field.mask, field.shift).unwrap();
+        names.push(name);
+    }
+    for value in &group.values {
+        let name = constant_name(&value.name);
+        if names.contains(&name) {
+            continue;
+        }
+        doc(out, &value.description);
+        writeln!(out, "            pub const {}: u32 = {:#X};", name, value.value).unwrap();
+        names.push(name);
+    }
+    writeln!(out, "        }}").unwrap();
+}
+
+fn doc(out: &mut String, description: &str) {
+    if !description.is_empty() {
+        writeln!(out, "            /// {}", description).unwrap();
+    }
+}
+
+fn group_path(group: &FieldGroup) -> String {
+    format!("::fields::{}::{}", module_name(&group.kind), module_name(&group.register))
+}
+
+// Rust keywords which a lower case register name could run into.
+const KEYWORDS: &[&str] = &[
+    "abstract", "alignof", "as", "become", "box", "break", "const", "continue", "crate", "do",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "offsetof", "override", "priv", "proc", "pub", "pure",
+    "ref", "return", "self", "sizeof", "static", "struct", "super", "trait", "true", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+pub fn module_name(name: &str) -> String {
+    let name = identifier(&name.to_lowercase());
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+pub fn constant_name(name: &str) -> String {
+    identifier(&name.to_uppercase())
+}
+
+// Names can't start with a digit (like the PWM generator registers, `0_CTL`).
+fn identifier(name: &str) -> String {
+    if name.chars().next().map_or(true, |c| c.is_digit(10)) {
+        format!("_{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use device::Device;
+
+    const HEADER: &str = "\
+//*****************************************************************************
+//
+// GPIO registers (PORTF)
+//
+//*****************************************************************************
+#define GPIO_PORTF_DATA_R       (*((volatile uint32_t *)0x400253FC))
+#define GPIO_PORTF_IM_R         (*((volatile uint32_t *)0x40025410))
+
+//*****************************************************************************
+//
+// Univeral Serial Bus registers (USB0)
+//
+//*****************************************************************************
+#define USB0_FADDR_R            (*((volatile uint8_t *)0x40050000))
+#define USB0_TYPE0_R            (*((volatile uint8_t *)0x4005001A))
+
+//*****************************************************************************
+//
+// PWM registers (PWM0)
+//
+//*****************************************************************************
+#define PWM0_CTL_R              (*((volatile uint32_t *)0x40028000))
+#define PWM0_0_CTL_R            (*((volatile uint32_t *)0x40028040))
+
+//*****************************************************************************
+//
+// The following are defines for the bit fields in the GPIO_O_IM register.
+//
+//*****************************************************************************
+#define GPIO_IM_DMAIME          0x00000100  // GPIO uDMA Done Interrupt Mask
+                                            // Enable
+#define GPIO_IM_GPIO_M          0x000000FF  // GPIO Interrupt Mask Enable
+#define GPIO_IM_GPIO_S          0
+
+//*****************************************************************************
+//
+// The following are defines for the bit fields in the USB_O_TYPE0 register.
+//
+//*****************************************************************************
+#define USB_TYPE0_SPEED_M       0x000000C0  // Operating Speed
+#define USB_TYPE0_SPEED_HIGH    0x00000040  // High
+#define USB_TYPE0_SPEED_S       6
+";
+
+    fn generated() -> String {
+        rust(&Device::from_header(HEADER))
+    }
+
+    #[test]
+    fn registers_are_constants_in_their_peripheral_module() {
+        let code = generated();
+        assert!(code.contains("/// GPIO registers (PORTF)\npub mod gpio_portf {"));
+        assert!(code.contains("    pub const DATA: ::Register<::NoFields> = ::Register::new(0x400253FC);"));
+    }
+
+    #[test]
+    fn registers_with_fields_are_tied_to_them() {
+        assert!(generated().contains(
+            "    pub const IM: ::Register<::fields::gpio::im::Fields> = ::Register::new(0x40025410);"));
+    }
+
+    #[test]
+    fn narrow_registers_have_their_width() {
+        let code = generated();
+        assert!(code.contains("    pub const FADDR: ::Register<::NoFields, u8> = ::Register::new(0x40050000);"));
+        assert!(code.contains("    pub const TYPE0: ::Register<::fields::usb::type0::Fields, u8>"));
+    }
+
+    #[test]
+    fn fields_have_their_mask_shift_and_description() {
+        let code = generated();
+        assert!(code.contains("pub mod fields {\n    pub mod gpio {\n        pub mod im {\n            pub enum Fields {}"));
+        assert!(code.contains("            /// GPIO Interrupt Mask Enable\n            pub const GPIO: ::Field<Fields> = ::Field::new(0x000000FF, 0);"));
+        assert!(code.contains("            /// GPIO uDMA Done Interrupt Mask Enable\n            pub const DMAIME: ::Field<Fields> = ::Field::new(0x00000100, 8);"));
+    }
+
+    #[test]
+    fn values_are_shifted_constants() {
+        assert!(generated().contains("            /// High\n            pub const SPEED_HIGH: u32 = 0x1;"));
+    }
+
+    #[test]
+    fn names_starting_with_digits_are_prefixed() {
+        assert!(generated().contains("    pub const _0_CTL: ::Register<::NoFields> = ::Register::new(0x40028040);"));
+    }
+
+    #[test]
+    fn module_names_avoid_keywords() {
+        assert_eq!("type_", module_name("TYPE"));
+        assert_eq!("rcc", module_name("RCC"));
+        assert_eq!("_0_ctl", module_name("0_CTL"));
+    }
+}
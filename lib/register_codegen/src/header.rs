@@ -0,0 +1,278 @@
+/*
+    Reads the register definitions out of a TivaWare device header (like tm4c123gh6pm.h).
+
+    The header is a list of blocks, each under a banner comment. Register blocks are titled
+    `<description> registers (<instance>)` and define each register as a dereferenced pointer:
+
+        #define GPIO_PORTF_DIR_R        (*((volatile uint32_t *)0x40025400))
+
+    Bit field blocks are titled `The following are defines for the bit fields in the <NAME>
+    register`, and define masks, shifts and values:
+
+        #define SYSCTL_RCC_XTAL_M       0x000007C0  // Crystal Value
+        #define SYSCTL_RCC_XTAL_16MHZ   0x00000540  // 16 MHz
+        #define SYSCTL_RCC_XTAL_S       6
+
+    Peripherals with several instances (every GPIO port, every UART) share their bit fields, and
+    the block is named with `_O_` (GPIO_O_IM) instead of after one instance's register.
+*/
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Define {
+    pub name: String,
+    pub value: String,
+    pub description: String,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Block {
+    // A register block: its title and the register defines.
+    Registers(String, Vec<Define>),
+    // A bit field block: the name of the register (or the shared `_O_` name) and the defines.
+    Fields(String, Vec<Define>),
+}
+
+// Split the header into register and bit field blocks. Anything else is left out.
+#[allow(deprecated)]
+pub fn blocks(header: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    let mut in_banner = false;
+    let mut waiting_for_name = false;
+    let mut lines = header.lines();
+
+    while let Some(line) = lines.next() {
+        let text = line.trim();
+
+        // Banner comments start in the first column. Comments after a define are indented.
+        if line.starts_with("//") {
+            let title = text.trim_left_matches('/').trim();
+            if title.is_empty() || title.chars().all(|c| c == '*') {
+                in_banner = true;
+                continue;
+            }
+            if waiting_for_name {
+                waiting_for_name = false;
+                if let Some(name) = title.split_whitespace().next().and_then(identifier) {
+                    current = Some(Block::Fields(name, Vec::new()));
+                }
+                continue;
+            }
+            if !in_banner {
+                continue;
+            }
+            in_banner = false;
+
+            if let Some(block) = current.take() {
+                blocks.push(block);
+            }
+            if title.contains("registers (") {
+                current = Some(Block::Registers(title.to_string(), Vec::new()));
+            } else if let Some(start) = title.find("bit fields in the") {
+                let rest = &title[start + "bit fields in the".len()..];
+                match rest.split_whitespace().next().and_then(identifier) {
+                    Some(name) => current = Some(Block::Fields(name, Vec::new())),
+                    None => waiting_for_name = true,
+                }
+            }
+            continue;
+        }
+        in_banner = false;
+
+        if text.starts_with("//") {
+            // The description of the last define carries on.
+            if let Some(define) = current.as_mut().and_then(last_define) {
+                let more = text.trim_left_matches('/').trim();
+                if !define.description.is_empty() {
+                    define.description.push(' ');
+                }
+                define.description.push_str(more);
+            }
+            continue;
+        }
+
+        if text.starts_with("#define") {
+            let mut text = text.to_string();
+            // Long names put the value on the next line.
+            while text.ends_with('\\') {
+                text.pop();
+                match lines.next() {
+                    Some(next) => {
+                        text.push(' ');
+                        text.push_str(next.trim());
+                    }
+                    None => break,
+                }
+            }
+            if let (Some(define), Some(block)) = (parse_define(&text), current.as_mut()) {
+                match *block {
+                    Block::Registers(_, ref mut defines) | Block::Fields(_, ref mut defines) => {
+                        defines.push(define)
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(block) = current.take() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+fn last_define(block: &mut Block) -> Option<&mut Define> {
+    match *block {
+        Block::Registers(_, ref mut defines) | Block::Fields(_, ref mut defines) => defines.last_mut(),
+    }
+}
+
+// `#define NAME VALUE // description`
+fn parse_define(text: &str) -> Option<Define> {
+    let text = text["#define".len()..].trim();
+    let (code, description) = match text.find("//") {
+        Some(at) => (text[..at].trim(), text[at + 2..].trim()),
+        None => (text, ""),
+    };
+    let mut parts = code.splitn(2, char::is_whitespace);
+    let name = parts.next().and_then(identifier);
+    let value = parts.next().unwrap_or("").trim();
+    if value.is_empty() {
+        return None;
+    }
+    name.map(|name| Define { name, value: value.to_string(), description: description.to_string() })
+}
+
+// The text as a C identifier, if it is one.
+fn identifier(text: &str) -> Option<String> {
+    let valid = !text.is_empty() &&
+                !text.chars().next().unwrap().is_digit(10) &&
+                text.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if valid { Some(text.to_string()) } else { None }
+}
+
+// A register address and width from `(*((volatile uint32_t *)0x40025400))`.
+pub fn register_address(value: &str) -> Option<(u32, u8)> {
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let prefix = "(*((volatileuint";
+    if !value.starts_with(prefix) || !value.ends_with("))") {
+        return None;
+    }
+    // Leaves `32_t*)0x40025400`.
+    let mut parts = value[prefix.len()..value.len() - 2].splitn(2, "_t*)");
+    match (parts.next().and_then(|w| w.parse().ok()), parts.next().and_then(number)) {
+        (Some(width), Some(address)) => Some((address, width)),
+        _ => None,
+    }
+}
+
+// A hex or decimal number, with the C unsigned suffix if there is one.
+#[allow(deprecated)]
+pub fn number(text: &str) -> Option<u32> {
+    let text = text.trim().trim_right_matches(|c| c == 'u' || c == 'U');
+    if text.starts_with("0x") || text.starts_with("0X") {
+        u32::from_str_radix(&text[2..], 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "\
+//*****************************************************************************
+//
+// GPIO registers (PORTF)
+//
+//*****************************************************************************
+#define GPIO_PORTF_DATA_BITS_R  ((volatile uint32_t *)0x40025000)
+#define GPIO_PORTF_DATA_R       (*((volatile uint32_t *)0x400253FC))
+#define GPIO_PORTF_DIR_R        (*((volatile uint32_t *)0x40025400))
+
+//*****************************************************************************
+//
+// The following are defines for the bit fields in the SYSCTL_RCGCGPIO
+// register.
+//
+//*****************************************************************************
+#define SYSCTL_RCGCGPIO_R5      0x00000020  // GPIO Port F Run Mode Clock
+                                            // Gating Control
+#define SYSCTL_RCGCGPIO_R4      0x00000010  // GPIO Port E Run Mode Clock
+
+//*****************************************************************************
+//
+// Interrupt assignments
+//
+//*****************************************************************************
+#define INT_GPIOF               46          // GPIO Port F
+
+//*****************************************************************************
+//
+// The following are defines for the bit fields in the
+// SYSCTL_DID0 register.
+//
+//*****************************************************************************
+#define SYSCTL_DID0_CLASS_BLIZZARD                                            \\
+                                0x00050000  // Tiva(TM) C Series TM4C123-class
+";
+
+    #[test]
+    fn the_header_is_split_into_register_and_field_blocks() {
+        let blocks = blocks(HEADER);
+        assert_eq!(3, blocks.len());
+        match blocks[0] {
+            Block::Registers(ref title, ref defines) => {
+                assert_eq!("GPIO registers (PORTF)", title);
+                assert_eq!(3, defines.len());
+            }
+            _ => panic!("expected registers"),
+        }
+        match blocks[1] {
+            Block::Fields(ref name, ref defines) => {
+                assert_eq!("SYSCTL_RCGCGPIO", name);
+                assert_eq!(2, defines.len());
+            }
+            _ => panic!("expected fields"),
+        }
+    }
+
+    #[test]
+    fn descriptions_carry_on_over_several_lines() {
+        match blocks(HEADER)[1] {
+            Block::Fields(_, ref defines) => {
+                assert_eq!("SYSCTL_RCGCGPIO_R5", defines[0].name);
+                assert_eq!("0x00000020", defines[0].value);
+                assert_eq!("GPIO Port F Run Mode Clock Gating Control", defines[0].description);
+            }
+            _ => panic!("expected fields"),
+        }
+    }
+
+    #[test]
+    fn names_and_values_can_be_split_over_lines() {
+        match blocks(HEADER)[2] {
+            Block::Fields(ref name, ref defines) => {
+                assert_eq!("SYSCTL_DID0", name);
+                assert_eq!("SYSCTL_DID0_CLASS_BLIZZARD", defines[0].name);
+                assert_eq!(Some(0x50000), number(&defines[0].value));
+            }
+            _ => panic!("expected fields"),
+        }
+    }
+
+    #[test]
+    fn register_addresses_and_widths_are_read() {
+        assert_eq!(Some((0x400253FC, 32)), register_address("(*((volatile uint32_t *)0x400253FC))"));
+        assert_eq!(Some((0x40050000, 8)), register_address("(*((volatile uint8_t *)0x40050000))"));
+        assert_eq!(None, register_address("((volatile uint32_t *)0x40025000)"));
+    }
+
+    #[test]
+    fn numbers_can_be_hex_or_decimal() {
+        assert_eq!(Some(0x7C0), number("0x000007C0"));
+        assert_eq!(Some(6), number("6"));
+        assert_eq!(Some(0x10), number("0x10U"));
+        assert_eq!(None, number("GPIO_O_DATA"));
+    }
+}
@@ -0,0 +1,100 @@
+/*
+    Generates the Rust register definitions for a Tiva device from its TivaWare header. Used by
+    the build script of the tiva_registers crate:
+
+        register_codegen::generate("../TivaWare/inc/tm4c123gh6pm.h", &out_dir.join("registers.rs"))
+
+    See the header module for what is read from the header, the device module for how it becomes
+    peripherals, registers and fields, and the generate module for the Rust that comes out.
+*/
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+pub mod header;
+pub mod device;
+pub mod generate;
+
+pub use device::Device;
+
+// Read the header at `header` and write the Rust for it to `out`.
+pub fn generate<P, Q>(header: P, out: Q) -> io::Result<()>
+    where P: AsRef<Path>, Q: AsRef<Path>
+{
+    let mut text = String::new();
+    File::open(header)?.read_to_string(&mut text)?;
+    let device = Device::from_header(&text);
+    File::create(out)?.write_all(generate::rust(&device).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+
+    // The header the firmware is built with.
+    fn tm4c123gh6pm() -> Device {
+        let mut text = String::new();
+        File::open("../TivaWare/inc/tm4c123gh6pm.h").unwrap().read_to_string(&mut text).unwrap();
+        Device::from_header(&text)
+    }
+
+    fn address(device: &Device, peripheral: &str, register: &str) -> u32 {
+        device.peripheral(peripheral).unwrap()
+            .registers.iter().find(|r| r.name == register).unwrap()
+            .address
+    }
+
+    #[test]
+    fn every_register_in_the_header_is_found() {
+        let mut text = String::new();
+        File::open("../TivaWare/inc/tm4c123gh6pm.h").unwrap().read_to_string(&mut text).unwrap();
+        let in_header = text.lines()
+            .filter(|l| l.starts_with("#define") && l.contains("(*((volatile uint"))
+            .count();
+        let found: usize = tm4c123gh6pm().peripherals.iter().map(|p| p.registers.len()).sum();
+        assert_eq!(in_header, found);
+    }
+
+    #[test]
+    fn registers_the_firmware_uses_have_the_right_addresses() {
+        let device = tm4c123gh6pm();
+        assert_eq!(0x400253FC, address(&device, "GPIO_PORTF", "DATA"));
+        assert_eq!(0x4002541C, address(&device, "GPIO_PORTF", "ICR"));
+        assert_eq!(0x400FE608, address(&device, "SYSCTL", "RCGCGPIO"));
+        assert_eq!(0x400FE060, address(&device, "SYSCTL", "RCC"));
+        assert_eq!(0xE000ED04, address(&device, "NVIC", "INT_CTRL"));
+        assert_eq!(0xE000ED88, address(&device, "NVIC", "CPAC"));
+        assert_eq!(0x4000C000, address(&device, "UART0", "DR"));
+    }
+
+    #[test]
+    fn fields_the_firmware_uses_have_the_right_masks() {
+        let device = tm4c123gh6pm();
+        let field = |kind: &str, register: &str, name: &str| {
+            let group = device.group(kind, register).unwrap();
+            let field = group.fields.iter().find(|f| f.name == name).unwrap();
+            (field.mask, field.shift)
+        };
+        assert_eq!((0xFF, 0), field("NVIC", "INT_CTRL", "VEC_ACT"));
+        assert_eq!((0x00300000, 20), field("NVIC", "CPAC", "CP10"));
+        assert_eq!((0x00C00000, 22), field("NVIC", "CPAC", "CP11"));
+        assert_eq!((0x7C0, 6), field("SYSCTL", "RCC", "XTAL"));
+        assert_eq!((0x20, 5), field("SYSCTL", "RCGCGPIO", "R5"));
+        assert_eq!((0xFF, 0), field("GPIO", "ICR", "GPIO"));
+        assert_eq!((0x10, 4), field("UART", "FR", "RXFE"));
+    }
+
+    #[test]
+    fn shared_fields_reach_every_instance() {
+        let device = tm4c123gh6pm();
+        for port in &["GPIO_PORTA", "GPIO_PORTF", "GPIO_PORTF_AHB"] {
+            let im = device.peripheral(port).unwrap().registers.iter().find(|r| r.name == "IM").unwrap();
+            assert!(im.group.is_some(), "{} IM has no fields", port);
+        }
+        let load = device.peripheral("WATCHDOG1").unwrap().registers.iter().find(|r| r.name == "LOAD").unwrap();
+        assert!(load.group.is_some());
+    }
+}
@@ -0,0 +1,22 @@
+extern crate register_codegen;
+
+use std::env;
+use std::path::PathBuf;
+
+// The TivaWare header for the device selected by the cargo features.
+fn header() -> &'static str {
+    if env::var("CARGO_FEATURE_TM4C123GH6PM").is_ok() {
+        "../TivaWare/inc/tm4c123gh6pm.h"
+    } else if env::var("CARGO_FEATURE_TM4C1294NCPDT").is_ok() {
+        "../TivaWare/inc/tm4c1294ncpdt.h"
+    } else {
+        panic!("Select a device with a cargo feature, such as tm4c123gh6pm.");
+    }
+}
+
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let header = header();
+    register_codegen::generate(header, out_dir.join("registers.rs")).unwrap();
+    println!("cargo:rerun-if-changed={}", header);
+}
@@ -0,0 +1,255 @@
+/*
+    Direct access to the registers of a Tiva device, generated from its TivaWare header (see
+    register_codegen). Each peripheral is a module of registers, and the bit fields of each
+    register are in the `fields` module:
+
+        use tiva_registers::{gpio_portf, sysctl};
+        use tiva_registers::fields::sysctl::{rcgcgpio, rcc};
+
+        sysctl::RCGCGPIO.set(rcgcgpio::R5);
+        sysctl::RCC.write_field(rcc::XTAL, rcc::XTAL_16MHZ);
+        gpio_portf::DIR.write(0x0E);
+
+    Names follow the header with the peripheral prefix and the `_R` dropped: GPIO_PORTF_DIR_R is
+    gpio_portf::DIR, and the SYSCTL_RCC_XTAL_M field is fields::sysctl::rcc::XTAL. Peripherals with
+    several instances share their fields, so the GPIO_IM_* fields are fields::gpio::im for every
+    port. Fields can only be used with the register they belong to.
+
+    These do exactly what the header's macros do in C, so drivers can move off driverlib one
+    register at a time.
+*/
+
+#![no_std]
+#![feature(const_fn)]
+
+use core::marker::PhantomData;
+use core::ptr;
+
+// The registers and fields of the device selected by the cargo features.
+include!(concat!(env!("OUT_DIR"), "/registers.rs"));
+
+// The fields of a register the header doesn't define any fields for.
+pub enum NoFields {}
+
+// The size of a register. Most are 32 bits, but some USB registers are 8 or 16.
+pub trait Width: Copy {
+    fn to_u32(self) -> u32;
+    fn from_u32(value: u32) -> Self;
+}
+
+impl Width for u8 {
+    fn to_u32(self) -> u32 { self as u32 }
+    fn from_u32(value: u32) -> u8 { value as u8 }
+}
+
+impl Width for u16 {
+    fn to_u32(self) -> u32 { self as u32 }
+    fn from_u32(value: u32) -> u16 { value as u16 }
+}
+
+impl Width for u32 {
+    fn to_u32(self) -> u32 { self }
+    fn from_u32(value: u32) -> u32 { value }
+}
+
+// A memory mapped register. `F` marks which fields belong to it.
+pub struct Register<F, T = u32> {
+    address: usize,
+    fields: PhantomData<(F, T)>,
+}
+
+impl<F, T> Register<F, T> {
+    pub const fn new(address: usize) -> Register<F, T> {
+        Register { address: address, fields: PhantomData }
+    }
+
+    pub fn address(&self) -> usize {
+        self.address
+    }
+}
+
+impl<F, T> Register<F, T> where T: Width {
+    pub fn read(&self) -> T {
+        unsafe { ptr::read_volatile(self.address as *const T) }
+    }
+
+    pub fn write(&self, value: T) {
+        unsafe { ptr::write_volatile(self.address as *mut T, value) }
+    }
+
+    // Read the register, change the value and write it back.
+    pub fn modify<M>(&self, change: M)
+        where M: FnOnce(T) -> T
+    {
+        let value = self.read();
+        self.write(change(value));
+    }
+
+    // The value of one field, shifted down.
+    pub fn read_field(&self, field: Field<F>) -> u32 {
+        field.get(self.read().to_u32())
+    }
+
+    // Change one field, leaving the rest of the register as it was.
+    pub fn write_field(&self, field: Field<F>, value: u32) {
+        self.modify(|r| T::from_u32(field.set(r.to_u32(), value)));
+    }
+
+    // Whether every bit of the field is set.
+    pub fn is_set(&self, field: Field<F>) -> bool {
+        self.read().to_u32() & field.mask == field.mask
+    }
+
+    // Set every bit of the field.
+    pub fn set(&self, field: Field<F>) {
+        self.modify(|r| T::from_u32(r.to_u32() | field.mask));
+    }
+
+    // Clear every bit of the field.
+    pub fn clear(&self, field: Field<F>) {
+        self.modify(|r| T::from_u32(r.to_u32() & !field.mask));
+    }
+}
+
+// Some bits of a register `F`.
+pub struct Field<F> {
+    mask: u32,
+    shift: u8,
+    register: PhantomData<F>,
+}
+
+impl<F> Clone for Field<F> {
+    fn clone(&self) -> Field<F> {
+        *self
+    }
+}
+
+impl<F> Copy for Field<F> {}
+
+impl<F> Field<F> {
+    pub const fn new(mask: u32, shift: u8) -> Field<F> {
+        Field { mask: mask, shift: shift, register: PhantomData }
+    }
+
+    // The bits of the field, in place.
+    pub fn mask(self) -> u32 {
+        self.mask
+    }
+
+    // The position of the lowest bit of the field.
+    pub fn shift(self) -> u8 {
+        self.shift
+    }
+
+    // A value for the field, moved into place.
+    pub fn value(self, value: u32) -> u32 {
+        let shifted = value << self.shift;
+        assert!(shifted & !self.mask == 0 && shifted >> self.shift == value, "value doesn't fit the field");
+        shifted
+    }
+
+    // The field of a register value, shifted down.
+    pub fn get(self, register: u32) -> u32 {
+        (register & self.mask) >> self.shift
+    }
+
+    // The register value with the field replaced.
+    pub fn set(self, register: u32, value: u32) -> u32 {
+        (register & !self.mask) | self.value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum TestFields {}
+
+    const LOW: Field<TestFields> = Field::new(0x0000000F, 0);
+    const MIDDLE: Field<TestFields> = Field::new(0x000007C0, 6);
+    const TOP: Field<TestFields> = Field::new(0x80000000, 31);
+
+    fn register(memory: &mut u32) -> Register<TestFields> {
+        Register::new(memory as *mut u32 as usize)
+    }
+
+    #[test]
+    fn registers_read_and_write_memory() {
+        let mut memory = 0;
+        register(&mut memory).write(0x12345678);
+        assert_eq!(0x12345678, register(&mut memory).read());
+    }
+
+    #[test]
+    fn writing_a_field_leaves_the_rest_of_the_register() {
+        let mut memory = 0xFFFFFFFF;
+        register(&mut memory).write_field(MIDDLE, 0x15);
+        assert_eq!(0xFFFFFD7F, memory);
+        assert_eq!(0x15, register(&mut memory).read_field(MIDDLE));
+    }
+
+    #[test]
+    fn fields_can_be_set_and_cleared() {
+        let mut memory = 0;
+        register(&mut memory).set(TOP);
+        register(&mut memory).set(LOW);
+        assert!(register(&mut memory).is_set(TOP));
+        assert_eq!(0x8000000F, memory);
+
+        register(&mut memory).clear(TOP);
+        assert!(!register(&mut memory).is_set(TOP));
+        assert_eq!(0x0000000F, memory);
+    }
+
+    #[test]
+    fn modify_changes_the_value_in_place() {
+        let mut memory = 0x10;
+        register(&mut memory).modify(|r| r | 0x01);
+        assert_eq!(0x11, memory);
+    }
+
+    #[test]
+    fn narrow_registers_only_touch_their_own_bytes() {
+        let mut memory: [u8; 2] = [0, 0xAA];
+        let register: Register<TestFields, u8> = Register::new(&mut memory[0] as *mut u8 as usize);
+        register.write_field(LOW, 0x5);
+        assert_eq!([0x05, 0xAA], memory);
+    }
+
+    #[test]
+    #[should_panic]
+    fn values_which_do_not_fit_the_field_are_rejected() {
+        MIDDLE.value(0x20);
+    }
+
+    // The generated definitions match the header.
+    #[test]
+    #[cfg(feature = "tm4c123gh6pm")]
+    fn registers_are_at_their_header_addresses() {
+        assert_eq!(0x400253FC, gpio_portf::DATA.address());
+        assert_eq!(0x4002541C, gpio_portf::ICR.address());
+        assert_eq!(0x400FE060, sysctl::RCC.address());
+        assert_eq!(0xE000ED04, nvic::INT_CTRL.address());
+        assert_eq!(0x40050000, usb0::FADDR.address());
+    }
+
+    #[test]
+    #[cfg(feature = "tm4c123gh6pm")]
+    fn fields_match_the_header() {
+        assert_eq!((0x000000FF, 0), (fields::nvic::int_ctrl::VEC_ACT.mask(), fields::nvic::int_ctrl::VEC_ACT.shift()));
+        assert_eq!((0x000007C0, 6), (fields::sysctl::rcc::XTAL.mask(), fields::sysctl::rcc::XTAL.shift()));
+        assert_eq!(0x540, fields::sysctl::rcc::XTAL.value(fields::sysctl::rcc::XTAL_16MHZ));
+        assert_eq!(0x00000020, fields::sysctl::rcgcgpio::R5.mask());
+        assert_eq!(0x00300000, fields::nvic::cpac::CP10.value(fields::nvic::cpac::CP10_FULL));
+    }
+
+    #[test]
+    #[cfg(feature = "tm4c1294ncpdt")]
+    fn tm4c1294ncpdt_registers_match_the_header() {
+        assert_eq!(0x4005D3FC, gpio_portf_ahb::DATA.address());
+        assert_eq!(0x400643FC, gpio_portn::DATA.address());
+        assert_eq!(0x400FE0B0, sysctl::RSCLKCFG.address());
+        assert_eq!(0xE000ED04, nvic::INT_CTRL.address());
+        assert_eq!((0x000003FF, 0), (fields::sysctl::rsclkcfg::PSYSDIV.mask(), fields::sysctl::rsclkcfg::PSYSDIV.shift()));
+    }
+}
@@ -0,0 +1,84 @@
+extern crate bindgen;
+extern crate gcc;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// Where the TivaWare sources are, relative to this crate.
+const TIVAWARE: &'static str = "../TivaWare";
+
+// The part driverlib is built for, from the cargo features. pin_map.h only defines the pin
+// functions of this part.
+fn part() -> &'static str {
+    if env::var("CARGO_FEATURE_TM4C123GH6PM").is_ok() {
+        "PART_TM4C123GH6PM"
+    } else if env::var("CARGO_FEATURE_TM4C1294NCPDT").is_ok() {
+        "PART_TM4C1294NCPDT"
+    } else {
+        panic!("Select a device with a cargo feature, such as tm4c123gh6pm.");
+    }
+}
+
+// True when cargo is building with the release profile.
+fn is_release() -> bool {
+    env::var("PROFILE").map(|profile| profile == "release").unwrap_or(false)
+}
+
+// The C compiler flags for the target, following the cargo profile like the rust code does.
+fn target_cflags() -> String {
+    let optimization = if is_release() { "-Os" } else { "-O0 -g -DDEBUG" };
+    format!("{} -ffunction-sections -fdata-sections -mthumb -mcpu=cortex-m4 -mfpu=fpv4-sp-d16 \
+             -mfloat-abi=softfp --specs=nano.specs -std=c99", optimization)
+}
+
+fn is_target() -> bool {
+    env::var("TARGET").unwrap() != env::var("HOST").unwrap()
+}
+
+// Compile every driverlib source into libdriver.a, like driverlib's makefile does.
+fn build_driverlib() {
+    env::set_var("TARGET_CC", "arm-none-eabi-gcc");
+    env::set_var("TARGET_CFLAGS", target_cflags());
+
+    let mut config = gcc::Config::new();
+    config.include(TIVAWARE).define(part(), None);
+    let source = PathBuf::from(TIVAWARE).join("driverlib");
+    for entry in fs::read_dir(&source).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(false, |extension| extension == "c") {
+            config.file(&path);
+        }
+    }
+    config.compile("libdriver.a");
+    println!("cargo:rerun-if-changed={}", source.display());
+}
+
+// Generate the raw bindings: every function and constant of the headers in driverlib.h.
+fn generate_bindings() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let mut builder = bindgen::builder()
+        .header("driverlib.h")
+        .clang_arg(format!("-I{}", TIVAWARE))
+        .clang_arg(format!("-D{}", part()))
+        .use_core()
+        .ctypes_prefix("ctypes");
+    if is_target() {
+        // Lay types out as the target does.
+        builder = builder.clang_arg("--target=thumbv7em-none-eabi");
+    }
+    builder.generate()
+        .expect("Couldn't generate the driverlib bindings.")
+        .write_to_file(out_dir.join("driverlib.rs"))
+        .unwrap();
+    println!("cargo:rerun-if-changed=driverlib.h");
+}
+
+fn main() {
+    generate_bindings();
+
+    // The tests run on the host, where nothing calls into driverlib.
+    if is_target() {
+        build_driverlib();
+    }
+}
@@ -0,0 +1,53 @@
+/*
+    The internal EEPROM, which keeps its contents with the power off. It's read and programmed a
+    32-bit word at a time, at byte addresses which are multiples of 4, and is made of blocks of
+    16 words. Enable its peripheral and call init before using it:
+
+        sysctl::enable(Peripheral::Eeprom0);
+        eeprom::init()?;
+        eeprom::read(&mut words, 0);
+*/
+
+use raw;
+
+// The EEPROM couldn't recover from a write which was interrupted, and mustn't be used.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InitError;
+
+// Programming failed. Holds the EEPROM_RC_ status flags from EEPROMProgram.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ProgramError(pub u32);
+
+// The number of words in a block.
+pub const BLOCK_WORDS: u32 = 16;
+
+// Finish any write which was interrupted by a reset or power loss.
+pub fn init() -> Result<(), InitError> {
+    match unsafe { raw::EEPROMInit() } {
+        raw::EEPROM_INIT_OK => Ok(()),
+        _ => Err(InitError),
+    }
+}
+
+// The size in bytes.
+pub fn size() -> u32 {
+    unsafe { raw::EEPROMSizeGet() }
+}
+
+pub fn block_count() -> u32 {
+    unsafe { raw::EEPROMBlockCountGet() }
+}
+
+// Read words, starting at a byte address.
+pub fn read(words: &mut [u32], address: u32) {
+    unsafe { raw::EEPROMRead(words.as_mut_ptr(), address, (words.len() * 4) as u32); }
+}
+
+// Program words, starting at a byte address. Waits until they've all been written.
+pub fn program(words: &[u32], address: u32) -> Result<(), ProgramError> {
+    // EEPROMProgram doesn't change the words, whatever its signature says.
+    match unsafe { raw::EEPROMProgram(words.as_ptr() as *mut u32, address, (words.len() * 4) as u32) } {
+        0 => Ok(()),
+        status => Err(ProgramError(status)),
+    }
+}
@@ -0,0 +1,326 @@
+/*
+    General purpose I/O. Pins are set up and used a port at a time, several pins at once:
+
+        gpio::set_output(Port::F, Pin::P1 | Pin::P2);
+        gpio::write(Port::F, Pin::P1 | Pin::P2, Pin::P1);  // P1 high, P2 low.
+*/
+
+use core::ops::BitOr;
+use raw;
+use sysctl::Peripheral;
+
+macro_rules! ports {
+    ($($name:ident => $apb_base:ident, $ahb_base:ident, $peripheral:ident,)*) => {
+        // The GPIO ports. Not every part has all of them.
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        pub enum Port {
+            $($name,)*
+        }
+
+        impl Port {
+            // The address of the port's registers. TM4C123 parts use the APB aperture, which is
+            // there out of reset. TM4C129 parts only have the AHB aperture.
+            pub fn base(self) -> u32 {
+                match self {
+                    $(Port::$name => if cfg!(feature = "tm4c1294ncpdt") {
+                        raw::$ahb_base
+                    } else {
+                        raw::$apb_base
+                    },)*
+                }
+            }
+
+            // The peripheral to enable before using the port.
+            pub fn peripheral(self) -> Peripheral {
+                match self {
+                    $(Port::$name => Peripheral::$peripheral,)*
+                }
+            }
+        }
+    }
+}
+
+// Ports K and up are only on the AHB.
+ports! {
+    A => GPIO_PORTA_BASE, GPIO_PORTA_AHB_BASE, GpioA,
+    B => GPIO_PORTB_BASE, GPIO_PORTB_AHB_BASE, GpioB,
+    C => GPIO_PORTC_BASE, GPIO_PORTC_AHB_BASE, GpioC,
+    D => GPIO_PORTD_BASE, GPIO_PORTD_AHB_BASE, GpioD,
+    E => GPIO_PORTE_BASE, GPIO_PORTE_AHB_BASE, GpioE,
+    F => GPIO_PORTF_BASE, GPIO_PORTF_AHB_BASE, GpioF,
+    G => GPIO_PORTG_BASE, GPIO_PORTG_AHB_BASE, GpioG,
+    H => GPIO_PORTH_BASE, GPIO_PORTH_AHB_BASE, GpioH,
+    J => GPIO_PORTJ_BASE, GPIO_PORTJ_AHB_BASE, GpioJ,
+    K => GPIO_PORTK_BASE, GPIO_PORTK_BASE, GpioK,
+    L => GPIO_PORTL_BASE, GPIO_PORTL_BASE, GpioL,
+    M => GPIO_PORTM_BASE, GPIO_PORTM_BASE, GpioM,
+    N => GPIO_PORTN_BASE, GPIO_PORTN_BASE, GpioN,
+    P => GPIO_PORTP_BASE, GPIO_PORTP_BASE, GpioP,
+    Q => GPIO_PORTQ_BASE, GPIO_PORTQ_BASE, GpioQ,
+}
+
+// One pin of a port.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pin {
+    P0,
+    P1,
+    P2,
+    P3,
+    P4,
+    P5,
+    P6,
+    P7,
+}
+
+impl Pin {
+    // The pin's bit in the port.
+    pub fn mask(self) -> u8 {
+        (match self {
+            Pin::P0 => raw::GPIO_PIN_0,
+            Pin::P1 => raw::GPIO_PIN_1,
+            Pin::P2 => raw::GPIO_PIN_2,
+            Pin::P3 => raw::GPIO_PIN_3,
+            Pin::P4 => raw::GPIO_PIN_4,
+            Pin::P5 => raw::GPIO_PIN_5,
+            Pin::P6 => raw::GPIO_PIN_6,
+            Pin::P7 => raw::GPIO_PIN_7,
+        }) as u8
+    }
+}
+
+// Some of the pins of a port. Pins combine with `|`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pins(u8);
+
+impl Pins {
+    pub fn none() -> Pins {
+        Pins(0)
+    }
+
+    pub fn all() -> Pins {
+        Pins(0xFF)
+    }
+
+    // One bit for each pin, as driverlib takes them.
+    pub fn mask(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, pin: Pin) -> bool {
+        self.0 & pin.mask() != 0
+    }
+}
+
+impl From<Pin> for Pins {
+    fn from(pin: Pin) -> Pins {
+        Pins(pin.mask())
+    }
+}
+
+impl BitOr<Pin> for Pin {
+    type Output = Pins;
+
+    fn bitor(self, other: Pin) -> Pins {
+        Pins(self.mask() | other.mask())
+    }
+}
+
+impl BitOr<Pin> for Pins {
+    type Output = Pins;
+
+    fn bitor(self, other: Pin) -> Pins {
+        Pins(self.0 | other.mask())
+    }
+}
+
+impl BitOr<Pins> for Pins {
+    type Output = Pins;
+
+    fn bitor(self, other: Pins) -> Pins {
+        Pins(self.0 | other.0)
+    }
+}
+
+// The drive strength of output pins.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Strength {
+    Ma2,
+    Ma4,
+    Ma6,
+    Ma8,
+    // 8mA with slew rate control.
+    Ma8SlewControl,
+    Ma10,
+    Ma12,
+}
+
+impl Strength {
+    // The driverlib GPIO_STRENGTH_ value.
+    pub fn value(self) -> u32 {
+        match self {
+            Strength::Ma2 => raw::GPIO_STRENGTH_2MA,
+            Strength::Ma4 => raw::GPIO_STRENGTH_4MA,
+            Strength::Ma6 => raw::GPIO_STRENGTH_6MA,
+            Strength::Ma8 => raw::GPIO_STRENGTH_8MA,
+            Strength::Ma8SlewControl => raw::GPIO_STRENGTH_8MA_SC,
+            Strength::Ma10 => raw::GPIO_STRENGTH_10MA,
+            Strength::Ma12 => raw::GPIO_STRENGTH_12MA,
+        }
+    }
+}
+
+// How a pin's pad is wired up.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PadType {
+    Standard,
+    StandardPullUp,
+    StandardPullDown,
+    OpenDrain,
+    Analog,
+    // Wakes the part from hibernation.
+    WakeHigh,
+    WakeLow,
+}
+
+impl PadType {
+    // The driverlib GPIO_PIN_TYPE_ value.
+    pub fn value(self) -> u32 {
+        match self {
+            PadType::Standard => raw::GPIO_PIN_TYPE_STD,
+            PadType::StandardPullUp => raw::GPIO_PIN_TYPE_STD_WPU,
+            PadType::StandardPullDown => raw::GPIO_PIN_TYPE_STD_WPD,
+            PadType::OpenDrain => raw::GPIO_PIN_TYPE_OD,
+            PadType::Analog => raw::GPIO_PIN_TYPE_ANALOG,
+            PadType::WakeHigh => raw::GPIO_PIN_TYPE_WAKE_HIGH,
+            PadType::WakeLow => raw::GPIO_PIN_TYPE_WAKE_LOW,
+        }
+    }
+}
+
+// What makes an input pin interrupt.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InterruptType {
+    FallingEdge,
+    RisingEdge,
+    BothEdges,
+    LowLevel,
+    HighLevel,
+}
+
+impl InterruptType {
+    // The driverlib interrupt type value.
+    pub fn value(self) -> u32 {
+        match self {
+            InterruptType::FallingEdge => raw::GPIO_FALLING_EDGE,
+            InterruptType::RisingEdge => raw::GPIO_RISING_EDGE,
+            InterruptType::BothEdges => raw::GPIO_BOTH_EDGES,
+            InterruptType::LowLevel => raw::GPIO_LOW_LEVEL,
+            InterruptType::HighLevel => raw::GPIO_HIGH_LEVEL,
+        }
+    }
+}
+
+// The pins' interrupt flags. GPIO_INT_PIN_n is the same bit as GPIO_PIN_n.
+fn interrupt_flags(pins: Pins) -> u32 {
+    pins.mask() as u32
+}
+
+pub fn set_output<P: Into<Pins>>(port: Port, pins: P) {
+    unsafe { raw::GPIOPinTypeGPIOOutput(port.base(), pins.into().mask()); }
+}
+
+pub fn set_input<P: Into<Pins>>(port: Port, pins: P) {
+    unsafe { raw::GPIOPinTypeGPIOInput(port.base(), pins.into().mask()); }
+}
+
+// Hand the pins over to a UART. Their function must be picked with `configure_pin` too.
+pub fn set_uart<P: Into<Pins>>(port: Port, pins: P) {
+    unsafe { raw::GPIOPinTypeUART(port.base(), pins.into().mask()); }
+}
+
+// Pick the peripheral function of a pin, one of the pin_map.h constants in `raw` (like
+// GPIO_PA0_U0RX).
+pub fn configure_pin(function: u32) {
+    unsafe { raw::GPIOPinConfigure(function); }
+}
+
+pub fn set_pad_config<P: Into<Pins>>(port: Port, pins: P, strength: Strength, pad_type: PadType) {
+    unsafe { raw::GPIOPadConfigSet(port.base(), pins.into().mask(), strength.value(), pad_type.value()); }
+}
+
+// Set the pins which are in `high` and clear the rest of `pins`. Pins outside `pins` are left
+// alone.
+pub fn write<P: Into<Pins>, H: Into<Pins>>(port: Port, pins: P, high: H) {
+    unsafe { raw::GPIOPinWrite(port.base(), pins.into().mask(), high.into().mask()); }
+}
+
+// Which of the pins are high.
+pub fn read<P: Into<Pins>>(port: Port, pins: P) -> Pins {
+    unsafe { Pins(raw::GPIOPinRead(port.base(), pins.into().mask()) as u8) }
+}
+
+pub fn set_interrupt_type<P: Into<Pins>>(port: Port, pins: P, interrupt_type: InterruptType) {
+    unsafe { raw::GPIOIntTypeSet(port.base(), pins.into().mask(), interrupt_type.value()); }
+}
+
+pub fn enable_interrupts<P: Into<Pins>>(port: Port, pins: P) {
+    unsafe { raw::GPIOIntEnable(port.base(), interrupt_flags(pins.into())); }
+}
+
+pub fn disable_interrupts<P: Into<Pins>>(port: Port, pins: P) {
+    unsafe { raw::GPIOIntDisable(port.base(), interrupt_flags(pins.into())); }
+}
+
+pub fn clear_interrupts<P: Into<Pins>>(port: Port, pins: P) {
+    unsafe { raw::GPIOIntClear(port.base(), interrupt_flags(pins.into())); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raw;
+    use sysctl::Peripheral;
+
+    #[test]
+    fn pins_combine_into_one_mask() {
+        let pins = Pin::P1 | Pin::P2 | Pin::P3;
+        assert_eq!(0x0E, pins.mask());
+        assert!(pins.contains(Pin::P2));
+        assert!(!pins.contains(Pin::P4));
+        assert_eq!(Pins::from(Pin::P4), Pins::none() | Pin::P4);
+    }
+
+    #[test]
+    fn interrupt_flags_are_the_pin_bits() {
+        assert_eq!(raw::GPIO_INT_PIN_4, interrupt_flags(Pin::P4.into()));
+        assert_eq!(raw::GPIO_INT_PIN_0 | raw::GPIO_INT_PIN_7, interrupt_flags(Pin::P0 | Pin::P7));
+    }
+
+    #[test]
+    fn ports_have_their_peripheral() {
+        assert_eq!(Peripheral::GpioF, Port::F.peripheral());
+        assert_eq!(Peripheral::GpioN, Port::N.peripheral());
+    }
+
+    #[test]
+    #[cfg(feature = "tm4c123gh6pm")]
+    fn tm4c123_ports_are_on_the_apb() {
+        assert_eq!(0x40025000, Port::F.base());
+    }
+
+    #[test]
+    #[cfg(feature = "tm4c1294ncpdt")]
+    fn tm4c129_ports_are_on_the_ahb() {
+        assert_eq!(0x4005D000, Port::F.base());
+        assert_eq!(0x40060000, Port::J.base());
+        assert_eq!(0x40064000, Port::N.base());
+    }
+
+    #[test]
+    fn pad_settings_have_their_driverlib_values() {
+        assert_eq!(0x1, Strength::Ma2.value());
+        assert_eq!(0x6E, Strength::Ma8SlewControl.value());
+        assert_eq!(0xA, PadType::StandardPullUp.value());
+        assert_eq!(0x0, InterruptType::FallingEdge.value());
+    }
+}
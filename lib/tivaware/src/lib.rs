@@ -0,0 +1,60 @@
+/*
+    Rust bindings for the TivaWare Peripheral Driver Library (driverlib).
+
+    `raw` is generated by bindgen from the driverlib headers (see driverlib.h), so it has every
+    driverlib function and every constant, including the pin functions in pin_map.h for the
+    selected part:
+
+        use tivaware::raw;
+
+        unsafe { raw::GPIOPinConfigure(raw::GPIO_PA0_U0RX); }
+
+    The other modules are a thin safe layer over the parts the firmware uses, which take enums
+    instead of the header's constants:
+
+        use tivaware::{gpio, sysctl};
+        use tivaware::gpio::{Port, Pin, Strength, PadType};
+
+        sysctl::enable(sysctl::Peripheral::GpioF);
+        gpio::set_input(Port::F, Pin::P4 | Pin::P0);
+        gpio::set_pad_config(Port::F, Pin::P4 | Pin::P0, Strength::Ma2, PadType::StandardPullUp);
+
+    The build compiles driverlib from lib/TivaWare for the part selected by the cargo features.
+*/
+
+#![no_std]
+
+pub mod eeprom;
+pub mod gpio;
+pub mod sysctl;
+pub mod systick;
+pub mod uart;
+
+// The C types bindgen uses in the raw bindings.
+#[allow(non_camel_case_types)]
+pub mod ctypes {
+    pub enum c_void {}
+    pub type c_char = u8;
+    pub type c_schar = i8;
+    pub type c_uchar = u8;
+    pub type c_short = i16;
+    pub type c_ushort = u16;
+    pub type c_int = i32;
+    pub type c_uint = u32;
+    #[cfg(target_pointer_width = "32")]
+    pub type c_long = i32;
+    #[cfg(target_pointer_width = "32")]
+    pub type c_ulong = u32;
+    #[cfg(target_pointer_width = "64")]
+    pub type c_long = i64;
+    #[cfg(target_pointer_width = "64")]
+    pub type c_ulong = u64;
+    pub type c_longlong = i64;
+    pub type c_ulonglong = u64;
+}
+
+// The driverlib functions and constants, as bindgen generated them from the headers.
+#[allow(non_upper_case_globals, non_camel_case_types, non_snake_case, dead_code)]
+pub mod raw {
+    include!(concat!(env!("OUT_DIR"), "/driverlib.rs"));
+}
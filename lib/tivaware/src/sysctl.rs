@@ -0,0 +1,155 @@
+/*
+    System control: turning peripherals on and off, and the system clock.
+*/
+
+use raw;
+
+macro_rules! peripherals {
+    ($($name:ident => $value:ident,)*) => {
+        // The peripherals which can be turned on. Not every part has all of them.
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        pub enum Peripheral {
+            $($name,)*
+        }
+
+        impl Peripheral {
+            // The driverlib SYSCTL_PERIPH_ value.
+            pub fn value(self) -> u32 {
+                match self {
+                    $(Peripheral::$name => raw::$value,)*
+                }
+            }
+        }
+    }
+}
+
+peripherals! {
+    Adc0 => SYSCTL_PERIPH_ADC0,
+    Adc1 => SYSCTL_PERIPH_ADC1,
+    Can0 => SYSCTL_PERIPH_CAN0,
+    Can1 => SYSCTL_PERIPH_CAN1,
+    Comp0 => SYSCTL_PERIPH_COMP0,
+    Eeprom0 => SYSCTL_PERIPH_EEPROM0,
+    GpioA => SYSCTL_PERIPH_GPIOA,
+    GpioB => SYSCTL_PERIPH_GPIOB,
+    GpioC => SYSCTL_PERIPH_GPIOC,
+    GpioD => SYSCTL_PERIPH_GPIOD,
+    GpioE => SYSCTL_PERIPH_GPIOE,
+    GpioF => SYSCTL_PERIPH_GPIOF,
+    GpioG => SYSCTL_PERIPH_GPIOG,
+    GpioH => SYSCTL_PERIPH_GPIOH,
+    GpioJ => SYSCTL_PERIPH_GPIOJ,
+    GpioK => SYSCTL_PERIPH_GPIOK,
+    GpioL => SYSCTL_PERIPH_GPIOL,
+    GpioM => SYSCTL_PERIPH_GPIOM,
+    GpioN => SYSCTL_PERIPH_GPION,
+    GpioP => SYSCTL_PERIPH_GPIOP,
+    GpioQ => SYSCTL_PERIPH_GPIOQ,
+    Hibernate => SYSCTL_PERIPH_HIBERNATE,
+    I2c0 => SYSCTL_PERIPH_I2C0,
+    I2c1 => SYSCTL_PERIPH_I2C1,
+    I2c2 => SYSCTL_PERIPH_I2C2,
+    I2c3 => SYSCTL_PERIPH_I2C3,
+    Pwm0 => SYSCTL_PERIPH_PWM0,
+    Pwm1 => SYSCTL_PERIPH_PWM1,
+    Qei0 => SYSCTL_PERIPH_QEI0,
+    Qei1 => SYSCTL_PERIPH_QEI1,
+    Ssi0 => SYSCTL_PERIPH_SSI0,
+    Ssi1 => SYSCTL_PERIPH_SSI1,
+    Ssi2 => SYSCTL_PERIPH_SSI2,
+    Ssi3 => SYSCTL_PERIPH_SSI3,
+    Timer0 => SYSCTL_PERIPH_TIMER0,
+    Timer1 => SYSCTL_PERIPH_TIMER1,
+    Timer2 => SYSCTL_PERIPH_TIMER2,
+    Timer3 => SYSCTL_PERIPH_TIMER3,
+    Timer4 => SYSCTL_PERIPH_TIMER4,
+    Timer5 => SYSCTL_PERIPH_TIMER5,
+    Uart0 => SYSCTL_PERIPH_UART0,
+    Uart1 => SYSCTL_PERIPH_UART1,
+    Uart2 => SYSCTL_PERIPH_UART2,
+    Uart3 => SYSCTL_PERIPH_UART3,
+    Uart4 => SYSCTL_PERIPH_UART4,
+    Uart5 => SYSCTL_PERIPH_UART5,
+    Uart6 => SYSCTL_PERIPH_UART6,
+    Uart7 => SYSCTL_PERIPH_UART7,
+    Udma => SYSCTL_PERIPH_UDMA,
+    Usb0 => SYSCTL_PERIPH_USB0,
+    Watchdog0 => SYSCTL_PERIPH_WDOG0,
+    Watchdog1 => SYSCTL_PERIPH_WDOG1,
+    WideTimer0 => SYSCTL_PERIPH_WTIMER0,
+    WideTimer1 => SYSCTL_PERIPH_WTIMER1,
+    WideTimer2 => SYSCTL_PERIPH_WTIMER2,
+    WideTimer3 => SYSCTL_PERIPH_WTIMER3,
+    WideTimer4 => SYSCTL_PERIPH_WTIMER4,
+    WideTimer5 => SYSCTL_PERIPH_WTIMER5,
+}
+
+// Turn the peripheral's clock on, and wait until it can be used. Its registers fault if they're
+// accessed too soon after it's enabled.
+pub fn enable(peripheral: Peripheral) {
+    unsafe {
+        raw::SysCtlPeripheralEnable(peripheral.value());
+        while !raw::SysCtlPeripheralReady(peripheral.value()) {}
+    }
+}
+
+pub fn disable(peripheral: Peripheral) {
+    unsafe { raw::SysCtlPeripheralDisable(peripheral.value()); }
+}
+
+// Put the peripheral back in its reset state.
+pub fn reset(peripheral: Peripheral) {
+    unsafe { raw::SysCtlPeripheralReset(peripheral.value()); }
+}
+
+// Reset the whole processor, as the reset button does.
+pub fn reset_system() -> ! {
+    unsafe { raw::SysCtlReset(); }
+    loop {}
+}
+
+pub fn is_present(peripheral: Peripheral) -> bool {
+    unsafe { raw::SysCtlPeripheralPresent(peripheral.value()) }
+}
+
+pub fn is_ready(peripheral: Peripheral) -> bool {
+    unsafe { raw::SysCtlPeripheralReady(peripheral.value()) }
+}
+
+// Configure the system clock of a TM4C123 part. The configuration is the SYSCTL_SYSDIV_,
+// SYSCTL_USE_, SYSCTL_OSC_ and SYSCTL_XTAL_ constants in `raw` or'ed together, as for
+// SysCtlClockSet.
+pub fn set_clock(config: u32) {
+    unsafe { raw::SysCtlClockSet(config); }
+}
+
+// The system clock frequency in Hz of a TM4C123 part. TM4C129 parts can't work it out, so keep
+// what set_clock_frequency returns instead.
+pub fn clock() -> u32 {
+    unsafe { raw::SysCtlClockGet() }
+}
+
+// Configure the system clock of a TM4C129 part to run as close to `frequency` Hz as it can. The
+// configuration is the SYSCTL_XTAL_, SYSCTL_OSC_, SYSCTL_USE_ and SYSCTL_CFG_VCO_ constants in
+// `raw` or'ed together, as for SysCtlClockFreqSet. Returns the frequency it runs at, or 0 if it
+// can't be configured like that.
+pub fn set_clock_frequency(config: u32, frequency: u32) -> u32 {
+    unsafe { raw::SysCtlClockFreqSet(config, frequency) }
+}
+
+// Busy wait. Each count is three instruction cycles.
+pub fn delay(count: u32) {
+    unsafe { raw::SysCtlDelay(count); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peripherals_have_their_driverlib_values() {
+        assert_eq!(0xf0000805, Peripheral::GpioF.value());
+        assert_eq!(0xf0001800, Peripheral::Uart0.value());
+        assert_eq!(0xf0005800, Peripheral::Eeprom0.value());
+    }
+}
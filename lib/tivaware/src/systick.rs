@@ -0,0 +1,36 @@
+/*
+    The SysTick timer, which counts down from its period at the system clock rate.
+*/
+
+use raw;
+
+// The number of clock ticks between SysTick interrupts. At most 2^24.
+pub fn set_period(ticks: u32) {
+    unsafe { raw::SysTickPeriodSet(ticks); }
+}
+
+pub fn enable() {
+    unsafe { raw::SysTickEnable(); }
+}
+
+pub fn disable() {
+    unsafe { raw::SysTickDisable(); }
+}
+
+pub fn enable_interrupt() {
+    unsafe { raw::SysTickIntEnable(); }
+}
+
+pub fn disable_interrupt() {
+    unsafe { raw::SysTickIntDisable(); }
+}
+
+// The number of clock ticks between SysTick interrupts.
+pub fn period() -> u32 {
+    unsafe { raw::SysTickPeriodGet() }
+}
+
+// The counter, which counts down from period() - 1 to 0.
+pub fn value() -> u32 {
+    unsafe { raw::SysTickValueGet() }
+}
@@ -0,0 +1,273 @@
+/*
+    The UARTs. Each UART is configured from the system clock frequency, so the baud rate is right
+    whatever the clock is set to:
+
+        uart::set_config(Uart::Uart0, clock_hz, 115200, Config::default());
+        uart::enable(Uart::Uart0);
+*/
+
+use core::ops::BitOr;
+use raw;
+use sysctl::Peripheral;
+
+macro_rules! uarts {
+    ($($name:ident => $base:ident, $peripheral:ident,)*) => {
+        // The UARTs. Not every part has all of them.
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        pub enum Uart {
+            $($name,)*
+        }
+
+        impl Uart {
+            // The address of the UART's registers.
+            pub fn base(self) -> u32 {
+                match self {
+                    $(Uart::$name => raw::$base,)*
+                }
+            }
+
+            // The peripheral to enable before using the UART.
+            pub fn peripheral(self) -> Peripheral {
+                match self {
+                    $(Uart::$name => Peripheral::$peripheral,)*
+                }
+            }
+        }
+    }
+}
+
+uarts! {
+    Uart0 => UART0_BASE, Uart0,
+    Uart1 => UART1_BASE, Uart1,
+    Uart2 => UART2_BASE, Uart2,
+    Uart3 => UART3_BASE, Uart3,
+    Uart4 => UART4_BASE, Uart4,
+    Uart5 => UART5_BASE, Uart5,
+    Uart6 => UART6_BASE, Uart6,
+    Uart7 => UART7_BASE, Uart7,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+    // The parity bit is always one or always zero.
+    One,
+    Zero,
+}
+
+// The frame format.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Config {
+    pub data_bits: DataBits,
+    pub stop_bits: StopBits,
+    pub parity: Parity,
+}
+
+impl Config {
+    // The driverlib UART_CONFIG_ value.
+    pub fn value(self) -> u32 {
+        let data_bits = match self.data_bits {
+            DataBits::Five => raw::UART_CONFIG_WLEN_5,
+            DataBits::Six => raw::UART_CONFIG_WLEN_6,
+            DataBits::Seven => raw::UART_CONFIG_WLEN_7,
+            DataBits::Eight => raw::UART_CONFIG_WLEN_8,
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => raw::UART_CONFIG_STOP_ONE,
+            StopBits::Two => raw::UART_CONFIG_STOP_TWO,
+        };
+        let parity = match self.parity {
+            Parity::None => raw::UART_CONFIG_PAR_NONE,
+            Parity::Even => raw::UART_CONFIG_PAR_EVEN,
+            Parity::Odd => raw::UART_CONFIG_PAR_ODD,
+            Parity::One => raw::UART_CONFIG_PAR_ONE,
+            Parity::Zero => raw::UART_CONFIG_PAR_ZERO,
+        };
+        data_bits | stop_bits | parity
+    }
+}
+
+// 8N1.
+impl Default for Config {
+    fn default() -> Config {
+        Config { data_bits: DataBits::Eight, stop_bits: StopBits::One, parity: Parity::None }
+    }
+}
+
+// How full (or, for transmit, how empty) the 16 byte FIFO gets before it interrupts.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FifoLevel {
+    OneEighth,
+    OneQuarter,
+    OneHalf,
+    ThreeQuarters,
+    SevenEighths,
+}
+
+impl FifoLevel {
+    fn tx_value(self) -> u32 {
+        match self {
+            FifoLevel::OneEighth => raw::UART_FIFO_TX1_8,
+            FifoLevel::OneQuarter => raw::UART_FIFO_TX2_8,
+            FifoLevel::OneHalf => raw::UART_FIFO_TX4_8,
+            FifoLevel::ThreeQuarters => raw::UART_FIFO_TX6_8,
+            FifoLevel::SevenEighths => raw::UART_FIFO_TX7_8,
+        }
+    }
+
+    fn rx_value(self) -> u32 {
+        match self {
+            FifoLevel::OneEighth => raw::UART_FIFO_RX1_8,
+            FifoLevel::OneQuarter => raw::UART_FIFO_RX2_8,
+            FifoLevel::OneHalf => raw::UART_FIFO_RX4_8,
+            FifoLevel::ThreeQuarters => raw::UART_FIFO_RX6_8,
+            FifoLevel::SevenEighths => raw::UART_FIFO_RX7_8,
+        }
+    }
+}
+
+// Some of a UART's interrupts. They combine with `|`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Interrupts(u32);
+
+pub const INT_RX: Interrupts = Interrupts(raw::UART_INT_RX);
+pub const INT_RX_TIMEOUT: Interrupts = Interrupts(raw::UART_INT_RT);
+pub const INT_TX: Interrupts = Interrupts(raw::UART_INT_TX);
+pub const INT_OVERRUN: Interrupts = Interrupts(raw::UART_INT_OE);
+pub const INT_BREAK: Interrupts = Interrupts(raw::UART_INT_BE);
+pub const INT_PARITY: Interrupts = Interrupts(raw::UART_INT_PE);
+pub const INT_FRAMING: Interrupts = Interrupts(raw::UART_INT_FE);
+
+impl Interrupts {
+    pub fn none() -> Interrupts {
+        Interrupts(0)
+    }
+
+    // The driverlib UART_INT_ flags.
+    pub fn value(self) -> u32 {
+        self.0
+    }
+
+    // Whether any of `other` is in these.
+    pub fn contains(self, other: Interrupts) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Interrupts {
+    type Output = Interrupts;
+
+    fn bitor(self, other: Interrupts) -> Interrupts {
+        Interrupts(self.0 | other.0)
+    }
+}
+
+// Set the baud rate and frame format. `clock` is the frequency the UART runs from, the system
+// clock unless the clock source has been changed.
+pub fn set_config(uart: Uart, clock: u32, baud: u32, config: Config) {
+    unsafe { raw::UARTConfigSetExpClk(uart.base(), clock, baud, config.value()); }
+}
+
+pub fn enable(uart: Uart) {
+    unsafe { raw::UARTEnable(uart.base()); }
+}
+
+pub fn disable(uart: Uart) {
+    unsafe { raw::UARTDisable(uart.base()); }
+}
+
+// Set the FIFO levels which trigger the transmit and receive interrupts.
+pub fn set_fifo_levels(uart: Uart, transmit: FifoLevel, receive: FifoLevel) {
+    unsafe { raw::UARTFIFOLevelSet(uart.base(), transmit.tx_value(), receive.rx_value()); }
+}
+
+// Whether there's a received byte waiting in the FIFO.
+pub fn is_byte_available(uart: Uart) -> bool {
+    unsafe { raw::UARTCharsAvail(uart.base()) }
+}
+
+// Whether there's room in the transmit FIFO.
+pub fn is_space_available(uart: Uart) -> bool {
+    unsafe { raw::UARTSpaceAvail(uart.base()) }
+}
+
+// The next received byte, if there is one. The receive error flags of the byte are dropped; they
+// also raise the error interrupts.
+pub fn read_byte(uart: Uart) -> Option<u8> {
+    match unsafe { raw::UARTCharGetNonBlocking(uart.base()) } {
+        -1 => None,
+        data => Some(data as u8),
+    }
+}
+
+// Put a byte in the transmit FIFO. False if the FIFO is full.
+pub fn write_byte(uart: Uart, byte: u8) -> bool {
+    unsafe { raw::UARTCharPutNonBlocking(uart.base(), byte) }
+}
+
+pub fn enable_interrupts(uart: Uart, interrupts: Interrupts) {
+    unsafe { raw::UARTIntEnable(uart.base(), interrupts.value()); }
+}
+
+pub fn disable_interrupts(uart: Uart, interrupts: Interrupts) {
+    unsafe { raw::UARTIntDisable(uart.base(), interrupts.value()); }
+}
+
+// The interrupts which are asserted. Masked leaves out the ones which aren't enabled.
+pub fn interrupt_status(uart: Uart, masked: bool) -> Interrupts {
+    unsafe { Interrupts(raw::UARTIntStatus(uart.base(), masked)) }
+}
+
+pub fn clear_interrupts(uart: Uart, interrupts: Interrupts) {
+    unsafe { raw::UARTIntClear(uart.base(), interrupts.value()); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sysctl::Peripheral;
+
+    #[test]
+    fn uarts_have_their_base_address_and_peripheral() {
+        assert_eq!(0x4000C000, Uart::Uart0.base());
+        assert_eq!(Peripheral::Uart0, Uart::Uart0.peripheral());
+    }
+
+    #[test]
+    fn the_default_config_is_8n1() {
+        assert_eq!(0x60, Config::default().value());
+        let config = Config { data_bits: DataBits::Seven, stop_bits: StopBits::Two, parity: Parity::Even };
+        assert_eq!(0x40 | 0x08 | 0x06, config.value());
+    }
+
+    #[test]
+    fn fifo_levels_have_their_driverlib_values() {
+        assert_eq!(0x02, FifoLevel::OneHalf.tx_value());
+        assert_eq!(0x10, FifoLevel::OneHalf.rx_value());
+    }
+
+    #[test]
+    fn interrupts_combine() {
+        let interrupts = INT_RX | INT_RX_TIMEOUT;
+        assert_eq!(0x50, interrupts.value());
+        assert!(interrupts.contains(INT_RX));
+        assert!(!interrupts.contains(INT_TX));
+        assert!(!Interrupts::none().contains(INT_RX));
+    }
+}
@@ -18,6 +18,13 @@ apt-get -y install qemu-system-arm
 # Install nightly rust. Do this as the vagrant user so it installs in the right place. 
 sudo -u vagrant HOME=/home/vagrant bash -c "curl https://sh.rustup.rs -sSf | sh -s -- -y --default-toolchain=nightly"
 
+# The host tools and the library crates' tests (rake test:host) use current stable.
+sudo -u vagrant HOME=/home/vagrant bash -c "~/.cargo/bin/rustup toolchain install stable"
+
+# bindgen needs libclang to generate the TivaWare bindings.
+apt-get -y install clang
+apt-get -y install libclang-dev
+
 # Need these to install xargo.
 apt-get -y install gcc
 apt-get -y install cmake
@@ -1,34 +1,146 @@
 
-# The location of the compiled TivaWare Peripheral Driver Library.
-driverlib = "lib/TivaWare/driverlib/gcc/libdriver.a"
+# The board to build for (see src/board), picked with BOARD=<board>. The default is the
+# EK-TM4C123GXL.
+board = ENV["BOARD"] || "ek-tm4c123gxl"
+# BINARY_LOG=1 sends log messages in the compact binary format (see tools/log_decoder).
+log_feature = ENV["BINARY_LOG"] ? " binary_log" : ""
+features = "--no-default-features --features \"libc_allocator #{board}#{log_feature}\""
+
+# The openocd configuration for the board. A custom board needs its own.
+openocd_config = ENV["OPENOCD_CONFIG"] || "board/#{board}.cfg"
+
 binary = "target/cortex-m4f/debug/rust-tiva-blinky"
+release_binary = "target/cortex-m4f/release/rust-tiva-blinky"
 
 task :default => :load
 
+# How far the release build may grow past its saved baseline (size/<board>/release.json) before
+# `rake test:size` fails. Save a new baseline when the growth is expected. build.rs holds the link
+# to the same budget for flash, with FLASH_MARGIN, so keep the two in step.
+release_flash_margin = 1024
+
+# Each board's linker script, the same as the BOARDS table in build.rs.
+LINKER_SCRIPTS = {
+    "ek-tm4c123gxl" => "src/board/ek_tm4c123gxl.ld",
+    "ek-tm4c1294xl" => "src/board/ek_tm4c1294xl.ld",
+    "custom-board" => "src/board/custom.ld",
+}
+
+# Report what fills FLASH and RAM in a build, compared against the baseline saved for its board and
+# profile. The JSON report is written next to the binary. With a margin, growing more than that past
+# the baseline fails.
+def size_report(binary, board, profile, margin = nil)
+    linker_script = LINKER_SCRIPTS.fetch(board)
+    sh "cargo run --quiet --manifest-path tools/size_report/Cargo.toml -- #{binary} #{linker_script}" +
+        " --baseline size/#{board}/#{profile}.json --json #{binary}.size.json" +
+        (margin ? " --margin #{margin}" : "")
+end
+
 task :build => "#{binary}"
 
+# The firmware and the crates only it builds need its nightly (the one provision.sh installs). The
+# crates the host tools share, and the tools, are tested with a current compiler. Either can be
+# picked with FIRMWARE_TOOLCHAIN=<toolchain> or HOST_TOOLCHAIN=<toolchain>.
+firmware_cargo = "cargo +#{ENV["FIRMWARE_TOOLCHAIN"] || "nightly"}"
+host_cargo = "cargo +#{ENV["HOST_TOOLCHAIN"] || "stable"}"
+
 desc "Run the tests"
-task :test do
-    sh %[cargo test]
+task :test => ["test:firmware", "test:size", "test:host"]
+
+namespace :test do
+    desc "Run the tests of the firmware and the crates only it builds, with the firmware's nightly"
+    task :firmware do
+        sh %[#{firmware_cargo} test]
+        sh %[#{firmware_cargo} test --no-default-features --features "libc_allocator ek-tm4c1294xl"]
+        sh %[#{firmware_cargo} test --manifest-path lib/tiva_registers/Cargo.toml]
+        sh %[#{firmware_cargo} test --manifest-path lib/tiva_registers/Cargo.toml --no-default-features --features tm4c1294ncpdt]
+        sh %[#{firmware_cargo} test --manifest-path lib/tivaware/Cargo.toml]
+        sh %[#{firmware_cargo} test --manifest-path lib/tivaware/Cargo.toml --no-default-features --features tm4c1294ncpdt]
+    end
+
+    desc "Check the release build against its baseline plus the margin"
+    task :size do
+        if File.exist?("size/#{board}/release.json")
+            Rake::Task[release_binary].invoke
+            size_report(release_binary, board, "release", release_flash_margin)
+        else
+            puts "No size/#{board}/release.json baseline to check against. Save one with rake size:baseline."
+        end
+    end
+
+    desc "Run the tests of the shared library crates and the host tools, with a current compiler"
+    task :host do
+        sh %[#{host_cargo} test --manifest-path lib/critical_section_arm/Cargo.toml]
+        sh %[#{host_cargo} test --manifest-path lib/heap_stats/Cargo.toml]
+        sh %[#{host_cargo} test --manifest-path lib/block_allocator/Cargo.toml]
+        sh %[#{host_cargo} test --manifest-path lib/libc_allocator/Cargo.toml]
+        sh %[#{host_cargo} test --manifest-path lib/register_codegen/Cargo.toml]
+        sh %[#{host_cargo} test --manifest-path lib/protocol/Cargo.toml]
+        sh %[#{host_cargo} test --manifest-path tools/size_report/Cargo.toml]
+        sh %[#{host_cargo} test --manifest-path tools/log_decoder/Cargo.toml]
+        sh %[#{host_cargo} test --manifest-path tools/device_client/Cargo.toml]
+    end
 end
 
-# The debug build requires driverlib to be built first.
 desc "Build the project in debug mode"
-task "#{binary}" => "#{driverlib}" do
+task "#{binary}" do
     puts "Building rust app with xargo..."
-    sh "xargo build --target cortex-m4f"
+    sh "xargo build --target cortex-m4f #{features}"
+    size_report(binary, board, "debug")
+end
+
+desc "Build the project in release mode"
+task "#{release_binary}" do
+    puts "Building rust app with xargo in release mode..."
+    sh "xargo build --target cortex-m4f --release #{features}"
+    size_report(release_binary, board, "release")
 end
 
 desc "Flash the applicaion onto the board"
 task :load => "#{binary}" do
     puts "Flashing with openocd..."
-    sh "openocd -f board/ek-tm4c123gxl.cfg -c \"program #{binary} verify reset exit\""
+    sh "openocd -f #{openocd_config} -c \"program #{binary} verify reset exit\""
+end
+
+namespace :release do
+    desc "Build the project in release mode"
+    task :build => "#{release_binary}"
+
+    desc "Flash the release build onto the board"
+    task :load => "#{release_binary}" do
+        puts "Flashing with openocd..."
+        sh "openocd -f #{openocd_config} -c \"program #{release_binary} verify reset exit\""
+    end
+end
+
+desc "Show the flash and RAM used by the debug and release builds"
+task :size => ["#{binary}", "#{release_binary}"] do
+    size_report(binary, board, "debug")
+    size_report(release_binary, board, "release")
+end
+
+namespace :size do
+    desc "Save the current size reports as the baselines to compare against"
+    task :baseline do
+        # The old baselines set the flash budget (see build.rs), so they go first, in case the
+        # build has grown past them.
+        rm_f ["size/#{board}/debug.json", "size/#{board}/release.json"]
+        Rake::Task[:size].invoke
+        mkdir_p "size/#{board}"
+        cp "#{binary}.size.json", "size/#{board}/debug.json"
+        cp "#{release_binary}.size.json", "size/#{board}/release.json"
+    end
+end
+
+desc "Decode a binary log from CAPTURE=<file>, or from stdin (like the serial port)"
+task :decode_log do
+    sh "cargo run --quiet --manifest-path tools/log_decoder/Cargo.toml -- #{binary} #{ENV["CAPTURE"]}"
 end
 
 desc "Start openocd"
 task :ocd do
     puts "Starting openocd..."
-    sh "openocd -f board/ek-tm4c123gxl.cfg"
+    sh "openocd -f #{openocd_config}"
 end
 
 desc "Start a debug session with gdb"
@@ -36,31 +148,7 @@ task :gdb => "#{binary}" do
     puts "Starting gdb..."
     sh "arm-none-eabi-gdb #{binary}" +
         %[ --ex "target remote :3333"] +
+        %[ --ex "monitor arm semihosting enable"] +
         %[ --ex load] +
         %[ --ex "tui enable"]
-end
-
-# Build instructions for the TivaWare Peripheral Driver Library (i.e. "driverlib").
-namespace :driverlib do
-
-    driverlib_src_path = "lib/TivaWare/driverlib"
-
-    desc "Build TivaWare Peripheral Driver Library"
-    task :build => "#{driverlib}"
-
-    file "#{driverlib}" do |task| 
-        puts "Building #{File.basename(task.name)}..."
-        chdir("#{driverlib_src_path}") do
-            sh "make"
-        end
-    end
-
-    desc "Clean TivaWare Peripheral Driver Library"
-    task :clean do |task|
-        puts "Cleaning #{File.basename(task.name)}..."
-        chdir("#{driverlib_src_path}") do
-            sh "make clean"
-        end
-    end
-
 end
\ No newline at end of file
@@ -0,0 +1,38 @@
+/*
+    A template for a board of your own. Change the pins and the clock for your board, the memory
+    in custom.ld for its part, and build with the `custom-board` feature instead of the default
+    board:
+
+        xargo build --target cortex-m4f --no-default-features --features "libc_allocator custom-board"
+
+    As it is, this is set up like the EK-TM4C123GXL.
+*/
+
+use board::Led;
+use tivaware::gpio::{Pin, Port};
+use tivaware::{raw, sysctl};
+
+// The LEDs the application shows its state with. They can be on different ports.
+pub const RED_LED: Led = Led { port: Port::F, pin: Pin::P1 };
+pub const BLUE_LED: Led = Led { port: Port::F, pin: Pin::P2 };
+pub const GREEN_LED: Led = Led { port: Port::F, pin: Pin::P3 };
+
+// The buttons, which must be on the same port and pull the pin low when pressed. The interrupt is
+// the port's, and button_interrupt! binds the button handler to it too.
+pub const BUTTON_PORT: Port = Port::F;
+pub const BUTTON_1_PIN: Pin = Pin::P4;
+pub const BUTTON_2_PIN: Pin = Pin::P0;
+button_interrupt!(GPIOF);
+
+// The pins UART0 is on, and their pin_map.h functions.
+pub const UART_PORT: Port = Port::A;
+pub const UART_RX_PIN: Pin = Pin::P0;
+pub const UART_TX_PIN: Pin = Pin::P1;
+pub const UART_RX_FUNCTION: u32 = raw::GPIO_PA0_U0RX;
+pub const UART_TX_FUNCTION: u32 = raw::GPIO_PA1_U0TX;
+
+// Set up the system clock for the board's crystal. Returns the system clock frequency in Hz.
+pub fn init_clock() -> u32 {
+    sysctl::set_clock(raw::SYSCTL_SYSDIV_1 | raw::SYSCTL_USE_OSC | raw::SYSCTL_OSC_MAIN | raw::SYSCTL_XTAL_16MHZ);
+    sysctl::clock()
+}
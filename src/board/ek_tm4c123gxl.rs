@@ -0,0 +1,32 @@
+/*
+    The Tiva C Series TM4C123G LaunchPad (EK-TM4C123GXL), with a TM4C123GH6PM.
+*/
+
+use board::Led;
+use tivaware::gpio::{Pin, Port};
+use tivaware::{raw, sysctl};
+
+// The three colors of the RGB LED.
+pub const RED_LED: Led = Led { port: Port::F, pin: Pin::P1 };
+pub const BLUE_LED: Led = Led { port: Port::F, pin: Pin::P2 };
+pub const GREEN_LED: Led = Led { port: Port::F, pin: Pin::P3 };
+
+// SW1 and SW2. They pull the pin low when pressed. SW2's pin, PF0, is locked (it can be the NMI)
+// and needs unlocking before it works as an input.
+pub const BUTTON_PORT: Port = Port::F;
+pub const BUTTON_1_PIN: Pin = Pin::P4;
+pub const BUTTON_2_PIN: Pin = Pin::P0;
+button_interrupt!(GPIOF);
+
+// UART0's pins, which go to the debugger's virtual COM port.
+pub const UART_PORT: Port = Port::A;
+pub const UART_RX_PIN: Pin = Pin::P0;
+pub const UART_TX_PIN: Pin = Pin::P1;
+pub const UART_RX_FUNCTION: u32 = raw::GPIO_PA0_U0RX;
+pub const UART_TX_FUNCTION: u32 = raw::GPIO_PA1_U0TX;
+
+// Run straight from the 16MHz crystal. Returns the system clock frequency in Hz.
+pub fn init_clock() -> u32 {
+    sysctl::set_clock(raw::SYSCTL_SYSDIV_1 | raw::SYSCTL_USE_OSC | raw::SYSCTL_OSC_MAIN | raw::SYSCTL_XTAL_16MHZ);
+    sysctl::clock()
+}
@@ -0,0 +1,38 @@
+/*
+    The Tiva C Series TM4C1294 Connected LaunchPad (EK-TM4C1294XL), with a TM4C1294NCPDT.
+*/
+
+use board::Led;
+use tivaware::gpio::{Pin, Port};
+use tivaware::{raw, sysctl};
+
+// The user LEDs, D1 to D3. D4 (PF0) isn't used.
+pub const RED_LED: Led = Led { port: Port::N, pin: Pin::P1 };
+pub const BLUE_LED: Led = Led { port: Port::N, pin: Pin::P0 };
+pub const GREEN_LED: Led = Led { port: Port::F, pin: Pin::P4 };
+
+// USR_SW1 and USR_SW2. They pull the pin low when pressed.
+pub const BUTTON_PORT: Port = Port::J;
+pub const BUTTON_1_PIN: Pin = Pin::P0;
+pub const BUTTON_2_PIN: Pin = Pin::P1;
+button_interrupt!(GPIOJ);
+
+// UART0's pins, which go to the debugger's virtual COM port.
+pub const UART_PORT: Port = Port::A;
+pub const UART_RX_PIN: Pin = Pin::P0;
+pub const UART_TX_PIN: Pin = Pin::P1;
+pub const UART_RX_FUNCTION: u32 = raw::GPIO_PA0_U0RX;
+pub const UART_TX_FUNCTION: u32 = raw::GPIO_PA1_U0TX;
+
+// The system clock, from the PLL. The PLL's VCO runs at 480MHz, which divides down to 120MHz.
+pub const SYSTEM_CLOCK_HZ: u32 = 120000000;
+
+// Run from the PLL off the 25MHz crystal, with the VCO at 480MHz. Returns the system clock
+// frequency in Hz.
+pub fn init_clock() -> u32 {
+    let frequency = sysctl::set_clock_frequency(raw::SYSCTL_XTAL_25MHZ | raw::SYSCTL_OSC_MAIN |
+                                                raw::SYSCTL_USE_PLL | raw::SYSCTL_CFG_VCO_480,
+                                                SYSTEM_CLOCK_HZ);
+    assert!(frequency == SYSTEM_CLOCK_HZ, "The PLL can't give the system clock frequency");
+    frequency
+}
@@ -0,0 +1,47 @@
+/*
+    The board the firmware runs on, selected by a cargo feature:
+
+        ek-tm4c123gxl   Tiva C Series TM4C123G LaunchPad (the default)
+        ek-tm4c1294xl   Tiva C Series TM4C1294 Connected LaunchPad
+        custom-board    A template for a board of your own (see custom.rs)
+
+    Every board module defines the same things: where the LEDs and buttons are, how the clock is
+    set up, and (in the .ld file of the same name) the memory layout and the device's interrupt
+    handlers for the linker script. build.rs picks the board's .ld file. The application only uses
+    what's here, so it builds the same for every board.
+*/
+
+use tivaware::gpio::{Pin, Port};
+
+// Declare the buttons' interrupt, the interrupt of their port, as BUTTON_INTERRUPT and bind the
+// button handler to it:
+//
+//     button_interrupt!(GPIOF);
+macro_rules! button_interrupt {
+    ($name:ident) => {
+        pub const BUTTON_INTERRUPT: ::device::Interrupt = ::device::interrupt::$name;
+        interrupt!($name, ::button::handler);
+    };
+}
+
+// An LED, lit by driving its pin high.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Led {
+    pub port: Port,
+    pub pin: Pin,
+}
+
+#[cfg(feature = "ek-tm4c123gxl")]
+mod ek_tm4c123gxl;
+#[cfg(feature = "ek-tm4c123gxl")]
+pub use self::ek_tm4c123gxl::*;
+
+#[cfg(feature = "ek-tm4c1294xl")]
+mod ek_tm4c1294xl;
+#[cfg(feature = "ek-tm4c1294xl")]
+pub use self::ek_tm4c1294xl::*;
+
+#[cfg(feature = "custom-board")]
+mod custom;
+#[cfg(feature = "custom-board")]
+pub use self::custom::*;
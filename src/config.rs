@@ -0,0 +1,432 @@
+/*
+    The settings kept in the EEPROM across resets: the state machine's flash count and times.
+
+    The EEPROM holds two records, each in its own block. A record is:
+
+        [header: magic, version, field count] [sequence] [fields...] [CRC-16]
+
+    Loading takes the valid record with the higher sequence number. Saving writes the other slot
+    with the next sequence number, so if the power fails part way through, the record before it
+    is still there and its CRC still checks. With no valid record (a new board) the settings are
+    DEFAULTS.
+
+    New fields are added at the end, keeping the version. A record with fewer fields (from older
+    firmware) gets the defaults for the rest, and one with more (from newer firmware) has the
+    extras ignored. Any other change, like a field changing its units, needs a new VERSION and a
+    case in `migrate` to convert the older records. Records of a later version can't be read, so
+    the settings are the defaults. Records which aren't the current version and size are written
+    back the next time the settings are saved.
+*/
+
+use protocol::crc::crc16;
+use tivaware::eeprom::{self, ProgramError, BLOCK_WORDS};
+use tivaware::sysctl::{self, Peripheral};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Config {
+    pub flash_count: u32,
+    // Times are in 10 Hz ticks.
+    pub led_on_time: u32,
+    pub led_off_time: u32,
+    pub wait_time: u32,
+}
+
+pub const DEFAULTS: Config = Config { flash_count: 1, led_on_time: 4, led_off_time: 3, wait_time: 20 };
+
+// The settings in a Config, for reading or changing them one at a time.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Setting {
+    FlashCount,
+    LedOnTime,
+    LedOffTime,
+    WaitTime,
+}
+
+// The most flashes, and the longest time in ticks (a minute).
+pub const MAX_FLASH_COUNT: u32 = 50;
+pub const MAX_TIME: u32 = 600;
+
+impl Setting {
+    // The least and most the setting can be.
+    pub fn range(self) -> (u32, u32) {
+        match self {
+            Setting::FlashCount => (1, MAX_FLASH_COUNT),
+            // With no on time the flash never finishes.
+            Setting::LedOnTime => (1, MAX_TIME),
+            Setting::LedOffTime | Setting::WaitTime => (0, MAX_TIME),
+        }
+    }
+}
+
+// The number of fields in Config, and their settings in order.
+const FIELDS: usize = 4;
+const SETTINGS: [Setting; FIELDS] = [Setting::FlashCount, Setting::LedOnTime, Setting::LedOffTime, Setting::WaitTime];
+
+impl Config {
+    pub fn get(&self, setting: Setting) -> u32 {
+        match setting {
+            Setting::FlashCount => self.flash_count,
+            Setting::LedOnTime => self.led_on_time,
+            Setting::LedOffTime => self.led_off_time,
+            Setting::WaitTime => self.wait_time,
+        }
+    }
+
+    // Change a setting, as long as the value is in its range.
+    pub fn set(&mut self, setting: Setting, value: u32) -> Result<(), &'static str> {
+        let (least, most) = setting.range();
+        if value < least || value > most {
+            return Err("Out of range");
+        }
+        match setting {
+            Setting::FlashCount => self.flash_count = value,
+            Setting::LedOnTime => self.led_on_time = value,
+            Setting::LedOffTime => self.led_off_time = value,
+            Setting::WaitTime => self.wait_time = value,
+        }
+        Ok(())
+    }
+
+    // Whether every setting is in its range.
+    pub fn is_valid(&self) -> bool {
+        SETTINGS.iter().all(|&setting| {
+            let (least, most) = setting.range();
+            self.get(setting) >= least && self.get(setting) <= most
+        })
+    }
+
+    fn fields(&self) -> [u32; FIELDS] {
+        [self.flash_count, self.led_on_time, self.led_off_time, self.wait_time]
+    }
+
+    // The config with the fields given, in order, and the defaults for any after them.
+    fn from_fields(fields: &[u32]) -> Config {
+        let mut all = DEFAULTS.fields();
+        for (field, &value) in all.iter_mut().zip(fields) {
+            *field = value;
+        }
+        Config { flash_count: all[0], led_on_time: all[1], led_off_time: all[2], wait_time: all[3] }
+    }
+}
+
+// The version of the records written.
+const VERSION: u8 = 1;
+
+const MAGIC: u32 = 0xC0F1;
+
+// Each record has a block to itself.
+const SLOTS: [u32; 2] = [0, BLOCK_WORDS * 4];
+const SLOT_WORDS: usize = BLOCK_WORDS as usize;
+
+// The words around the fields: the header and sequence before, and the CRC after.
+const OVERHEAD: usize = 3;
+
+// Bring a record's fields up to date. Every version so far has had the same fields, so there's
+// nothing to convert yet.
+fn migrate(_version: u8, fields: &[u32]) -> Config {
+    Config::from_fields(fields)
+}
+
+// Where the records are: the EEPROM on the target, and memory in the tests.
+pub trait Eeprom {
+    fn read(&mut self, words: &mut [u32], address: u32);
+    fn program(&mut self, words: &[u32], address: u32) -> Result<(), ProgramError>;
+}
+
+// The chip's EEPROM.
+pub struct Internal;
+
+impl Eeprom for Internal {
+    fn read(&mut self, words: &mut [u32], address: u32) {
+        eeprom::read(words, address);
+    }
+
+    fn program(&mut self, words: &[u32], address: u32) -> Result<(), ProgramError> {
+        eeprom::program(words, address)
+    }
+}
+
+// Open the settings in the chip's EEPROM. None if the EEPROM can't be used.
+pub fn open() -> Option<Store<Internal>> {
+    sysctl::enable(Peripheral::Eeprom0);
+    match eeprom::init() {
+        Ok(()) => Some(Store::open(Internal)),
+        Err(_) => None,
+    }
+}
+
+pub struct Store<E: Eeprom> {
+    eeprom: E,
+    // The settings last loaded or saved.
+    config: Config,
+    // The newest record's sequence number and slot.
+    sequence: u32,
+    slot: usize,
+    // The newest record isn't in the current format.
+    stale: bool,
+}
+
+impl<E: Eeprom> Store<E> {
+    // Load the newest record from the EEPROM.
+    pub fn open(mut eeprom: E) -> Store<E> {
+        let records = [load(&mut eeprom, 0), load(&mut eeprom, 1)];
+        let newest = match (records[0], records[1]) {
+            (Some(a), Some(b)) => if newer(b.sequence, a.sequence) { 1 } else { 0 },
+            (None, Some(_)) => 1,
+            _ => 0,
+        };
+        match records[newest] {
+            Some(record) => Store {
+                eeprom,
+                config: record.config,
+                sequence: record.sequence,
+                slot: newest,
+                stale: record.stale,
+            },
+            // Nothing saved yet. The first save goes in the first slot.
+            None => Store { eeprom, config: DEFAULTS, sequence: 0, slot: 1, stale: false },
+        }
+    }
+
+    // The settings last loaded or saved.
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    // Save the settings if they've changed. The save isn't tried again if it fails, until the
+    // settings change again.
+    pub fn save(&mut self, config: &Config) -> Result<(), ProgramError> {
+        if *config == self.config && !self.stale {
+            return Ok(());
+        }
+        self.config = *config;
+        self.stale = false;
+
+        let sequence = self.sequence.wrapping_add(1);
+        let slot = 1 - self.slot;
+        let mut record = [0; FIELDS + OVERHEAD];
+        record[0] = MAGIC << 16 | (VERSION as u32) << 8 | FIELDS as u32;
+        record[1] = sequence;
+        record[2..2 + FIELDS].copy_from_slice(&config.fields());
+        record[2 + FIELDS] = crc(&record[..2 + FIELDS]);
+        self.eeprom.program(&record, SLOTS[slot])?;
+
+        self.sequence = sequence;
+        self.slot = slot;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Record {
+    config: Config,
+    sequence: u32,
+    stale: bool,
+}
+
+// The record in a slot, if there's a valid one.
+fn load<E: Eeprom>(eeprom: &mut E, slot: usize) -> Option<Record> {
+    let mut words = [0; SLOT_WORDS];
+    eeprom.read(&mut words, SLOTS[slot]);
+
+    let header = words[0];
+    let version = (header >> 8) as u8;
+    let count = (header & 0xFF) as usize;
+    if header >> 16 != MAGIC || version == 0 || version > VERSION || count + OVERHEAD > SLOT_WORDS {
+        return None;
+    }
+    if words[2 + count] != crc(&words[..2 + count]) {
+        return None;
+    }
+    // Settings out of range would upset the state machine on every boot.
+    let config = migrate(version, &words[2..2 + count]);
+    if !config.is_valid() {
+        return None;
+    }
+    Some(Record {
+        config,
+        sequence: words[1],
+        stale: version != VERSION || count != FIELDS,
+    })
+}
+
+// Whether sequence number `a` comes after `b`, allowing for them wrapping around.
+fn newer(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+// The CRC of the words, as they're laid out in the EEPROM.
+fn crc(words: &[u32]) -> u32 {
+    let mut crc = 0;
+    for &word in words {
+        crc = crc16(crc, &[word as u8, (word >> 8) as u8, (word >> 16) as u8, (word >> 24) as u8]);
+    }
+    crc as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An EEPROM in memory, which can be made to lose power after programming some words.
+    struct Memory {
+        words: [u32; SLOT_WORDS * 2],
+        // How many more words can be programmed before the power fails.
+        power: usize,
+    }
+
+    impl Memory {
+        fn new() -> Memory {
+            // Erased EEPROM reads as all ones.
+            Memory { words: [0xFFFFFFFF; SLOT_WORDS * 2], power: usize::max_value() }
+        }
+    }
+
+    impl<'a> Eeprom for &'a mut Memory {
+        fn read(&mut self, words: &mut [u32], address: u32) {
+            let start = address as usize / 4;
+            let len = words.len();
+            words.copy_from_slice(&self.words[start..start + len]);
+        }
+
+        fn program(&mut self, words: &[u32], address: u32) -> Result<(), ProgramError> {
+            let start = address as usize / 4;
+            for (i, &word) in words.iter().enumerate() {
+                if self.power == 0 {
+                    return Err(ProgramError(0));
+                }
+                self.power -= 1;
+                self.words[start + i] = word;
+            }
+            Ok(())
+        }
+    }
+
+    const CHANGED: Config = Config { flash_count: 5, led_on_time: 2, led_off_time: 6, wait_time: 10 };
+
+    #[test]
+    fn a_blank_eeprom_has_the_defaults() {
+        let mut memory = Memory::new();
+        assert_eq!(DEFAULTS, Store::open(&mut memory).config());
+    }
+
+    #[test]
+    fn saved_settings_are_loaded() {
+        let mut memory = Memory::new();
+        Store::open(&mut memory).save(&CHANGED).unwrap();
+        assert_eq!(CHANGED, Store::open(&mut memory).config());
+
+        // Each save goes in the other slot.
+        let mut config = CHANGED;
+        {
+            let mut store = Store::open(&mut memory);
+            for count in 6..10 {
+                config.flash_count = count;
+                store.save(&config).unwrap();
+            }
+        }
+        assert_eq!(config, Store::open(&mut memory).config());
+    }
+
+    #[test]
+    fn unchanged_settings_arent_written() {
+        let mut memory = Memory::new();
+        memory.power = 0;
+        assert_eq!(Ok(()), Store::open(&mut memory).save(&DEFAULTS));
+    }
+
+    #[test]
+    fn a_save_interrupted_by_power_loss_leaves_the_last_settings() {
+        let mut memory = Memory::new();
+        Store::open(&mut memory).save(&CHANGED).unwrap();
+        for words in 0..FIELDS + OVERHEAD {
+            let mut copy = Memory { words: memory.words, power: words };
+            let mut config = CHANGED;
+            config.wait_time = 99;
+            assert!(Store::open(&mut copy).save(&config).is_err());
+            assert_eq!(CHANGED, Store::open(&mut copy).config());
+        }
+    }
+
+    #[test]
+    fn corrupted_records_are_ignored() {
+        let mut memory = Memory::new();
+        {
+            let mut store = Store::open(&mut memory);
+            store.save(&CHANGED).unwrap();
+            store.save(&DEFAULTS).unwrap();
+        }
+        // The newest record is in the second slot.
+        memory.words[SLOT_WORDS + 3] ^= 0x100;
+        assert_eq!(CHANGED, Store::open(&mut memory).config());
+    }
+
+    #[test]
+    fn records_with_settings_out_of_range_are_ignored() {
+        let mut memory = Memory::new();
+        Store::open(&mut memory).save(&CHANGED).unwrap();
+        let mut record = [MAGIC << 16 | (VERSION as u32) << 8 | 4, 2, 1, 0xFFFFFFFF, 3, 20, 0];
+        record[6] = crc(&record[..6]);
+        memory.words[SLOT_WORDS..SLOT_WORDS + 7].copy_from_slice(&record);
+        assert_eq!(CHANGED, Store::open(&mut memory).config());
+    }
+
+    #[test]
+    fn settings_are_kept_in_their_ranges() {
+        let mut config = DEFAULTS;
+        assert_eq!(Ok(()), config.set(Setting::LedOffTime, 0));
+        assert_eq!(Ok(()), config.set(Setting::WaitTime, MAX_TIME));
+        assert!(config.set(Setting::LedOnTime, 0).is_err());
+        assert!(config.set(Setting::LedOnTime, 0xFFFFFFFF).is_err());
+        assert!(config.set(Setting::FlashCount, 0).is_err());
+        assert!(config.set(Setting::FlashCount, MAX_FLASH_COUNT + 1).is_err());
+        assert_eq!(Config { led_off_time: 0, wait_time: MAX_TIME, ..DEFAULTS }, config);
+        assert!(config.is_valid());
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_around() {
+        assert!(newer(1, 0));
+        assert!(newer(0, 0xFFFFFFFF));
+        assert!(!newer(0xFFFFFFFF, 0));
+    }
+
+    // A record as an older firmware with only the first two fields would have written it.
+    #[test]
+    fn older_records_get_the_defaults_for_new_fields_and_are_rewritten() {
+        let mut memory = Memory::new();
+        let mut record = [MAGIC << 16 | 1 << 8 | 2, 7, 9, 8, 0];
+        record[4] = crc(&record[..4]);
+        memory.words[..5].copy_from_slice(&record);
+
+        let config = Config { flash_count: 9, led_on_time: 8, ..DEFAULTS };
+        {
+            let mut store = Store::open(&mut memory);
+            assert_eq!(config, store.config());
+            store.save(&config).unwrap();
+        }
+        assert_eq!(MAGIC << 16 | (VERSION as u32) << 8 | FIELDS as u32, memory.words[SLOT_WORDS]);
+        assert_eq!(8, memory.words[SLOT_WORDS + 1]);
+        assert_eq!(config, Store::open(&mut memory).config());
+    }
+
+    // A record from a newer firmware which added a field.
+    #[test]
+    fn records_with_more_fields_keep_the_ones_this_version_knows() {
+        let mut memory = Memory::new();
+        let mut record = [MAGIC << 16 | 1 << 8 | 5, 1, 2, 3, 4, 5, 6, 0];
+        record[7] = crc(&record[..7]);
+        memory.words[..8].copy_from_slice(&record);
+        assert_eq!(Config { flash_count: 2, led_on_time: 3, led_off_time: 4, wait_time: 5 },
+                   Store::open(&mut memory).config());
+    }
+
+    #[test]
+    fn later_versions_cant_be_read() {
+        let mut memory = Memory::new();
+        let mut record = [MAGIC << 16 | ((VERSION + 1) as u32) << 8 | 4, 1, 2, 3, 4, 5, 0];
+        record[6] = crc(&record[..6]);
+        memory.words[..7].copy_from_slice(&record);
+        assert_eq!(DEFAULTS, Store::open(&mut memory).config());
+    }
+}
@@ -0,0 +1,137 @@
+/*
+    Device specific interrupt definitions.
+
+    Each device lists its interrupts (in IRQ order) with the `device_interrupts!` macro. This
+    generates the `interrupt` module of named interrupt numbers and the interrupt section of the
+    vector table. Every named interrupt has a symbol of the same name which the linker defaults to
+    `default_handler` (see the device's .ld file). Bind a handler to an interrupt with the
+    `interrupt!` macro, which overrides that symbol.
+*/
+
+// An entry in the vector table.
+pub type Handler = unsafe extern "C" fn();
+
+// An interrupt request number. This is the position in the interrupt section of the vector
+// table, not the exception number.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Interrupt(pub u8);
+
+impl Interrupt {
+    // The number of the interrupt as seen by the NVIC.
+    pub fn number(self) -> u32 {
+        self.0 as u32
+    }
+
+    // The exception (vector) number of the interrupt. The first 16 vectors are the processor
+    // exceptions. This is the numbering used by the TivaWare INT_* constants.
+    pub fn vector(self) -> u32 {
+        self.0 as u32 + 16
+    }
+}
+
+// Declare the interrupts for a device. Takes the number of interrupts followed by every
+// interrupt slot in order, with `_` for reserved slots:
+//
+//     device_interrupts! {
+//         count: 3;
+//         GPIOA,
+//         _,
+//         UART0,
+//     }
+//
+// Each interrupt's number is its position in the list, the same as its slot in the vector table.
+// The count must match the number of slots listed or the vector table won't compile.
+macro_rules! device_interrupts {
+    (count: $count:expr; $($slot:tt,)*) => {
+        // The number of interrupts in the vector table for this device.
+        pub const NUM_INTERRUPTS: usize = $count;
+
+        // The named interrupts of this device.
+        pub mod interrupt {
+            use device::Interrupt;
+            __interrupt_constants!(0; $($slot,)*);
+        }
+
+        // An interrupt by its name in the `interrupt` module, for the nvic shell command.
+        pub fn find(name: &str) -> Option<::device::Interrupt> {
+            $( __interrupt_find!(name, $slot); )*
+            None
+        }
+
+        // The handler symbols. These are defined with `interrupt!` or default to
+        // `default_handler` in the linker script.
+        $( __interrupt_symbol!($slot); )*
+
+        // The interrupt section of the vector table, directly after the SysTick exception.
+        #[cfg(target_os = "none")]
+        #[link_section = ".interrupts"]
+        #[no_mangle]
+        pub static INTERRUPTS: [Option<::device::Handler>; NUM_INTERRUPTS] = [
+            $( __interrupt_slot!($slot), )*
+        ];
+    };
+}
+
+// A constant for each named slot, numbered by counting the slots before it. This recurses once
+// per slot, hence the raised recursion_limit in main.rs.
+macro_rules! __interrupt_constants {
+    ($n:expr;) => {};
+    ($n:expr; _, $($rest:tt,)*) => {
+        __interrupt_constants!($n + 1; $($rest,)*);
+    };
+    ($n:expr; $name:ident, $($rest:tt,)*) => {
+        pub const $name: Interrupt = Interrupt($n);
+        __interrupt_constants!($n + 1; $($rest,)*);
+    };
+}
+
+macro_rules! __interrupt_find {
+    ($name:expr, _) => {};
+    ($name:expr, $slot:ident) => {
+        if $name == stringify!($slot) {
+            return Some(interrupt::$slot);
+        }
+    };
+}
+
+macro_rules! __interrupt_symbol {
+    (_) => {};
+    ($name:ident) => {
+        #[cfg(target_os = "none")]
+        extern {
+            fn $name();
+        }
+    };
+}
+
+macro_rules! __interrupt_slot {
+    (_) => { None };
+    ($name:ident) => { Some($name) };
+}
+
+// Bind a handler function to a named interrupt of the device. Using a name which isn't one of
+// the device's interrupts is a compile error.
+//
+//     interrupt!(GPIOF, ::button::handler);
+macro_rules! interrupt {
+    ($name:ident, $handler:path) => {
+        #[allow(non_snake_case)]
+        #[no_mangle]
+        pub extern "C" fn $name() {
+            // Make sure this is an actual interrupt for this device.
+            let _ = ::device::interrupt::$name;
+            $handler();
+        }
+    };
+}
+
+// The device on the board selected by the cargo features.
+#[cfg(feature = "tm4c123gh6pm")]
+pub mod tm4c123gh6pm;
+#[cfg(feature = "tm4c123gh6pm")]
+pub use self::tm4c123gh6pm::*;
+
+#[cfg(feature = "tm4c1294ncpdt")]
+pub mod tm4c1294ncpdt;
+#[cfg(feature = "tm4c1294ncpdt")]
+pub use self::tm4c1294ncpdt::*;
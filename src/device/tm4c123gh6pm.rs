@@ -0,0 +1,158 @@
+/*
+    Interrupts of the TM4C123GH6PM, taken from the TM4C123 class in TivaWare's hw_ints.h.
+*/
+
+device_interrupts! {
+    count: 139;
+    GPIOA,      //   0: GPIO Port A
+    GPIOB,      //   1: GPIO Port B
+    GPIOC,      //   2: GPIO Port C
+    GPIOD,      //   3: GPIO Port D
+    GPIOE,      //   4: GPIO Port E
+    UART0,      //   5: UART0
+    UART1,      //   6: UART1
+    SSI0,       //   7: SSI0
+    I2C0,       //   8: I2C0
+    PWM0_FAULT, //   9: PWM0 Fault
+    PWM0_0,     //  10: PWM0 Generator 0
+    PWM0_1,     //  11: PWM0 Generator 1
+    PWM0_2,     //  12: PWM0 Generator 2
+    QEI0,       //  13: QEI0
+    ADC0SS0,    //  14: ADC0 Sequence 0
+    ADC0SS1,    //  15: ADC0 Sequence 1
+    ADC0SS2,    //  16: ADC0 Sequence 2
+    ADC0SS3,    //  17: ADC0 Sequence 3
+    WATCHDOG,   //  18: Watchdog Timers 0 and 1
+    TIMER0A,    //  19: 16/32-Bit Timer 0A
+    TIMER0B,    //  20: 16/32-Bit Timer 0B
+    TIMER1A,    //  21: 16/32-Bit Timer 1A
+    TIMER1B,    //  22: 16/32-Bit Timer 1B
+    TIMER2A,    //  23: 16/32-Bit Timer 2A
+    TIMER2B,    //  24: 16/32-Bit Timer 2B
+    COMP0,      //  25: Analog Comparator 0
+    COMP1,      //  26: Analog Comparator 1
+    COMP2,      //  27: Analog Comparator 2
+    SYSCTL,     //  28: System Control
+    FLASH,      //  29: Flash Memory Control and EEPROM
+    GPIOF,      //  30: GPIO Port F
+    GPIOG,      //  31: GPIO Port G
+    GPIOH,      //  32: GPIO Port H
+    UART2,      //  33: UART2
+    SSI1,       //  34: SSI1
+    TIMER3A,    //  35: 16/32-Bit Timer 3A
+    TIMER3B,    //  36: Timer 3B
+    I2C1,       //  37: I2C1
+    QEI1,       //  38: QEI1
+    CAN0,       //  39: CAN0
+    CAN1,       //  40: CAN1
+    _,          //  41: Reserved
+    _,          //  42: Reserved
+    HIBERNATE,  //  43: Hibernation Module
+    USB0,       //  44: USB
+    PWM0_3,     //  45: PWM Generator 3
+    UDMA,       //  46: uDMA Software
+    UDMAERR,    //  47: uDMA Error
+    ADC1SS0,    //  48: ADC1 Sequence 0
+    ADC1SS1,    //  49: ADC1 Sequence 1
+    ADC1SS2,    //  50: ADC1 Sequence 2
+    ADC1SS3,    //  51: ADC1 Sequence 3
+    _,          //  52: Reserved
+    _,          //  53: Reserved
+    GPIOJ,      //  54: GPIO Port J
+    GPIOK,      //  55: GPIO Port K
+    GPIOL,      //  56: GPIO Port L
+    SSI2,       //  57: SSI2
+    SSI3,       //  58: SSI3
+    UART3,      //  59: UART3
+    UART4,      //  60: UART4
+    UART5,      //  61: UART5
+    UART6,      //  62: UART6
+    UART7,      //  63: UART7
+    _,          //  64: Reserved
+    _,          //  65: Reserved
+    _,          //  66: Reserved
+    _,          //  67: Reserved
+    I2C2,       //  68: I2C2
+    I2C3,       //  69: I2C3
+    TIMER4A,    //  70: 16/32-Bit Timer 4A
+    TIMER4B,    //  71: 16/32-Bit Timer 4B
+    _,          //  72: Reserved
+    _,          //  73: Reserved
+    _,          //  74: Reserved
+    _,          //  75: Reserved
+    _,          //  76: Reserved
+    _,          //  77: Reserved
+    _,          //  78: Reserved
+    _,          //  79: Reserved
+    _,          //  80: Reserved
+    _,          //  81: Reserved
+    _,          //  82: Reserved
+    _,          //  83: Reserved
+    _,          //  84: Reserved
+    _,          //  85: Reserved
+    _,          //  86: Reserved
+    _,          //  87: Reserved
+    _,          //  88: Reserved
+    _,          //  89: Reserved
+    _,          //  90: Reserved
+    _,          //  91: Reserved
+    TIMER5A,    //  92: 16/32-Bit Timer 5A
+    TIMER5B,    //  93: 16/32-Bit Timer 5B
+    WTIMER0A,   //  94: 32/64-Bit Timer 0A
+    WTIMER0B,   //  95: 32/64-Bit Timer 0B
+    WTIMER1A,   //  96: 32/64-Bit Timer 1A
+    WTIMER1B,   //  97: 32/64-Bit Timer 1B
+    WTIMER2A,   //  98: 32/64-Bit Timer 2A
+    WTIMER2B,   //  99: 32/64-Bit Timer 2B
+    WTIMER3A,   // 100: 32/64-Bit Timer 3A
+    WTIMER3B,   // 101: 32/64-Bit Timer 3B
+    WTIMER4A,   // 102: 32/64-Bit Timer 4A
+    WTIMER4B,   // 103: 32/64-Bit Timer 4B
+    WTIMER5A,   // 104: 32/64-Bit Timer 5A
+    WTIMER5B,   // 105: 32/64-Bit Timer 5B
+    SYSEXC,     // 106: System Exception (imprecise)
+    _,          // 107: Reserved
+    _,          // 108: Reserved
+    I2C4,       // 109: I2C4
+    I2C5,       // 110: I2C5
+    GPIOM,      // 111: GPIO Port M
+    GPION,      // 112: GPIO Port N
+    _,          // 113: Reserved
+    _,          // 114: Reserved
+    _,          // 115: Reserved
+    GPIOP0,     // 116: GPIO Port P (Summary or P0)
+    GPIOP1,     // 117: GPIO Port P1
+    GPIOP2,     // 118: GPIO Port P2
+    GPIOP3,     // 119: GPIO Port P3
+    GPIOP4,     // 120: GPIO Port P4
+    GPIOP5,     // 121: GPIO Port P5
+    GPIOP6,     // 122: GPIO Port P6
+    GPIOP7,     // 123: GPIO Port P7
+    GPIOQ0,     // 124: GPIO Port Q (Summary or Q0)
+    GPIOQ1,     // 125: GPIO Port Q1
+    GPIOQ2,     // 126: GPIO Port Q2
+    GPIOQ3,     // 127: GPIO Port Q3
+    GPIOQ4,     // 128: GPIO Port Q4
+    GPIOQ5,     // 129: GPIO Port Q5
+    GPIOQ6,     // 130: GPIO Port Q6
+    GPIOQ7,     // 131: GPIO Port Q7
+    _,          // 132: Reserved
+    _,          // 133: Reserved
+    PWM1_0,     // 134: PWM1 Generator 0
+    PWM1_1,     // 135: PWM1 Generator 1
+    PWM1_2,     // 136: PWM1 Generator 2
+    PWM1_3,     // 137: PWM1 Generator 3
+    PWM1_FAULT, // 138: PWM1 Fault
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_interrupt_numbers_match_the_tivaware_vector_numbers() {
+        assert_eq!(46, interrupt::GPIOF.vector());
+        assert_eq!(21, interrupt::UART0.vector());
+        assert_eq!(154, interrupt::PWM1_FAULT.vector());
+    }
+}
@@ -0,0 +1,132 @@
+/*
+    Interrupts of the TM4C1294NCPDT, taken from the TM4C129 class in TivaWare's hw_ints.h.
+*/
+
+device_interrupts! {
+    count: 113;
+    GPIOA,      //   0: GPIO Port A
+    GPIOB,      //   1: GPIO Port B
+    GPIOC,      //   2: GPIO Port C
+    GPIOD,      //   3: GPIO Port D
+    GPIOE,      //   4: GPIO Port E
+    UART0,      //   5: UART0
+    UART1,      //   6: UART1
+    SSI0,       //   7: SSI0
+    I2C0,       //   8: I2C0
+    PWM0_FAULT, //   9: PWM Fault
+    PWM0_0,     //  10: PWM Generator 0
+    PWM0_1,     //  11: PWM Generator 1
+    PWM0_2,     //  12: PWM Generator 2
+    QEI0,       //  13: QEI0
+    ADC0SS0,    //  14: ADC0 Sequence 0
+    ADC0SS1,    //  15: ADC0 Sequence 1
+    ADC0SS2,    //  16: ADC0 Sequence 2
+    ADC0SS3,    //  17: ADC0 Sequence 3
+    WATCHDOG,   //  18: Watchdog Timers 0 and 1
+    TIMER0A,    //  19: 16/32-Bit Timer 0A
+    TIMER0B,    //  20: 16/32-Bit Timer 0B
+    TIMER1A,    //  21: 16/32-Bit Timer 1A
+    TIMER1B,    //  22: 16/32-Bit Timer 1B
+    TIMER2A,    //  23: 16/32-Bit Timer 2A
+    TIMER2B,    //  24: 16/32-Bit Timer 2B
+    COMP0,      //  25: Analog Comparator 0
+    COMP1,      //  26: Analog Comparator 1
+    COMP2,      //  27: Analog Comparator 2
+    SYSCTL,     //  28: System Control
+    FLASH,      //  29: Flash Memory Control
+    GPIOF,      //  30: GPIO Port F
+    GPIOG,      //  31: GPIO Port G
+    GPIOH,      //  32: GPIO Port H
+    UART2,      //  33: UART2
+    SSI1,       //  34: SSI1
+    TIMER3A,    //  35: 16/32-Bit Timer 3A
+    TIMER3B,    //  36: 16/32-Bit Timer 3B
+    I2C1,       //  37: I2C1
+    CAN0,       //  38: CAN 0
+    CAN1,       //  39: CAN1
+    EMAC0,      //  40: Ethernet MAC
+    HIBERNATE,  //  41: HIB
+    USB0,       //  42: USB MAC
+    PWM0_3,     //  43: PWM Generator 3
+    UDMA,       //  44: uDMA 0 Software
+    UDMAERR,    //  45: uDMA 0 Error
+    ADC1SS0,    //  46: ADC1 Sequence 0
+    ADC1SS1,    //  47: ADC1 Sequence 1
+    ADC1SS2,    //  48: ADC1 Sequence 2
+    ADC1SS3,    //  49: ADC1 Sequence 3
+    EPI0,       //  50: EPI 0
+    GPIOJ,      //  51: GPIO Port J
+    GPIOK,      //  52: GPIO Port K
+    GPIOL,      //  53: GPIO Port L
+    SSI2,       //  54: SSI 2
+    SSI3,       //  55: SSI 3
+    UART3,      //  56: UART 3
+    UART4,      //  57: UART 4
+    UART5,      //  58: UART 5
+    UART6,      //  59: UART 6
+    UART7,      //  60: UART 7
+    I2C2,       //  61: I2C 2
+    I2C3,       //  62: I2C 3
+    TIMER4A,    //  63: Timer 4A
+    TIMER4B,    //  64: Timer 4B
+    TIMER5A,    //  65: Timer 5A
+    TIMER5B,    //  66: Timer 5B
+    SYSEXC,     //  67: Floating-Point Exception
+    _,          //  68: Reserved
+    _,          //  69: Reserved
+    I2C4,       //  70: I2C 4
+    I2C5,       //  71: I2C 5
+    GPIOM,      //  72: GPIO Port M
+    GPION,      //  73: GPIO Port N
+    _,          //  74: Reserved
+    TAMPER0,    //  75: Tamper
+    GPIOP0,     //  76: GPIO Port P (Summary or P0)
+    GPIOP1,     //  77: GPIO Port P1
+    GPIOP2,     //  78: GPIO Port P2
+    GPIOP3,     //  79: GPIO Port P3
+    GPIOP4,     //  80: GPIO Port P4
+    GPIOP5,     //  81: GPIO Port P5
+    GPIOP6,     //  82: GPIO Port P6
+    GPIOP7,     //  83: GPIO Port P7
+    GPIOQ0,     //  84: GPIO Port Q (Summary or Q0)
+    GPIOQ1,     //  85: GPIO Port Q1
+    GPIOQ2,     //  86: GPIO Port Q2
+    GPIOQ3,     //  87: GPIO Port Q3
+    GPIOQ4,     //  88: GPIO Port Q4
+    GPIOQ5,     //  89: GPIO Port Q5
+    GPIOQ6,     //  90: GPIO Port Q6
+    GPIOQ7,     //  91: GPIO Port Q7
+    GPIOR,      //  92: GPIO Port R
+    GPIOS,      //  93: GPIO Port S
+    SHA0,       //  94: SHA/MD5
+    AES0,       //  95: AES
+    DES0,       //  96: DES
+    LCD0,       //  97: LCD
+    TIMER6A,    //  98: 16/32-Bit Timer 6A
+    TIMER6B,    //  99: 16/32-Bit Timer 6B
+    TIMER7A,    // 100: 16/32-Bit Timer 7A
+    TIMER7B,    // 101: 16/32-Bit Timer 7B
+    I2C6,       // 102: I2C 6
+    I2C7,       // 103: I2C 7
+    _,          // 104: Reserved
+    ONEWIRE0,   // 105: 1-Wire
+    _,          // 106: Reserved
+    _,          // 107: Reserved
+    _,          // 108: Reserved
+    I2C8,       // 109: I2C 8
+    I2C9,       // 110: I2C 9
+    GPIOT,      // 111: GPIO T
+    _,          // 112: Reserved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_interrupt_numbers_match_the_tivaware_vector_numbers() {
+        assert_eq!(46, interrupt::GPIOF.vector());
+        assert_eq!(67, interrupt::GPIOJ.vector());
+        assert_eq!(127, interrupt::GPIOT.vector());
+    }
+}
@@ -1,6 +1,19 @@
 
-use critical_section_arm::CriticalSection;
+use core::cell::RefCell;
+use core::fmt;
+use critical_section_arm::{self, Mutex};
 use collections::Vec;
+use protocol;
+use shell::{self, Command, Context};
+
+// The priority of every interrupt which raises events. The queue is protected by masking only
+// interrupts at this priority or lower, so anything more urgent is never held off by it.
+pub const RAISE_PRIORITY: u8 = 1;
+
+// The same priority as held in the NVIC priority registers (nvic::to_hardware_priority), which
+// masks every interrupt that raises events. Anything else shared with those interrupts is
+// protected with it too.
+pub const RAISE_CEILING: u8 = 0x20;
 
 #[derive(PartialEq, Debug)]
 pub enum Event {
@@ -10,23 +23,109 @@ pub enum Event {
     LedTurnOff,
     FlashLed { count: usize, on_time: usize, off_time: usize },
     FlashLedDone,
+    LowMemory,
+    // Bytes have arrived on the serial port (see uart::read).
+    SerialReceived,
+    // The serial port has seen receive errors (see uart::take_errors).
+    SerialError,
 }
 
-// The static vector which holds the queue of events. Since we'll be allocating this in
-// the init function below and never deallocating it, this technically leaks memory. But
-// this application is the only thing that runs on the target so it okay. There's no
-// context beyond the context of the application.
-static mut event_queue: Option<Vec<Event>> = None;
+impl Event {
+    // An event from its name and then its fields in order, as typed in the shell:
+    // `FlashLed 3 4 3`.
+    pub fn parse(words: &[&str]) -> Result<Event, &'static str> {
+        let (name, fields) = match words.split_first() {
+            Some((name, fields)) => (*name, fields),
+            None => return Err("Expected an event"),
+        };
+        let (event, field_count) = match name {
+            "ButtonPress" => (Event::ButtonPress, 0),
+            "TimeTick" => (Event::TimeTick, 0),
+            "LedTurnOn" => (Event::LedTurnOn, 0),
+            "LedTurnOff" => (Event::LedTurnOff, 0),
+            "FlashLed" if fields.len() == 3 => {
+                let event = Event::FlashLed {
+                    count: shell::number(fields[0])?,
+                    on_time: shell::number(fields[1])?,
+                    off_time: shell::number(fields[2])?,
+                };
+                (event, 3)
+            },
+            "FlashLed" => return Err("FlashLed takes a count, on time and off time"),
+            "FlashLedDone" => (Event::FlashLedDone, 0),
+            "LowMemory" => (Event::LowMemory, 0),
+            "SerialReceived" => (Event::SerialReceived, 0),
+            "SerialError" => (Event::SerialError, 0),
+            _ => return Err("Unknown event"),
+        };
+        if fields.len() != field_count {
+            return Err("Too many arguments");
+        }
+        Ok(event)
+    }
+}
 
-// Initialize the event queue. You must call this before any interrupts raise events.
-pub fn init() {
-    unsafe {
-        if event_queue.is_none() {
-            event_queue = Some(Vec::new());
+// Events are sent off the chip as protocol::Event, which has the wire encoding (see
+// lib/protocol/src/event.rs). Its fields are u32s, the same size as usize here.
+impl<'a> From<&'a Event> for protocol::Event {
+    fn from(event: &Event) -> protocol::Event {
+        match *event {
+            Event::ButtonPress => protocol::Event::ButtonPress,
+            Event::TimeTick => protocol::Event::TimeTick,
+            Event::LedTurnOn => protocol::Event::LedTurnOn,
+            Event::LedTurnOff => protocol::Event::LedTurnOff,
+            Event::FlashLed { count, on_time, off_time } => protocol::Event::FlashLed {
+                count: count as u32,
+                on_time: on_time as u32,
+                off_time: off_time as u32,
+            },
+            Event::FlashLedDone => protocol::Event::FlashLedDone,
+            Event::LowMemory => protocol::Event::LowMemory,
+            Event::SerialReceived => protocol::Event::SerialReceived,
+            Event::SerialError => protocol::Event::SerialError,
         }
     }
 }
 
+impl From<protocol::Event> for Event {
+    fn from(event: protocol::Event) -> Event {
+        match event {
+            protocol::Event::ButtonPress => Event::ButtonPress,
+            protocol::Event::TimeTick => Event::TimeTick,
+            protocol::Event::LedTurnOn => Event::LedTurnOn,
+            protocol::Event::LedTurnOff => Event::LedTurnOff,
+            protocol::Event::FlashLed { count, on_time, off_time } => Event::FlashLed {
+                count: count as usize,
+                on_time: on_time as usize,
+                off_time: off_time as usize,
+            },
+            protocol::Event::FlashLedDone => Event::FlashLedDone,
+            protocol::Event::LowMemory => Event::LowMemory,
+            protocol::Event::SerialReceived => Event::SerialReceived,
+            protocol::Event::SerialError => Event::SerialError,
+        }
+    }
+}
+
+pub const COMMANDS: &'static [Command] = &[
+    Command { name: "event", usage: "<name> [<fields>]", help: "Raise an event", run: raise_command },
+];
+
+fn raise_command(_: &mut Context, args: &[&str], _: &mut fmt::Write) -> Result<(), &'static str> {
+    raise(Event::parse(args)?);
+    Ok(())
+}
+
+// The static vector which holds the queue of events. It's allocated the first time an event is
+// raised and never deallocated, so this technically leaks memory. But this application is the
+// only thing that runs on the target so it okay. There's no context beyond the context of the
+// application.
+static EVENT_QUEUE: Mutex<RefCell<Vec<Event>>> = Mutex::lazy_with_ceiling(RAISE_CEILING, new_queue);
+
+fn new_queue() -> RefCell<Vec<Event>> {
+    RefCell::new(Vec::new())
+}
+
 pub fn raise(event: Event) {
 
     // Add the event to our queue. Do this in a critical section so that we
@@ -34,25 +133,64 @@ pub fn raise(event: Event) {
     // an interrupt and the vector won't get corrupted. Also, because the ARM
     // Cortex allows for nested interrupts, disabling interrupts from within
     // the interrupt context prevents a nested interrupt from corrupting the 
-    // vector.
-    unsafe {
-        let _cs = CriticalSection::new();
-        get_queue().push(event);
-    }
+    // vector. Only the interrupts which raise events need to be masked.
+    critical_section_arm::with_ceiling(RAISE_CEILING, |cs| EVENT_QUEUE.borrow(cs).borrow_mut().push(event));
 }
 
 pub fn get() -> Option<Event> {
     // Get the most recent event.
-    unsafe {
-        let _cs = CriticalSection::new();
-        get_queue().pop()
-    }
+    critical_section_arm::with_ceiling(RAISE_CEILING, |cs| EVENT_QUEUE.borrow(cs).borrow_mut().pop())
 }
 
-// Extract a mutable reference to the event queue vector from the option type.
-unsafe fn get_queue() -> &'static mut Vec<Event> {
-    match event_queue {
-        Some(ref mut q) => q,
-        None => panic!(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nvic;
+
+    // The queue is global, so everything using it is in this one test.
+    #[test]
+    fn raised_events_are_returned_most_recent_first() {
+        raise(Event::ButtonPress);
+        raise(Event::TimeTick);
+        assert_eq!(Some(Event::TimeTick), get());
+        assert_eq!(Some(Event::ButtonPress), get());
+        assert_eq!(None, get());
+    }
+
+    #[test]
+    fn the_ceiling_masks_the_interrupts_which_raise_events() {
+        assert_eq!(nvic::to_hardware_priority(RAISE_PRIORITY), RAISE_CEILING);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn events_are_parsed_from_their_name_and_fields() {
+        assert_eq!(Ok(Event::ButtonPress), Event::parse(&["ButtonPress"]));
+        assert_eq!(Ok(Event::FlashLed { count: 3, on_time: 4, off_time: 2 }),
+                   Event::parse(&["FlashLed", "3", "4", "2"]));
+        assert!(Event::parse(&["FlashLed", "3"]).is_err());
+        assert!(Event::parse(&["TimeTick", "1"]).is_err());
+        assert!(Event::parse(&["Nothing"]).is_err());
+        assert!(Event::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn every_event_survives_the_wire_encoding() {
+        let events = [
+            Event::ButtonPress,
+            Event::TimeTick,
+            Event::LedTurnOn,
+            Event::LedTurnOff,
+            Event::FlashLed { count: 3, on_time: 4, off_time: 2 },
+            Event::FlashLedDone,
+            Event::LowMemory,
+            Event::SerialReceived,
+            Event::SerialError,
+        ];
+        for event in events.iter() {
+            let mut bytes = [0; protocol::event::MAX_LENGTH];
+            let length = protocol::event::encode(&protocol::Event::from(event), &mut bytes).unwrap();
+            let decoded = protocol::event::decode(&bytes[..length]).map(Event::from);
+            assert_eq!(Ok(event), decoded.as_ref());
+        }
+    }
+}
@@ -1,7 +1,24 @@
 /*
-    Exception handlers. For now, these just set a breakepoint.
+    Exception handlers, and the setup of the faults and priority grouping in init. Every handler
+    records what it can for the debugger, then stops at a breakpoint. The memory management fault
+    runs on a stack of its own, so it can tell a stack overflow apart from other faults.
 */
 
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_BOOL_INIT, ATOMIC_USIZE_INIT};
+use nvic::{self, Exception};
+use stack;
+#[cfg(target_arch = "arm")]
+use tiva_registers::nvic as nvic_registers;
+use tiva_registers::fields::nvic::fault_stat;
+#[cfg(target_arch = "arm")]
+use tiva_registers::fields::nvic::int_ctrl;
+
+// The exception number which last ended up in the default handler. When the breakpoint is hit,
+// inspect this from the debugger to see which handler is missing. Subtract 16 to get the
+// interrupt number.
+#[no_mangle]
+pub static UNHANDLED_EXCEPTION: AtomicUsize = ATOMIC_USIZE_INIT;
+
 #[cfg(target_arch = "arm")]
 fn breakpoint() {
     unsafe {
@@ -14,33 +31,160 @@ fn breakpoint() {
     unimplemented!();
 }
 
-#[allow(dead_code)]
-pub fn default_handler() -> ! {
+#[cfg(target_arch = "arm")]
+fn active_exception() -> u32 {
+    // The Interrupt Control and State register holds the active exception number.
+    nvic_registers::INT_CTRL.read_field(int_ctrl::VEC_ACT)
+}
+
+#[cfg(not(target_arch = "arm"))]
+fn active_exception() -> u32 {
+    unimplemented!();
+}
+
+// Give bus and usage faults their own handlers, instead of escalating them to a hard fault. The
+// memory management fault is turned on with the stack guard (see stack). Every priority bit is
+// used for preemption, so the priority ceilings mask exactly the interrupts at or above them.
+pub fn init() {
+    nvic::set_priority_grouping(nvic::PRIORITY_BITS);
+    nvic::enable_exception(Exception::BusFault);
+    nvic::enable_exception(Exception::UsageFault);
+}
+
+// Every exception or interrupt without a handler of its own ends up here.
+#[no_mangle]
+pub extern "C" fn default_handler() {
+    UNHANDLED_EXCEPTION.store(active_exception() as usize, Ordering::Relaxed);
     breakpoint();
     loop {}
 }
 
-pub fn nmi() -> ! {
+// Debug builds of driverlib (built with DEBUG defined, see lib/tivaware/build.rs) check their
+// arguments with ASSERT, which calls this when a check fails. The arguments are the source file
+// and line of the check.
+#[no_mangle]
+pub extern "C" fn __error__(_filename: *const u8, _line: u32) {
     breakpoint();
     loop {}
 }
 
-pub fn hard_fault() -> ! {
+pub extern "C" fn nmi() {
     breakpoint();
     loop {}
 }
 
-pub fn memory_fault() -> ! {
+pub extern "C" fn hard_fault() {
     breakpoint();
     loop {}
 }
 
-pub fn bus_fault() -> ! {
+// Set when a memory management fault was caused by the stack running into its guard region.
+pub static STACK_OVERFLOW: AtomicBool = ATOMIC_BOOL_INIT;
+
+// The stack pointer when the memory management fault was taken. The handler moves to its own
+// stack, so look here from the debugger to find the faulting frame.
+#[no_mangle]
+pub static FAULT_STACK_POINTER: AtomicUsize = ATOMIC_USIZE_INIT;
+
+// The stack the memory management fault handler runs on. When the fault is a stack overflow, the
+// stack pointer is in the guard, and the handler's first push would fault again and escalate to a
+// hard fault. It's u64s to keep the 8 byte alignment the ABI requires. The trampoline below loads
+// the end of it, so keep its size in step with the `+ 256` there.
+const FAULT_STACK_WORDS: usize = 32;
+#[no_mangle]
+pub static mut FAULT_STACK: [u64; FAULT_STACK_WORDS] = [0; FAULT_STACK_WORDS];
+
+// The memory management fault vector. This can't touch the stack, so it's naked: it passes the
+// old stack pointer on, switches to FAULT_STACK and branches to the handler proper.
+#[cfg(target_arch = "arm")]
+#[naked]
+pub extern "C" fn memory_fault() {
+    unsafe {
+        asm!("mov r0, sp
+              ldr r1, =FAULT_STACK + 256
+              mov sp, r1
+              b memory_fault_on_fault_stack"
+             :::: "volatile");
+    }
+}
+
+#[cfg(not(target_arch = "arm"))]
+pub extern "C" fn memory_fault() {
+    unimplemented!();
+}
+
+#[no_mangle]
+pub extern "C" fn memory_fault_on_fault_stack(stack_pointer: usize) {
+    FAULT_STACK_POINTER.store(stack_pointer, Ordering::Relaxed);
+    if stack_overflowed() {
+        STACK_OVERFLOW.store(true, Ordering::Relaxed);
+    }
     breakpoint();
     loop {}
 }
 
-pub fn usage_fault() -> ! {
+fn stack_overflowed() -> bool {
+    let (status, address) = memory_fault_status();
+    is_stack_overflow(status, address, stack::guard())
+}
+
+// The fault status and the memory management fault address.
+#[cfg(target_arch = "arm")]
+fn memory_fault_status() -> (u32, usize) {
+    (nvic_registers::FAULT_STAT.read(), nvic_registers::MM_ADDR.read() as usize)
+}
+
+#[cfg(not(target_arch = "arm"))]
+fn memory_fault_status() -> (u32, usize) {
+    unimplemented!();
+}
+
+// Whether a fault with this status and address was the stack hitting the guard, either directly or
+// while pushing the registers for an exception.
+fn is_stack_overflow(status: u32, address: usize, guard: (usize, usize)) -> bool {
+    // MMARV: the fault address is valid. MSTKE: the fault happened while stacking for an exception.
+    (status & fault_stat::MSTKE.mask() != 0) ||
+        (status & fault_stat::MMARV.mask() != 0 && stack::contains(guard, address))
+}
+
+pub extern "C" fn bus_fault() {
+    breakpoint();
+    loop {}
+}
+
+pub extern "C" fn usage_fault() {
     breakpoint();
     loop {}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_stack_overflow, FAULT_STACK_WORDS};
+    use tiva_registers::fields::nvic::fault_stat;
+
+    const GUARD: (usize, usize) = (0x2000_1000, 256);
+
+    #[test]
+    fn faults_in_the_guard_are_stack_overflows() {
+        let valid = fault_stat::MMARV.mask();
+        assert!(is_stack_overflow(valid, 0x2000_1000, GUARD));
+        assert!(is_stack_overflow(valid, 0x2000_10FF, GUARD));
+        assert!(!is_stack_overflow(valid, 0x2000_0FFF, GUARD));
+        assert!(!is_stack_overflow(valid, 0x2000_1100, GUARD));
+    }
+
+    #[test]
+    fn fault_addresses_only_count_when_valid() {
+        assert!(!is_stack_overflow(0, 0x2000_1000, GUARD));
+    }
+
+    #[test]
+    fn stacking_faults_are_stack_overflows() {
+        assert!(is_stack_overflow(fault_stat::MSTKE.mask(), 0, GUARD));
+    }
+
+    #[test]
+    fn fault_stack_matches_the_trampoline() {
+        assert_eq!(256, FAULT_STACK_WORDS * 8);
+    }
+}
@@ -0,0 +1,71 @@
+/*
+    Heap usage, from whichever allocator the firmware is built with.
+
+    When the heap gets low the allocator calls back into here. That can happen in the middle of
+    raising an event (the queue allocates), so the callback only sets a flag and the LowMemory
+    event is raised later from the main loop.
+*/
+
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+use event::{self, Event};
+use shell::{Command, Context};
+
+#[cfg(all(target_os = "none", feature = "libc_allocator"))]
+use libc_allocator as allocator;
+#[cfg(all(target_os = "none", feature = "block_allocator"))]
+use block_allocator as allocator;
+
+// On the host the system allocator is in use, which keeps no statistics.
+#[cfg(not(target_os = "none"))]
+mod allocator {
+    pub use heap_stats::{HeapStats, LowMemoryHandler};
+
+    pub fn stats() -> HeapStats {
+        ::heap_stats::Recorder::new().stats(0, None)
+    }
+
+    pub fn set_low_memory_handler(_reserve: usize, _handler: LowMemoryHandler) {}
+}
+
+pub use self::allocator::HeapStats;
+
+// Raise LowMemory once less than this is left on the heap.
+const LOW_MEMORY_RESERVE: usize = 1024;
+
+static LOW_MEMORY: AtomicBool = ATOMIC_BOOL_INIT;
+
+pub fn init() {
+    allocator::set_low_memory_handler(LOW_MEMORY_RESERVE, low_memory);
+}
+
+pub fn stats() -> HeapStats {
+    allocator::stats()
+}
+
+// Raise the LowMemory event if the allocator has run low since the last call.
+pub fn poll() {
+    if LOW_MEMORY.swap(false, Ordering::Relaxed) {
+        event::raise(Event::LowMemory);
+    }
+}
+
+fn low_memory() {
+    LOW_MEMORY.store(true, Ordering::Relaxed);
+}
+
+pub const COMMANDS: &'static [Command] = &[
+    Command { name: "heap", usage: "", help: "Show heap usage", run: show_heap },
+];
+
+fn show_heap(_: &mut Context, _: &[&str], out: &mut fmt::Write) -> Result<(), &'static str> {
+    let stats = stats();
+    let _ = write!(out, "capacity: {}\r\nin use: {} (peak {})\r\nfree: {}\r\n",
+                   stats.capacity, stats.in_use, stats.peak, stats.free);
+    if let Some(largest_free_block) = stats.largest_free_block {
+        let _ = write!(out, "largest free block: {}\r\n", largest_free_block);
+    }
+    let _ = write!(out, "live allocations: {}\r\nallocations: {} ({} failed)\r\n",
+                   stats.live_allocations, stats.allocations, stats.failed_allocations);
+    Ok(())
+}
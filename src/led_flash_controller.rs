@@ -1,4 +1,6 @@
 use ::event::Event;
+#[cfg(feature = "binary_log")]
+use ::log::{Encode, Record};
 
 #[derive(Debug)]
 enum State {Inactive, Off, On}
@@ -18,6 +20,14 @@ pub struct LedFlashController {
     flashes_remaining: usize,
 }
 
+// Binary logs send the Debug text.
+#[cfg(feature = "binary_log")]
+impl Encode for LedFlashController {
+    fn encode(&self, record: &mut Record) {
+        record.debug(self);
+    }
+}
+
 impl LedFlashController {
 
     pub fn new() -> Self {
@@ -26,7 +36,7 @@ impl LedFlashController {
 
     fn handle_time_tick(&mut self) -> Option<Event> {
         
-        //println!("{:?}", self);
+        debug!("{:?}", self);
         
         // Decrement the timer.
         if self.time_remaining > 0 {
@@ -61,7 +71,8 @@ impl LedFlashController {
         self.on_time = on_time;
         self.off_time = off_time;
         self.state = State::On;
-        self.time_remaining = on_time + 1;
+        // FlashLed can be raised from the shell with any times, so don't overflow.
+        self.time_remaining = on_time.saturating_add(1);
         self.flashes_remaining = count;
         
         // There is no additional event raised.
@@ -98,6 +109,13 @@ mod tests {
         assert_eq!(None, event);
     }
     
+    #[test]
+    fn given_an_led_flash_has_been_requested_with_the_longest_on_time_then_it_doesnt_overflow() {
+        let mut c = LedFlashController { ..Default::default() };
+        let event = c.process_event(&Event::FlashLed{ count: 1, on_time: usize::max_value(), off_time: 0 });
+        assert_eq!(None, event);
+    }
+
     #[test]
     fn given_an_led_flash_has_been_requested_when_the_next_tick_occurs_the_led_is_turned_on() {
         // given
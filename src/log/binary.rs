@@ -0,0 +1,299 @@
+/*
+    Log messages in the binary format, for the binary_log feature. The format strings aren't kept
+    in flash and nothing is formatted on the target. Each message is sent as a record instead,
+    which tools/log_decoder turns back into text using the format strings in the ELF:
+
+        tag       u8, protocol::frame::LOG_RECORD
+        level     u8, 0 (Error) to 3 (Debug)
+        format    u16, the index of the message, and so its format string
+        time      u32, milliseconds since boot
+        arguments a tag byte and the value for each, see Tag
+
+    Numbers are little endian. Records are sent in frames like the host protocol's (a CRC, COBS
+    encoded, between zeros; see lib/protocol/src/frame.rs), so they can share the serial port with
+    text and protocol frames. Arguments have to implement Encode, which the integers, bool, char
+    and str do. Other types can send their Debug text with Record::debug. Records longer than
+    LINE_LENGTH lose the arguments which don't fit.
+*/
+
+use core::cmp;
+use core::fmt::{self, Write};
+use core::mem;
+use protocol::frame;
+use super::{queue, Level, LINE_LENGTH};
+
+// The type of each argument in a binary record.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Tag {
+    // u32.
+    Unsigned = 0,
+    // i32.
+    Signed = 1,
+    // u8, 0 or 1.
+    Bool = 2,
+    // u32, the code point.
+    Char = 3,
+    // u8 length, then UTF-8.
+    Str = 4,
+    // u8 length, then UTF-8 which was already formatted (see Record::debug).
+    Text = 5,
+}
+
+// A message's static in .log_strings (see the log macro): its module path, then its format string
+// ending with a zero.
+#[repr(C)]
+pub struct Message<F: ?Sized> {
+    pub module: &'static str,
+    pub format: F,
+}
+
+// The longest binary record once it's sealed in a frame: its CRC, the one byte COBS adds to
+// anything shorter than 254 bytes, and the zeros around it.
+const ENCODED_RECORD: usize = LINE_LENGTH + 5;
+
+// A message in the binary format, being built up.
+pub struct Record {
+    // With room for the CRC after the record.
+    bytes: [u8; LINE_LENGTH + 2],
+    len: usize,
+    // An argument didn't fit, so the rest are left out too.
+    full: bool,
+}
+
+impl Record {
+    pub fn new(level: Level, message: &'static Message<[u8]>, time_ms: u32) -> Record {
+        let mut record = Record { bytes: [0; LINE_LENGTH + 2], len: 0, full: false };
+        let index = format_index(message);
+        record.push(&[frame::LOG_RECORD, level as u8, index as u8, (index >> 8) as u8]);
+        record.push(&le_u32(time_ms));
+        record
+    }
+
+    pub fn unsigned(&mut self, value: u32) {
+        self.argument(Tag::Unsigned, &le_u32(value));
+    }
+
+    pub fn signed(&mut self, value: i32) {
+        self.argument(Tag::Signed, &le_u32(value as u32));
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.argument(Tag::Bool, &[value as u8]);
+    }
+
+    pub fn char(&mut self, value: char) {
+        self.argument(Tag::Char, &le_u32(value as u32));
+    }
+
+    // A string, which is left out if it doesn't fit.
+    pub fn str(&mut self, value: &str) {
+        if self.full || LINE_LENGTH - self.len < 2 + value.len() {
+            self.full = true;
+            return;
+        }
+        self.push(&[Tag::Str as u8, value.len() as u8]);
+        self.push(value.as_bytes());
+    }
+
+    // The value's Debug text, cut short if it doesn't fit. For types without their own encoding:
+    //
+    //     impl Encode for Errors {
+    //         fn encode(&self, record: &mut Record) { record.debug(self) }
+    //     }
+    pub fn debug(&mut self, value: &fmt::Debug) {
+        if self.full || LINE_LENGTH - self.len < 2 {
+            self.full = true;
+            return;
+        }
+        let start = self.len;
+        self.bytes[start] = Tag::Text as u8;
+        self.len += 2;
+        let _ = write!(TextWriter(self), "{:?}", value);
+        self.bytes[start + 1] = (self.len - start - 2) as u8;
+    }
+
+    // Queue the finished record. Used by the macros.
+    pub fn queue(mut self) {
+        let mut out = [0; ENCODED_RECORD];
+        let length = self.seal(&mut out);
+        queue(&out[..length]);
+    }
+
+    #[cfg(test)]
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    // The record in its frame.
+    fn seal(&mut self, out: &mut [u8; ENCODED_RECORD]) -> usize {
+        // Records are never too long for `out`.
+        frame::seal(&mut self.bytes, self.len, out).unwrap_or(0)
+    }
+
+    fn argument(&mut self, tag: Tag, value: &[u8]) {
+        if self.full || LINE_LENGTH - self.len < 1 + value.len() {
+            self.full = true;
+            return;
+        }
+        self.push(&[tag as u8]);
+        self.push(value);
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        if self.full || LINE_LENGTH - self.len < bytes.len() {
+            self.full = true;
+            return;
+        }
+        self.bytes[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+// Writes text into a record as far as it fits.
+struct TextWriter<'a>(&'a mut Record);
+
+impl<'a> fmt::Write for TextWriter<'a> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        let record = &mut *self.0;
+        let count = cmp::min(text.len(), LINE_LENGTH - record.len);
+        record.bytes[record.len..record.len + count].copy_from_slice(&text.as_bytes()[..count]);
+        record.len += count;
+        if count < text.len() { Err(fmt::Error) } else { Ok(()) }
+    }
+}
+
+// The index of a message, from the address of its static. The statics are aligned like the
+// module path's reference and the section starts one of those in, so the address divided by that
+// alignment, less one, is different for every message.
+fn format_index(message: &'static Message<[u8]>) -> u16 {
+    (message as *const Message<[u8]> as *const u8 as usize / mem::align_of::<&str>() - 1) as u16
+}
+
+fn le_u32(value: u32) -> [u8; 4] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]
+}
+
+// How an argument of a binary log message is sent.
+pub trait Encode {
+    fn encode(&self, record: &mut Record);
+}
+
+macro_rules! encode_as {
+    ($method:ident, $as:ty: $($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, record: &mut Record) {
+                    record.$method(*self as $as);
+                }
+            }
+        )*
+    }
+}
+
+encode_as!(unsigned, u32: u8, u16, u32, usize);
+encode_as!(signed, i32: i8, i16, i32, isize);
+encode_as!(bool, bool: bool);
+encode_as!(char, char: char);
+
+impl Encode for str {
+    fn encode(&self, record: &mut Record) {
+        record.str(self);
+    }
+}
+
+impl<'a, T: ?Sized> Encode for &'a T where T: Encode {
+    fn encode(&self, record: &mut Record) {
+        (**self).encode(record);
+    }
+}
+
+impl<'a, T: ?Sized> Encode for &'a mut T where T: Encode {
+    fn encode(&self, record: &mut Record) {
+        (**self).encode(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collections::Vec;
+
+    static MESSAGE: Message<[u8; 4]> = Message {
+        module: "rust_tiva_blinky::log::binary::tests",
+        format: *b"{}!\0",
+    };
+
+    fn record(level: Level, arguments: &[&Encode]) -> Vec<u8> {
+        let mut record = Record::new(level, &MESSAGE, 0x01020304);
+        for argument in arguments {
+            argument.encode(&mut record);
+        }
+        record.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn binary_records_have_a_header_and_tagged_arguments() {
+        let bytes = record(Level::Warn, &[&7u8, &-2i32, &true, &"hi"]);
+        let index = format_index(&MESSAGE);
+        assert_eq!(vec![0xFF, 1, index as u8, (index >> 8) as u8, 4, 3, 2, 1,
+                        0, 7, 0, 0, 0,
+                        1, 0xFE, 0xFF, 0xFF, 0xFF,
+                        2, 1,
+                        4, 2, b'h', b'i'],
+                   bytes);
+    }
+
+    #[derive(Debug)]
+    struct Point { x: u8 }
+
+    impl Encode for Point {
+        fn encode(&self, record: &mut Record) {
+            record.debug(self);
+        }
+    }
+
+    #[test]
+    fn other_types_send_their_debug_text() {
+        let bytes = record(Level::Info, &[&Point { x: 1 }]);
+        assert_eq!(&b"\x05\x0EPoint { x: 1 }"[..], &bytes[8..]);
+    }
+
+    #[test]
+    fn arguments_which_do_not_fit_are_left_out() {
+        let long = [b'a'; 85];
+        let long = ::core::str::from_utf8(&long).unwrap();
+        let bytes = record(Level::Info, &[&long, &1u32, &true]);
+        assert_eq!(8 + 2 + 85, bytes.len());
+
+        // Debug text is cut short instead.
+        let bytes = record(Level::Info, &[&&long[..80], &Point { x: 1 }]);
+        assert_eq!(LINE_LENGTH, bytes.len());
+        assert_eq!(&b"\x05\x04Poin"[..], &bytes[90..]);
+    }
+
+    #[test]
+    fn a_string_which_does_not_fit_leaves_no_tag_behind() {
+        let long = [b'a'; 90];
+        let long = ::core::str::from_utf8(&long).unwrap();
+        let bytes = record(Level::Info, &[&long]);
+        assert_eq!(8, bytes.len());
+    }
+
+    #[test]
+    fn records_are_sent_in_frames() {
+        let long = [b'a'; 85];
+        let long = ::core::str::from_utf8(&long).unwrap();
+        let mut record = Record::new(Level::Info, &MESSAGE, 0);
+        long.encode(&mut record);
+        let mut out = [0; ENCODED_RECORD];
+        let length = record.seal(&mut out);
+        assert_eq!(frame::encoded_length(record.as_bytes().len()), length);
+        assert_eq!(ENCODED_RECORD, frame::encoded_length(LINE_LENGTH));
+
+        let mut deframer = frame::Deframer::new();
+        for &byte in &out[..length - 1] {
+            assert_eq!(frame::Status::Pending, deframer.push(byte));
+        }
+        assert_eq!(frame::Status::LogRecord, deframer.push(out[length - 1]));
+    }
+}
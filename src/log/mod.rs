@@ -0,0 +1,480 @@
+/*
+    Log messages with a level and a timestamp:
+
+        info!("Started, clock {} Hz", clock_hz);
+        debug!("{:?}", self);
+
+    come out as
+
+        [    0.000] INFO  main: Started, clock 16000000 Hz
+
+    Messages less urgent than the level set for their module are left out. The level is Info
+    unless it's changed with set_level, or for one module (and the modules inside it) with
+    set_module_level:
+
+        log::set_module_level("led_flash_controller", log::Level::Debug);
+
+    The shell does the same with `log level debug led_flash_controller`, or `log level warn` for
+    every module.
+
+    Logging never blocks, so it's fine in the interrupts which raise events: the line is formatted
+    on the stack and queued with those interrupts masked (see CEILING), and the main loop hands
+    the queue to the sink with flush. More urgent interrupts mustn't log. Lines which don't fit in
+    the queue are dropped and counted, and longer lines than LINE_LENGTH are cut short.
+
+    The sink is where the lines go: the serial port (uart), the debugger's console (semihosting),
+    or a ring in memory which keeps the most recent lines (memory) until they're dumped to
+    another sink. Lines go to memory until set_sink picks another, or the shell's `log sink`. The
+    shell's `log dump` sends what's kept there to the serial port.
+
+    With the binary_log feature, messages are sent in a compact binary format instead of as text
+    (see binary.rs). The text format is in text.rs.
+*/
+
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::str;
+use core::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use critical_section_arm::{self, Mutex};
+use event;
+use ring_buffer::{RingBuffer, Storage};
+use semihosting;
+use shell::{Command, Context};
+
+#[cfg(not(feature = "binary_log"))]
+macro_rules! log {
+    ($level:expr, $format:tt $(, $arg:expr)*) => {
+        if ::log::enabled($level, module_path!()) {
+            ::log::write($level, module_path!(), format_args!($format $(, $arg)*));
+        }
+    }
+}
+
+// Each message has a static in the .log_strings section, which isn't loaded (see layout.ld),
+// holding its module path (in flash already for `enabled`) and its format string. The static's
+// address is the message's index. log_message! makes the static from the format string; build.rs
+// generates it from the messages it finds in src, which have to give their format string as a
+// plain string literal. One it can't find fails the build with "no rules expected the token".
+#[cfg(feature = "binary_log")]
+include!(concat!(env!("OUT_DIR"), "/log_messages.rs"));
+
+#[cfg(feature = "binary_log")]
+macro_rules! log {
+    ($level:expr, $format:tt $(, $arg:expr)*) => {
+        if ::log::enabled($level, module_path!()) {
+            log_message!($format);
+
+            let mut record = ::log::Record::new($level, &MESSAGE, ::systick::uptime_ms());
+            $( ::log::Encode::encode(&$arg, &mut record); )*
+            record.queue();
+        }
+    }
+}
+
+macro_rules! error {
+    ($($arg:tt)+) => { log!(::log::Level::Error, $($arg)+) }
+}
+
+macro_rules! warn {
+    ($($arg:tt)+) => { log!(::log::Level::Warn, $($arg)+) }
+}
+
+macro_rules! info {
+    ($($arg:tt)+) => { log!(::log::Level::Info, $($arg)+) }
+}
+
+macro_rules! debug {
+    ($($arg:tt)+) => { log!(::log::Level::Debug, $($arg)+) }
+}
+
+#[cfg(any(feature = "binary_log", test))]
+mod binary;
+#[cfg(any(not(feature = "binary_log"), test))]
+mod text;
+
+#[cfg(any(feature = "binary_log", test))]
+pub use self::binary::{Encode, Message, Record, Tag};
+#[cfg(any(not(feature = "binary_log"), test))]
+pub use self::text::write;
+
+// From most to least urgent.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    // By its name in lower case, like "debug".
+    fn parse(name: &str) -> Result<Level, &'static str> {
+        match name {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            _ => Err("Expected error, warn, info or debug"),
+        }
+    }
+}
+
+// Where lines go. Takes as many bytes as it can and returns how many that was; the rest are
+// offered again on the next flush.
+pub type Sink = fn(&[u8]) -> usize;
+
+// The longest line, including the line ending. Also the longest binary record.
+pub const LINE_LENGTH: usize = 96;
+
+// How many modules can have their own level.
+const MODULE_LEVELS: usize = 8;
+
+// The longest module name which can have its own level.
+const MODULE_NAME_LENGTH: usize = 24;
+
+// Lines waiting for flush.
+type Queue = RingBuffer<[u8; 512]>;
+
+// What the memory sink keeps.
+type History = RingBuffer<[u8; 1024]>;
+
+// The interrupts which raise events log, so everything shared with them is protected by masking
+// interrupts up to their priority.
+const CEILING: u8 = event::RAISE_CEILING;
+
+static QUEUE: Mutex<RefCell<Queue>> = Mutex::lazy_with_ceiling(CEILING, new_queue);
+static HISTORY: Mutex<RefCell<History>> = Mutex::lazy_with_ceiling(CEILING, new_history);
+static FILTER: Mutex<RefCell<Filter>> = Mutex::lazy_with_ceiling(CEILING, new_filter);
+static SINK: Mutex<Cell<Sink>> = Mutex::lazy_with_ceiling(CEILING, new_sink);
+
+// Lines dropped because the queue was full, since the last flush.
+static DROPPED: AtomicUsize = ATOMIC_USIZE_INIT;
+
+fn new_queue() -> RefCell<Queue> {
+    RefCell::new(RingBuffer::with_storage([0; 512]))
+}
+
+fn new_history() -> RefCell<History> {
+    RefCell::new(RingBuffer::with_storage([0; 1024]))
+}
+
+fn new_filter() -> RefCell<Filter> {
+    RefCell::new(Filter::new(Level::Info))
+}
+
+fn new_sink() -> Cell<Sink> {
+    Cell::new(memory)
+}
+
+// The level for every module without its own.
+pub fn set_level(level: Level) {
+    critical_section_arm::with_ceiling(CEILING, |cs| FILTER.borrow(cs).borrow_mut().level = level);
+}
+
+// The level for a module (named without the crate, like "uart"), and the modules inside it.
+// False if MODULE_LEVELS modules already have their own level, or the name is longer than
+// MODULE_NAME_LENGTH.
+pub fn set_module_level(module: &str, level: Level) -> bool {
+    critical_section_arm::with_ceiling(CEILING, |cs| FILTER.borrow(cs).borrow_mut().set(module, level))
+}
+
+pub fn set_sink(sink: Sink) {
+    critical_section_arm::with_ceiling(CEILING, |cs| SINK.borrow(cs).set(sink));
+}
+
+// Whether a message at `level` from the module at `module_path` is logged. Used by the macros.
+pub fn enabled(level: Level, module_path: &str) -> bool {
+    let module = module_name(module_path);
+    let module_level = critical_section_arm::with_ceiling(CEILING, |cs| {
+        FILTER.borrow(cs).borrow().level(module)
+    });
+    level <= module_level
+}
+
+// Queue a whole line or binary record, or drop it if it doesn't fit. Used by the macros.
+pub fn queue(bytes: &[u8]) {
+    critical_section_arm::with_ceiling(CEILING, |cs| {
+        let mut queue = QUEUE.borrow(cs).borrow_mut();
+        if queue.space() < bytes.len() {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        queue.extend(bytes);
+    });
+}
+
+// Hand the queued lines to the sink. Call from the main loop only, since sinks may be slow.
+pub fn flush() {
+    let dropped = DROPPED.swap(0, Ordering::Relaxed);
+    if dropped > 0 {
+        warn!("{} log lines dropped", dropped);
+    }
+    let sink = critical_section_arm::with_ceiling(CEILING, |cs| SINK.borrow(cs).get());
+    drain(&QUEUE, sink);
+}
+
+// Hand what the memory sink has kept to another sink, oldest first.
+pub fn dump(sink: Sink) {
+    drain(&HISTORY, sink);
+}
+
+// Send lines to the serial port. What doesn't fit in its transmit buffer waits for the next
+// flush.
+pub fn uart(bytes: &[u8]) -> usize {
+    ::uart::write(bytes)
+}
+
+// Send lines to the debugger's console. Only use this with a debugger attached (see semihosting),
+// which is why only the shell picks it.
+pub fn semihosting(bytes: &[u8]) -> usize {
+    semihosting::write(bytes);
+    bytes.len()
+}
+
+// Keep lines in memory, overwriting the oldest once it's full.
+pub fn memory(bytes: &[u8]) -> usize {
+    critical_section_arm::with_ceiling(CEILING, |cs| {
+        let mut history = HISTORY.borrow(cs).borrow_mut();
+        for &byte in bytes {
+            history.push_overwriting(byte);
+        }
+    });
+    bytes.len()
+}
+
+// Move bytes out of a buffer into a sink, until it's empty or the sink is full.
+fn drain<S>(buffer: &Mutex<RefCell<RingBuffer<S>>>, sink: Sink)
+    where S: Storage
+{
+    let mut chunk = [0; 32];
+    loop {
+        let count = critical_section_arm::with_ceiling(CEILING, |cs| {
+            buffer.borrow(cs).borrow().peek(&mut chunk)
+        });
+        if count == 0 {
+            break;
+        }
+        let taken = sink(&chunk[..count]);
+        critical_section_arm::with_ceiling(CEILING, |cs| buffer.borrow(cs).borrow_mut().discard(taken));
+        if taken < count {
+            break;
+        }
+    }
+}
+
+pub const COMMANDS: &'static [Command] = &[
+    Command { name: "log", usage: "<setting> [<value>]", help: "Dump kept lines, or pick the sink or level", run: log_command },
+];
+
+// `log dump` sends the lines kept in memory to the serial port, `log sink <sink>` picks uart,
+// semihosting or memory, and `log level <level> [<module>]` sets the level for every module or
+// for one.
+fn log_command(_: &mut Context, args: &[&str], _: &mut fmt::Write) -> Result<(), &'static str> {
+    match (args.get(0), args.len()) {
+        (Some(&"dump"), 1) => dump(serial),
+        (Some(&"sink"), 2) => set_sink(parse_sink(args[1])?),
+        (Some(&"level"), 2) => set_level(Level::parse(args[1])?),
+        (Some(&"level"), 3) => {
+            if !set_module_level(args[2], Level::parse(args[1])?) {
+                return Err("No room for another module");
+            }
+        },
+        _ => return Err("Expected dump, sink <sink> or level <level> [<module>]"),
+    }
+    Ok(())
+}
+
+fn parse_sink(name: &str) -> Result<Sink, &'static str> {
+    match name {
+        "uart" => Ok(uart as Sink),
+        "semihosting" => Ok(semihosting as Sink),
+        "memory" => Ok(memory as Sink),
+        _ => Err("Expected uart, semihosting or memory"),
+    }
+}
+
+// Send everything to the serial port, waiting for room when it's busy. Only for the main loop.
+fn serial(bytes: &[u8]) -> usize {
+    ::uart::write_all(bytes);
+    bytes.len()
+}
+
+// The module path without the crate, or "main" for the crate itself.
+fn module_name(module_path: &str) -> &str {
+    match module_path.find("::") {
+        Some(at) => &module_path[at + 2..],
+        None => "main",
+    }
+}
+
+// The levels of each module.
+struct Filter {
+    level: Level,
+    modules: [Option<(ModuleName, Level)>; MODULE_LEVELS],
+}
+
+impl Filter {
+    fn new(level: Level) -> Filter {
+        Filter { level: level, modules: [None; MODULE_LEVELS] }
+    }
+
+    fn set(&mut self, module: &str, level: Level) -> bool {
+        let name = match ModuleName::new(module) {
+            Some(name) => name,
+            None => return false,
+        };
+        let slot = self.modules.iter().position(|entry| match *entry {
+            Some((ref name, _)) => name.as_str() == module,
+            None => false,
+        }).or_else(|| self.modules.iter().position(|entry| entry.is_none()));
+        match slot {
+            Some(i) => {
+                self.modules[i] = Some((name, level));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The level of the innermost module set which contains `module`.
+    fn level(&self, module: &str) -> Level {
+        let mut best: Option<(&str, Level)> = None;
+        for &(ref name, level) in self.modules.iter().filter_map(|entry| entry.as_ref()) {
+            let name = name.as_str();
+            let contains = module == name ||
+                           (module.starts_with(name) && module[name.len()..].starts_with("::"));
+            if contains && best.map_or(true, |(longest, _)| name.len() > longest.len()) {
+                best = Some((name, level));
+            }
+        }
+        best.map_or(self.level, |(_, level)| level)
+    }
+}
+
+// A module name kept in a Filter. It's copied, so names typed at the shell can be used.
+#[derive(Clone, Copy)]
+struct ModuleName {
+    bytes: [u8; MODULE_NAME_LENGTH],
+    len: usize,
+}
+
+impl ModuleName {
+    fn new(name: &str) -> Option<ModuleName> {
+        if name.len() > MODULE_NAME_LENGTH {
+            return None;
+        }
+        let mut bytes = [0; MODULE_NAME_LENGTH];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Some(ModuleName { bytes: bytes, len: name.len() })
+    }
+
+    fn as_str(&self) -> &str {
+        // Only ever copied from a str.
+        str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collections::{String, Vec};
+    use core::cell::RefCell;
+    use core::cmp;
+    use critical_section_arm::{self, Mutex};
+
+    #[test]
+    fn modules_are_named_without_the_crate() {
+        assert_eq!("led_flash_controller", module_name("rust_tiva_blinky::led_flash_controller"));
+        assert_eq!("main", module_name("rust_tiva_blinky"));
+    }
+
+    #[test]
+    fn the_innermost_module_level_applies() {
+        let mut filter = Filter::new(Level::Warn);
+        assert!(filter.set("board", Level::Debug));
+        assert!(filter.set("board::custom", Level::Error));
+        assert_eq!(Level::Debug, filter.level("board"));
+        assert_eq!(Level::Error, filter.level("board::custom"));
+        assert_eq!(Level::Debug, filter.level("board::ek_tm4c123gxl"));
+        // Only whole module names match.
+        assert_eq!(Level::Warn, filter.level("boards"));
+        assert_eq!(Level::Warn, filter.level("uart"));
+    }
+
+    #[test]
+    fn module_levels_run_out() {
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let mut filter = Filter::new(Level::Info);
+        for name in names.iter() {
+            assert!(filter.set(name, Level::Debug));
+        }
+        assert!(!filter.set("i", Level::Debug));
+        // Changing a module which already has a level still works.
+        assert!(filter.set("a", Level::Error));
+        assert_eq!(Level::Error, filter.level("a"));
+    }
+
+    #[test]
+    fn module_names_are_copied() {
+        let mut filter = Filter::new(Level::Info);
+        {
+            let typed = String::from("uart");
+            assert!(filter.set(&typed, Level::Debug));
+        }
+        assert_eq!(Level::Debug, filter.level("uart"));
+        assert!(!filter.set("a_module_name_over_the_limit", Level::Debug));
+    }
+
+    #[test]
+    fn shell_settings_are_parsed() {
+        assert_eq!(Ok(Level::Warn), Level::parse("warn"));
+        assert!(Level::parse("WARN").is_err());
+        assert!(parse_sink("semihosting").is_ok());
+        assert!(parse_sink("disk").is_err());
+    }
+
+    static SENT: Mutex<RefCell<Vec<u8>>> = Mutex::lazy(new_sent);
+
+    fn new_sent() -> RefCell<Vec<u8>> {
+        RefCell::new(Vec::new())
+    }
+
+    // Takes at most 10 bytes each time, like a sink whose buffer fills up.
+    fn slow_sink(bytes: &[u8]) -> usize {
+        let count = cmp::min(bytes.len(), 10);
+        critical_section_arm::free(|cs| SENT.borrow(cs).borrow_mut().extend_from_slice(&bytes[..count]));
+        count
+    }
+
+    fn sent() -> Vec<u8> {
+        critical_section_arm::free(|cs| SENT.borrow(cs).borrow().clone())
+    }
+
+    #[test]
+    #[cfg(not(feature = "binary_log"))]
+    fn lines_are_filtered_queued_and_flushed() {
+        set_sink(slow_sink);
+        info!("one");
+        debug!("left out");
+        assert!(set_module_level("log::tests", Level::Debug));
+        debug!("two");
+
+        // A slow sink takes the rest on later flushes.
+        flush();
+        assert_eq!(10, sent().len());
+        for _ in 0..10 {
+            flush();
+        }
+        assert_eq!(&b"[    0.000] INFO  log::tests: one\r\n[    0.000] DEBUG log::tests: two\r\n"[..],
+                   &sent()[..]);
+
+        // The memory sink keeps lines until they're dumped.
+        set_sink(memory);
+        error!("three");
+        flush();
+        for _ in 0..10 {
+            dump(slow_sink);
+        }
+        assert!(sent().ends_with(b"ERROR log::tests: three\r\n"));
+    }
+}
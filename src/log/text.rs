@@ -0,0 +1,91 @@
+/*
+    Log messages as lines of text, formatted on the target.
+*/
+
+use core::cmp;
+use core::fmt::{self, Write};
+use systick;
+use super::{module_name, queue, Level, LINE_LENGTH};
+
+// Queue a line. Used by the macros.
+pub fn write(level: Level, module_path: &str, args: fmt::Arguments) {
+    let mut line = Line::new();
+    format_line(&mut line, systick::uptime_ms(), level, module_name(module_path), args);
+
+    queue(line.as_bytes());
+}
+
+// `[    1.234] INFO  module: message`
+fn format_line(line: &mut Line, time_ms: u32, level: Level, module: &str, args: fmt::Arguments) {
+    let _ = write!(line, "[{:5}.{:03}] {} {}: ", time_ms / 1000, time_ms % 1000, level.name(), module);
+    let _ = line.write_fmt(args);
+    line.end();
+}
+
+impl Level {
+    fn name(&self) -> &'static str {
+        match *self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN ",
+            Level::Info => "INFO ",
+            Level::Debug => "DEBUG",
+        }
+    }
+}
+
+// One line being formatted. Whatever doesn't fit is cut off, leaving room for the line ending.
+struct Line {
+    bytes: [u8; LINE_LENGTH],
+    len: usize,
+}
+
+impl Line {
+    fn new() -> Line {
+        Line { bytes: [0; LINE_LENGTH], len: 0 }
+    }
+
+    fn end(&mut self) {
+        self.bytes[self.len] = b'\r';
+        self.bytes[self.len + 1] = b'\n';
+        self.len += 2;
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl fmt::Write for Line {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        let room = LINE_LENGTH - 2 - self.len;
+        let count = cmp::min(text.len(), room);
+        self.bytes[self.len..self.len + count].copy_from_slice(&text.as_bytes()[..count]);
+        self.len += count;
+        if count < text.len() { Err(fmt::Error) } else { Ok(()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collections::Vec;
+
+    fn formatted(time_ms: u32, level: Level, module: &str, args: fmt::Arguments) -> Vec<u8> {
+        let mut line = Line::new();
+        format_line(&mut line, time_ms, level, module, args);
+        line.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn lines_have_a_timestamp_level_and_module() {
+        assert_eq!(&b"[   12.045] WARN  uart: 3 errors\r\n"[..],
+                   &formatted(12045, Level::Warn, "uart", format_args!("{} errors", 3))[..]);
+    }
+
+    #[test]
+    fn long_lines_are_cut_short() {
+        let line = formatted(0, Level::Info, "main", format_args!("{:200}", ""));
+        assert_eq!(LINE_LENGTH, line.len());
+        assert!(line.ends_with(b"\r\n"));
+    }
+}
@@ -20,12 +20,26 @@
 #![feature(asm)]
 #![cfg_attr(target_os = "none", feature(asm))]
 
+// For the memory fault trampoline, which has to run without a stack frame.
+#![feature(naked_functions)]
+
 // Allow using types which implement Drop to be used as globals.
 #![feature(drop_types_in_const)]
 
-// Pull in our custom allocator.
-#[cfg(target_os = "none")]
+// For building globals with const functions, like critical_section_arm::Mutex.
+#![feature(const_fn)]
+
+// device_interrupts! numbers the interrupts by recursing once for each slot in the list.
+#![recursion_limit = "256"]
+
+// Pull in our custom allocator, whichever one is selected by the cargo features.
+#[cfg(all(target_os = "none", feature = "libc_allocator"))]
 extern crate libc_allocator;
+#[cfg(all(target_os = "none", feature = "block_allocator"))]
+extern crate block_allocator;
+
+// Heap usage statistics kept by the allocators.
+extern crate heap_stats;
 
 // We need this for the dynamic allocation on the heap used by vector.
 #[cfg(target_os = "none")]
@@ -38,48 +52,93 @@ extern crate collections;
 // For creating critical sections where we disable interrupts.
 extern crate critical_section_arm;
 
+// The device's registers, generated from its TivaWare header.
+extern crate tiva_registers;
+
+// Bindings for TivaWare's driverlib.
+extern crate tivaware;
+
+// The binary protocol for driving the firmware from a host.
+extern crate protocol;
+
+// On the target, core comes in through no_std. Pull it in by name for the host.
+#[cfg(not(target_os = "none"))]
+extern crate core;
+
 mod lang_items;
+// The logging macros, which every module after this one can use.
+#[macro_use]
+mod log;
+#[macro_use]
+mod startup;
+#[macro_use]
+mod device;
+mod board;
 mod vector_table;
 mod exception;
+mod nvic;
 mod led;
 mod button;
 mod event;
 mod systick;
+mod ring_buffer;
+mod uart;
+mod semihosting;
+mod config;
 mod state_machine;
+mod shell;
+mod remote;
 mod led_flash_controller;
+mod heap;
+mod stack;
 
+use event::Event;
 use led_flash_controller::LedFlashController;
+use remote::Remote;
+use shell::{Context, Shell};
 use state_machine::StateMachine;
 
-extern {
-    fn zero_fill_bss();
-    fn copy_initialized_data();
-}
+// The baud rate of the serial port.
+const SERIAL_BAUD: u32 = 115200;
 
 // Conceptually, this is our program "entry point". It's the first thing the microcontroller will
-// execute when it (re)boots. (As far as the linker is concerned the entry point must be named
-// `start` (by default; it can have a different name). That's why this function is `pub`lic, named
-// `start` and is marked as `#[no_mangle]`.)
+// execute when it (re)boots, once startup::reset has set up RAM. (As far as the linker is concerned
+// the entry point must be named `start` (by default; it can have a different name). That's why
+// this function is `pub`lic, named `start` and is marked as `#[no_mangle]`.)
 //
 // Returning from this function is undefined because there is nothing to return to! To statically
 // forbid returning from this function, we mark it as divergent, hence the `fn() -> !` signature.
 #[no_mangle]
 pub fn start() -> ! {
 
-    unsafe {
-        copy_initialized_data();
-        zero_fill_bss();
-    }
-
-    event::init();
-    systick::init(10); //Generate a time tick at 10 Hz.
+    exception::init();
+    stack::init();
+    heap::init();
+    let clock_hz = board::init_clock();
+    systick::init(clock_hz, 10); //Generate a time tick at 10 Hz.
+    uart::init(clock_hz, SERIAL_BAUD);
+    log::set_sink(log::uart);
     led::init();
     button::init();
+    info!("Started, system clock {} Hz", clock_hz);
+
+    // The settings saved in the EEPROM, or the defaults if it can't be used.
+    let mut settings = config::open();
+    if settings.is_none() {
+        error!("The EEPROM can't be used, settings won't be saved");
+    }
+    let config = settings.as_ref().map_or(config::DEFAULTS, |settings| settings.config());
     
-    let mut state_machine = StateMachine::new();
+    let mut state_machine = StateMachine::new(&config);
     let mut led_flash_controller = LedFlashController::new();
+    let mut shell = Shell::new();
+    let mut remote = Remote::new();
+    shell.start(&mut uart::Writer);
     
     loop {
+        heap::poll();
+        log::flush();
+        
         match event::get() {
             Some(e) => {
                 match state_machine.execute(&e) {
@@ -92,12 +151,54 @@ pub fn start() -> ! {
                     Some(next_event) => event::raise(next_event),
                     _ => (),
                 }
+                handle_serial(&e, &mut shell, &mut remote, &mut state_machine);
+                if let Some(ref mut settings) = settings {
+                    save_settings(settings, &state_machine);
+                }
             },
             None => {},
         }
     }
 }
 
+// Answer the host protocol requests which arrive on the serial port, and pass everything else to
+// the shell.
+fn handle_serial(event: &Event, shell: &mut Shell, remote: &mut Remote, state_machine: &mut StateMachine) {
+    match *event {
+        Event::SerialReceived => {
+            let mut bytes = [0; 16];
+            loop {
+                let count = uart::read(&mut bytes);
+                if count == 0 {
+                    break;
+                }
+                let mut typed = [0; 16];
+                let mut typed_count = 0;
+                for &byte in &bytes[..count] {
+                    if !remote.receive(byte, state_machine) {
+                        typed[typed_count] = byte;
+                        typed_count += 1;
+                    }
+                }
+                let mut context = Context { state_machine: &mut *state_machine };
+                shell.receive(&typed[..typed_count], &mut context, &mut uart::Writer);
+            }
+        },
+        Event::SerialError => {
+            warn!("Serial errors: {:?}", uart::take_errors());
+        },
+        _ => {},
+    }
+}
+
+// Save the state machine's settings whenever they change, from a button press, the shell or the
+// host protocol.
+fn save_settings(settings: &mut config::Store<config::Internal>, state_machine: &StateMachine) {
+    if let Err(error) = settings.save(&state_machine.config()) {
+        warn!("Couldn't save the settings, EEPROM status {:#x}", error.0);
+    }
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -0,0 +1,329 @@
+/*
+    Control of the Nested Vectored Interrupt Controller (NVIC).
+
+    Interrupts are enabled, pended and prioritized by name (see device::interrupt). Handlers can
+    also be installed at run time, which moves the vector table into RAM. TivaWare keeps the RAM
+    copy of the table in the `vtable` section, which the linker script places at the start of RAM
+    to meet its 1024 byte alignment.
+
+    The `nvic` shell command does the same from the terminal, naming interrupts as in
+    device::interrupt and exceptions as in TivaWare's hw_ints.h without the FAULT_ prefix:
+
+        > nvic UART0
+        enabled, priority 1
+        > nvic GPIOF priority 2
+        > nvic PENDSV priority 7
+
+    The interrupts which raise events can't be made more urgent than event::RAISE_PRIORITY, since
+    they share the event queue, the serial buffers and the log with code which only masks up to
+    event::RAISE_CEILING.
+*/
+
+use board;
+use core::fmt;
+use device::{self, interrupt, Handler, Interrupt};
+use event;
+use exception;
+use shell::{self, Command, Context};
+use tivaware::raw;
+
+// The TM4C implements the upper 3 bits of each 8-bit priority field.
+pub const PRIORITY_BITS: u8 = 3;
+
+// The number of distinct priority levels. Zero is the highest (most urgent) priority.
+pub const PRIORITY_LEVELS: u8 = 1 << PRIORITY_BITS;
+
+// System exceptions whose priority can be configured. The values are the exception numbers.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Exception {
+    MemoryFault = 4,
+    BusFault = 5,
+    UsageFault = 6,
+    SvCall = 11,
+    DebugMonitor = 12,
+    PendSv = 14,
+    SysTick = 15,
+}
+
+impl Exception {
+    // By the name TivaWare gives it (hw_ints.h), without the FAULT_ prefix.
+    fn find(name: &str) -> Option<Exception> {
+        match name {
+            "MPU" => Some(Exception::MemoryFault),
+            "BUS" => Some(Exception::BusFault),
+            "USAGE" => Some(Exception::UsageFault),
+            "SVCALL" => Some(Exception::SvCall),
+            "DEBUG" => Some(Exception::DebugMonitor),
+            "PENDSV" => Some(Exception::PendSv),
+            "SYSTICK" => Some(Exception::SysTick),
+            _ => None,
+        }
+    }
+}
+
+pub fn enable(interrupt: Interrupt) {
+    unsafe { raw::IntEnable(interrupt.vector()); }
+}
+
+pub fn disable(interrupt: Interrupt) {
+    unsafe { raw::IntDisable(interrupt.vector()); }
+}
+
+pub fn is_enabled(interrupt: Interrupt) -> bool {
+    unsafe { raw::IntIsEnabled(interrupt.vector()) != 0 }
+}
+
+// Make the interrupt pending, as though the peripheral had requested it.
+pub fn pend(interrupt: Interrupt) {
+    unsafe { raw::IntPendSet(interrupt.vector()); }
+}
+
+pub fn unpend(interrupt: Interrupt) {
+    unsafe { raw::IntPendClear(interrupt.vector()); }
+}
+
+// Set the priority of an interrupt, from 0 (highest) to PRIORITY_LEVELS - 1 (lowest).
+pub fn set_priority(interrupt: Interrupt, priority: u8) {
+    unsafe { raw::IntPrioritySet(interrupt.vector(), to_hardware_priority(priority)); }
+}
+
+pub fn priority(interrupt: Interrupt) -> u8 {
+    unsafe { from_hardware_priority(raw::IntPriorityGet(interrupt.vector()) as u8) }
+}
+
+// Turn on the handler for a fault (memory, bus or usage) or SysTick. Faults are escalated to a
+// hard fault until they're enabled.
+pub fn enable_exception(exception: Exception) {
+    unsafe { raw::IntEnable(exception as u32); }
+}
+
+// Set the priority of a system exception, from 0 (highest) to PRIORITY_LEVELS - 1 (lowest).
+pub fn set_exception_priority(exception: Exception, priority: u8) {
+    unsafe { raw::IntPrioritySet(exception as u32, to_hardware_priority(priority)); }
+}
+
+pub fn exception_priority(exception: Exception) -> u8 {
+    unsafe { from_hardware_priority(raw::IntPriorityGet(exception as u32) as u8) }
+}
+
+// Split the priority into preemption priority and subpriority. Interrupts only preempt each other
+// based on the upper `preemption_bits` of their priority, the rest only orders pending interrupts.
+pub fn set_priority_grouping(preemption_bits: u8) {
+    assert!(preemption_bits <= PRIORITY_BITS);
+    unsafe { raw::IntPriorityGroupingSet(preemption_bits as u32); }
+}
+
+// Install a handler for an interrupt at run time. The first call copies the vector table into RAM
+// and points the NVIC at the copy, so handlers bound with the interrupt! macro still apply to
+// every other interrupt.
+pub fn register(interrupt: Interrupt, handler: Handler) {
+    unsafe { raw::IntRegister(interrupt.vector(), Some(handler)); }
+}
+
+// Put the interrupt back to the default handler. Only meaningful once the vector table is in RAM.
+pub fn unregister(interrupt: Interrupt) {
+    unsafe { raw::IntUnregister(interrupt.vector()); }
+}
+
+// Put back the handler the interrupt is bound to in flash, undoing `register`. Reserved slots have
+// none, so they get TivaWare's default handler.
+pub fn restore(interrupt: Interrupt) {
+    match bound_handler(interrupt) {
+        Some(handler) => register(interrupt, handler),
+        None => unregister(interrupt),
+    }
+}
+
+#[cfg(target_os = "none")]
+fn bound_handler(interrupt: Interrupt) -> Option<Handler> {
+    device::INTERRUPTS[interrupt.number() as usize]
+}
+
+// The tests have no vector table, so nothing is bound.
+#[cfg(not(target_os = "none"))]
+fn bound_handler(_: Interrupt) -> Option<Handler> {
+    None
+}
+
+// The priority registers hold the priority in the upper bits of each byte. Also gives the ceiling
+// to mask interrupts up to a priority with (see critical_section_arm::PriorityCeiling). Panics if
+// the priority is beyond the implemented bits, so setting one is checked as it's made.
+pub fn to_hardware_priority(priority: u8) -> u8 {
+    assert!(priority < PRIORITY_LEVELS);
+    priority << (8 - PRIORITY_BITS)
+}
+
+pub fn from_hardware_priority(priority: u8) -> u8 {
+    priority >> (8 - PRIORITY_BITS)
+}
+
+pub const COMMANDS: &'static [Command] = &[
+    Command { name: "nvic", usage: "<name> [<action>]", help: "Show or change an interrupt", run: nvic_command },
+];
+
+// What the nvic command works on.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Target {
+    Interrupt(Interrupt),
+    Exception(Exception),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Action {
+    Show,
+    Enable,
+    Disable,
+    Pend,
+    Unpend,
+    // Send the interrupt to the default handler, which stops in the debugger (see exception).
+    Trap,
+    // Undo Trap.
+    Restore,
+    Priority(u8),
+}
+
+fn parse(args: &[&str]) -> Result<(Target, Action), &'static str> {
+    let target = match args.first() {
+        Some(name) => match device::find(name) {
+            Some(interrupt) => Target::Interrupt(interrupt),
+            None => Target::Exception(Exception::find(name).ok_or("No such interrupt")?),
+        },
+        None => return Err("Expected a name"),
+    };
+    let action = match (args.get(1), args.len()) {
+        (None, _) => Action::Show,
+        (Some(&"enable"), 2) => Action::Enable,
+        (Some(&"disable"), 2) => Action::Disable,
+        (Some(&"pend"), 2) => Action::Pend,
+        (Some(&"unpend"), 2) => Action::Unpend,
+        (Some(&"trap"), 2) => Action::Trap,
+        (Some(&"restore"), 2) => Action::Restore,
+        (Some(&"priority"), 3) => {
+            let priority = shell::number(args[2])?;
+            if priority >= PRIORITY_LEVELS as usize {
+                return Err("Out of range");
+            }
+            if priority < event::RAISE_PRIORITY as usize && raises_events(target) {
+                return Err("Raises events, so can't be more urgent than the event priority");
+            }
+            Action::Priority(priority as u8)
+        },
+        _ => return Err("Expected enable, disable, pend, unpend, trap, restore or priority <priority>"),
+    };
+    match (target, action) {
+        (Target::Exception(_), Action::Show) |
+        (Target::Exception(_), Action::Enable) |
+        (Target::Exception(_), Action::Priority(_)) |
+        (Target::Interrupt(_), _) => Ok((target, action)),
+        (Target::Exception(_), _) => Err("Exceptions can only be enabled or prioritized"),
+    }
+}
+
+// The interrupts which raise events, which all run at event::RAISE_PRIORITY.
+fn raises_events(target: Target) -> bool {
+    match target {
+        Target::Interrupt(interrupt) => {
+            interrupt == board::BUTTON_INTERRUPT || interrupt == interrupt::UART0
+        },
+        Target::Exception(exception) => exception == Exception::SysTick,
+    }
+}
+
+fn nvic_command(_: &mut Context, args: &[&str], out: &mut fmt::Write) -> Result<(), &'static str> {
+    match parse(args)? {
+        (Target::Interrupt(interrupt), Action::Show) => {
+            let state = if is_enabled(interrupt) { "enabled" } else { "disabled" };
+            let _ = write!(out, "{}, priority {}\r\n", state, priority(interrupt));
+        },
+        (Target::Interrupt(interrupt), Action::Enable) => enable(interrupt),
+        (Target::Interrupt(interrupt), Action::Disable) => disable(interrupt),
+        (Target::Interrupt(interrupt), Action::Pend) => pend(interrupt),
+        (Target::Interrupt(interrupt), Action::Unpend) => unpend(interrupt),
+        (Target::Interrupt(interrupt), Action::Trap) => {
+            register(interrupt, exception::default_handler as Handler)
+        },
+        (Target::Interrupt(interrupt), Action::Restore) => restore(interrupt),
+        (Target::Interrupt(interrupt), Action::Priority(priority)) => set_priority(interrupt, priority),
+        (Target::Exception(exception), Action::Show) => {
+            let _ = write!(out, "priority {}\r\n", exception_priority(exception));
+        },
+        (Target::Exception(exception), Action::Enable) => enable_exception(exception),
+        (Target::Exception(exception), Action::Priority(priority)) => {
+            set_exception_priority(exception, priority)
+        },
+        (Target::Exception(_), _) => unreachable!(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priorities_are_stored_in_the_upper_bits() {
+        assert_eq!(0x00, to_hardware_priority(0));
+        assert_eq!(0x20, to_hardware_priority(1));
+        assert_eq!(0xE0, to_hardware_priority(7));
+    }
+
+    #[test]
+    fn hardware_priorities_convert_back() {
+        for priority in 0 .. PRIORITY_LEVELS {
+            assert_eq!(priority, from_hardware_priority(to_hardware_priority(priority)));
+        }
+    }
+
+    #[test]
+    fn interrupts_and_exceptions_are_found_by_name() {
+        assert_eq!(Ok((Target::Interrupt(interrupt::UART0), Action::Show)), parse(&["UART0"]));
+        assert_eq!(Ok((Target::Exception(Exception::PendSv), Action::Priority(7))),
+                   parse(&["PENDSV", "priority", "7"]));
+        assert_eq!(Err("No such interrupt"), parse(&["UART99"]));
+        assert_eq!(Err("Expected a name"), parse(&[]));
+    }
+
+    #[test]
+    fn every_action_can_be_named() {
+        let actions = [("enable", Action::Enable), ("disable", Action::Disable), ("pend", Action::Pend),
+                       ("unpend", Action::Unpend), ("trap", Action::Trap), ("restore", Action::Restore)];
+        for &(name, action) in actions.iter() {
+            assert_eq!(Ok((Target::Interrupt(interrupt::GPIOA), action)), parse(&["GPIOA", name]));
+        }
+        assert!(parse(&["GPIOA", "explode"]).is_err());
+    }
+
+    #[test]
+    fn priorities_are_checked_before_they_are_set() {
+        assert_eq!(Err("Out of range"), parse(&["GPIOA", "priority", "8"]));
+        assert_eq!(Err("Expected a number"), parse(&["GPIOA", "priority", "high"]));
+    }
+
+    #[test]
+    fn what_raises_events_stays_at_or_below_the_event_priority() {
+        assert!(parse(&["UART0", "priority", "0"]).is_err());
+        assert!(parse(&["SYSTICK", "priority", "0"]).is_err());
+        assert_eq!(Ok((Target::Interrupt(interrupt::UART0), Action::Priority(event::RAISE_PRIORITY))),
+                   parse(&["UART0", "priority", "1"]));
+        assert_eq!(Ok((Target::Interrupt(interrupt::GPIOA), Action::Priority(0))),
+                   parse(&["GPIOA", "priority", "0"]));
+    }
+
+    #[test]
+    fn the_tests_have_no_bound_handlers() {
+        assert_eq!(None, bound_handler(interrupt::UART0).map(|handler| handler as usize));
+    }
+
+    #[test]
+    fn exceptions_can_only_be_enabled_or_prioritized() {
+        assert!(parse(&["BUS", "enable"]).is_ok());
+        assert!(parse(&["BUS", "pend"]).is_err());
+        assert!(parse(&["SYSTICK", "trap"]).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn priorities_beyond_the_implemented_bits_are_rejected() {
+        to_hardware_priority(PRIORITY_LEVELS);
+    }
+}
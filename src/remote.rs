@@ -0,0 +1,85 @@
+/*
+    The device's end of the host protocol (see lib/protocol), for test rigs and other tools.
+    Requests arrive in frames on the serial port among the bytes typed at the shell. Remote picks
+    them out, carries them out and sends back the responses, and hands back everything else for
+    the shell.
+*/
+
+use config::Setting;
+use event::{self, Event};
+use protocol::{ErrorCode, Handler, Parameter, Request, Response, Server};
+use state_machine::StateMachine;
+use uart;
+
+pub struct Remote {
+    server: Server,
+}
+
+impl Remote {
+    pub fn new() -> Remote {
+        Remote { server: Server::new() }
+    }
+
+    // Take a byte from the serial port. Returns false if it isn't part of a request, so is for
+    // the shell.
+    pub fn receive(&mut self, byte: u8, state_machine: &mut StateMachine) -> bool {
+        let mut device = Device { state_machine: state_machine };
+        self.server.receive(byte, &mut device, &mut |frame| uart::write_all(frame))
+    }
+}
+
+struct Device<'a> {
+    state_machine: &'a mut StateMachine,
+}
+
+impl<'a> Handler for Device<'a> {
+    fn handle(&mut self, request: Request) -> Response {
+        match request {
+            Request::Ping => Response::Pong,
+            Request::RaiseEvent(event) => {
+                event::raise(Event::from(event));
+                Response::Done
+            },
+            Request::GetParameter(parameter) => {
+                Response::Value(self.state_machine.config().get(setting(parameter)))
+            },
+            Request::SetParameter(parameter, value) => {
+                match self.state_machine.set(setting(parameter), value) {
+                    Ok(()) => Response::Done,
+                    Err(_) => Response::Error(ErrorCode::BadValue),
+                }
+            },
+        }
+    }
+}
+
+fn setting(parameter: Parameter) -> Setting {
+    match parameter {
+        Parameter::FlashCount => Setting::FlashCount,
+        Parameter::LedOnTime => Setting::LedOnTime,
+        Parameter::LedOffTime => Setting::LedOffTime,
+        Parameter::WaitTime => Setting::WaitTime,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config;
+
+    #[test]
+    fn parameters_are_the_state_machines_settings() {
+        let mut state_machine = StateMachine::new(&config::DEFAULTS);
+        {
+            let mut device = Device { state_machine: &mut state_machine };
+            assert_eq!(Response::Done, device.handle(Request::SetParameter(Parameter::WaitTime, 7)));
+            assert_eq!(Response::Value(7), device.handle(Request::GetParameter(Parameter::WaitTime)));
+            assert_eq!(Response::Value(1), device.handle(Request::GetParameter(Parameter::FlashCount)));
+            assert_eq!(Response::Error(ErrorCode::BadValue),
+                       device.handle(Request::SetParameter(Parameter::LedOnTime, 0xFFFFFFFF)));
+            assert_eq!(Response::Error(ErrorCode::BadValue),
+                       device.handle(Request::SetParameter(Parameter::FlashCount, 0)));
+        }
+        assert_eq!(7, state_machine.config().wait_time);
+    }
+}
@@ -0,0 +1,201 @@
+/*
+    A fixed size first-in first-out queue of bytes, which never allocates. Used to buffer data
+    between the main loop and an interrupt handler (inside a critical section).
+
+    The bytes are kept in an array. `RingBuffer` on its own holds CAPACITY bytes; other sizes are
+    made with `with_storage`:
+
+        let mut log: RingBuffer<[u8; 1024]> = RingBuffer::with_storage([0; 1024]);
+*/
+
+use core::cmp;
+
+// The number of bytes a ring buffer holds by default.
+pub const CAPACITY: usize = 128;
+
+// The array a ring buffer keeps its bytes in.
+pub trait Storage {
+    fn bytes(&self) -> &[u8];
+    fn bytes_mut(&mut self) -> &mut [u8];
+}
+
+macro_rules! array_storage {
+    ($($size:expr),*) => {
+        $(
+            impl Storage for [u8; $size] {
+                fn bytes(&self) -> &[u8] { self }
+                fn bytes_mut(&mut self) -> &mut [u8] { self }
+            }
+        )*
+    }
+}
+
+array_storage!(128, 512, 1024);
+
+pub struct RingBuffer<S = [u8; CAPACITY]> {
+    data: S,
+    // Where the oldest byte is.
+    start: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    pub const fn new() -> RingBuffer {
+        RingBuffer { data: [0; CAPACITY], start: 0, len: 0 }
+    }
+}
+
+impl<S> RingBuffer<S> where S: Storage {
+    pub fn with_storage(data: S) -> RingBuffer<S> {
+        RingBuffer { data: data, start: 0, len: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.bytes().len()
+    }
+
+    // How many more bytes fit.
+    pub fn space(&self) -> usize {
+        self.capacity() - self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    // Add a byte at the end. False (and the byte is dropped) if the buffer is full.
+    pub fn push(&mut self, byte: u8) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        let end = (self.start + self.len) % self.capacity();
+        self.data.bytes_mut()[end] = byte;
+        self.len += 1;
+        true
+    }
+
+    // Add a byte at the end, dropping the oldest byte to make room if the buffer is full.
+    pub fn push_overwriting(&mut self, byte: u8) {
+        if self.is_full() {
+            self.pop();
+        }
+        self.push(byte);
+    }
+
+    // Take the oldest byte.
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+        let byte = self.data.bytes()[self.start];
+        self.start = (self.start + 1) % self.capacity();
+        self.len -= 1;
+        Some(byte)
+    }
+
+    // Add as many bytes as fit. Returns how many were added.
+    pub fn extend(&mut self, bytes: &[u8]) -> usize {
+        bytes.iter().take_while(|&&byte| self.push(byte)).count()
+    }
+
+    // Copy the oldest bytes into `buffer` without taking them. Returns how many were copied.
+    pub fn peek(&self, buffer: &mut [u8]) -> usize {
+        let count = cmp::min(self.len, buffer.len());
+        for (i, byte) in buffer[..count].iter_mut().enumerate() {
+            *byte = self.data.bytes()[(self.start + i) % self.capacity()];
+        }
+        count
+    }
+
+    // Drop the oldest `count` bytes (or all of them, if there are fewer).
+    pub fn discard(&mut self, count: usize) {
+        let count = cmp::min(count, self.len);
+        self.start = (self.start + count) % self.capacity();
+        self.len -= count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_come_out_in_the_order_they_went_in() {
+        let mut buffer = RingBuffer::new();
+        assert!(buffer.push(1));
+        assert!(buffer.push(2));
+        assert!(!buffer.is_empty());
+        assert_eq!(Some(1), buffer.pop());
+        assert_eq!(Some(2), buffer.pop());
+        assert_eq!(None, buffer.pop());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn a_full_buffer_drops_new_bytes() {
+        let mut buffer = RingBuffer::new();
+        for i in 0..CAPACITY {
+            assert!(buffer.push(i as u8));
+        }
+        assert!(buffer.is_full());
+        assert!(!buffer.push(0xFF));
+        assert_eq!(Some(0), buffer.pop());
+    }
+
+    #[test]
+    fn the_buffer_wraps_around() {
+        let mut buffer = RingBuffer::new();
+        for round in 0..3 * CAPACITY {
+            assert!(buffer.push(round as u8));
+            assert!(buffer.push((round + 1) as u8));
+            assert_eq!(Some(round as u8), buffer.pop());
+            assert_eq!(Some((round + 1) as u8), buffer.pop());
+        }
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn extend_adds_what_fits() {
+        let mut buffer = RingBuffer::new();
+        assert_eq!(3, buffer.extend(b"abc"));
+        let lots = [0; CAPACITY];
+        assert_eq!(CAPACITY - 3, buffer.extend(&lots));
+        assert_eq!(Some(b'a'), buffer.pop());
+    }
+
+    #[test]
+    fn peek_leaves_the_bytes_until_they_are_discarded() {
+        let mut buffer = RingBuffer::new();
+        buffer.extend(b"abcd");
+        let mut bytes = [0; 3];
+        assert_eq!(3, buffer.peek(&mut bytes));
+        assert_eq!(b"abc", &bytes);
+        buffer.discard(2);
+        assert_eq!(2, buffer.peek(&mut bytes));
+        assert_eq!(b"cd", &bytes[..2]);
+        buffer.discard(10);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn overwriting_drops_the_oldest_bytes() {
+        let mut buffer = RingBuffer::new();
+        for i in 0..CAPACITY + 2 {
+            buffer.push_overwriting(i as u8);
+        }
+        assert!(buffer.is_full());
+        assert_eq!(Some(2), buffer.pop());
+    }
+
+    #[test]
+    fn buffers_can_have_other_sizes() {
+        let mut buffer = RingBuffer::with_storage([0; 512]);
+        assert_eq!(512, buffer.capacity());
+        assert_eq!(200, buffer.extend(&[1; 200]));
+        assert_eq!(312, buffer.space());
+    }
+}
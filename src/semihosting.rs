@@ -0,0 +1,41 @@
+/*
+    ARM semihosting: asking the debugger to do things on the host, here to print to its console.
+    A request is a `bkpt 0xAB` with the operation in r0 and its argument in r1, which the debugger
+    catches, handles and resumes from.
+
+    This only works while a debugger with semihosting turned on is attached (`rake gdb` turns it
+    on in openocd). Without one, the breakpoint causes a hard fault. Each request stops the
+    processor until the debugger has handled it, so it's slow.
+*/
+
+// Write a NUL terminated string to the debugger's console.
+const SYS_WRITE0: u32 = 0x04;
+
+// The most bytes written with each request, leaving room for the NUL.
+const CHUNK: usize = 64;
+
+// Print the bytes on the debugger's console. NUL bytes end up cutting a chunk short.
+pub fn write(bytes: &[u8]) {
+    for chunk in bytes.chunks(CHUNK) {
+        let mut text = [0; CHUNK + 1];
+        text[..chunk.len()].copy_from_slice(chunk);
+        unsafe { call(SYS_WRITE0, text.as_ptr()); }
+    }
+}
+
+#[cfg(target_arch = "arm")]
+unsafe fn call(operation: u32, argument: *const u8) -> u32 {
+    let result: u32;
+    asm!("bkpt 0xAB"
+         : "={r0}"(result)
+         : "{r0}"(operation), "{r1}"(argument)
+         : "memory"
+         : "volatile");
+    result
+}
+
+// There's no debugger to ask on the host.
+#[cfg(not(target_arch = "arm"))]
+unsafe fn call(_operation: u32, _argument: *const u8) -> u32 {
+    0
+}
@@ -0,0 +1,227 @@
+/*
+    A line based command shell on the serial port, for looking at and changing the running system.
+
+    Each line is a command name followed by its arguments, separated by spaces. Lines are edited
+    with backspace and run with enter. `help` lists the commands.
+
+    Commands come from the tables in COMMANDS. A component adds its own by defining a table of
+    them and listing it there:
+
+        pub const COMMANDS: &'static [Command] = &[
+            Command { name: "stack", usage: "", help: "Show stack usage", run: show_stack },
+        ];
+
+    A command gets the Context (the parts of the system which aren't globals), its arguments
+    (without the name), and somewhere to write its reply. Errors are shown with its usage.
+*/
+
+use core::fmt::{self, Write};
+use core::str;
+use event;
+use heap;
+use log;
+use nvic;
+use stack;
+use state_machine::{self, StateMachine};
+use tivaware::sysctl;
+
+pub struct Command {
+    pub name: &'static str,
+    // The arguments, as shown by help.
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub run: fn(&mut Context, &[&str], &mut fmt::Write) -> Result<(), &'static str>,
+}
+
+// What commands can reach besides the globals.
+pub struct Context<'a> {
+    pub state_machine: &'a mut StateMachine,
+}
+
+// Every component's commands.
+const COMMANDS: &'static [&'static [Command]] = &[
+    SHELL_COMMANDS,
+    state_machine::COMMANDS,
+    event::COMMANDS,
+    heap::COMMANDS,
+    stack::COMMANDS,
+    log::COMMANDS,
+    nvic::COMMANDS,
+];
+
+const SHELL_COMMANDS: &'static [Command] = &[
+    Command { name: "help", usage: "", help: "List the commands", run: help },
+    Command { name: "reset", usage: "", help: "Reset the processor", run: reset },
+];
+
+// The longest line, and the most words in it.
+const LINE_LENGTH: usize = 64;
+const MAX_WORDS: usize = 8;
+
+const PROMPT: &'static str = "> ";
+
+pub struct Shell {
+    line: [u8; LINE_LENGTH],
+    len: usize,
+    // The last byte was a carriage return, so a line feed straight after it isn't another line.
+    after_return: bool,
+}
+
+impl Shell {
+    pub fn new() -> Shell {
+        Shell { line: [0; LINE_LENGTH], len: 0, after_return: false }
+    }
+
+    // Show the first prompt.
+    pub fn start(&self, out: &mut fmt::Write) {
+        let _ = write!(out, "\r\nType 'help' for the commands.\r\n{}", PROMPT);
+    }
+
+    // Handle bytes typed at the terminal, echoing them and running each line as it's finished.
+    pub fn receive(&mut self, bytes: &[u8], context: &mut Context, out: &mut fmt::Write) {
+        for &byte in bytes {
+            let after_return = self.after_return;
+            self.after_return = byte == b'\r';
+            match byte {
+                b'\n' if after_return => {},
+                b'\r' | b'\n' => {
+                    let _ = out.write_str("\r\n");
+                    self.run_line(context, out);
+                    self.len = 0;
+                    let _ = out.write_str(PROMPT);
+                },
+                // Backspace or delete.
+                0x08 | 0x7F => {
+                    if self.len > 0 {
+                        self.len -= 1;
+                        let _ = out.write_str("\x08 \x08");
+                    }
+                },
+                b' '...b'~' if self.len < LINE_LENGTH => {
+                    self.line[self.len] = byte;
+                    self.len += 1;
+                    let _ = out.write_char(byte as char);
+                },
+                // Control characters, and anything past the end of the line.
+                _ => {},
+            }
+        }
+    }
+
+    fn run_line(&self, context: &mut Context, out: &mut fmt::Write) {
+        // Only printable ASCII goes in the line.
+        let line = str::from_utf8(&self.line[..self.len]).unwrap_or("");
+        let mut words = [""; MAX_WORDS];
+        let mut count = 0;
+        for word in line.split_whitespace() {
+            if count == MAX_WORDS {
+                let _ = write!(out, "Too many arguments\r\n");
+                return;
+            }
+            words[count] = word;
+            count += 1;
+        }
+        if count == 0 {
+            return;
+        }
+
+        match find(words[0]) {
+            Some(command) => {
+                if let Err(message) = (command.run)(context, &words[1..count], out) {
+                    let _ = write!(out, "{}\r\nUsage: {} {}\r\n", message, command.name, command.usage);
+                }
+            },
+            None => {
+                let _ = write!(out, "Unknown command '{}'. Type 'help' for the commands.\r\n", words[0]);
+            },
+        }
+    }
+}
+
+fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().flat_map(|table| table.iter()).find(|command| command.name == name)
+}
+
+// A number argument.
+pub fn number(argument: &str) -> Result<usize, &'static str> {
+    argument.parse().map_err(|_| "Expected a number")
+}
+
+fn help(_: &mut Context, _: &[&str], out: &mut fmt::Write) -> Result<(), &'static str> {
+    for command in COMMANDS.iter().flat_map(|table| table.iter()) {
+        let _ = write!(out, "{:<12} {:<20} {}\r\n", command.name, command.usage, command.help);
+    }
+    Ok(())
+}
+
+fn reset(_: &mut Context, _: &[&str], _: &mut fmt::Write) -> Result<(), &'static str> {
+    sysctl::reset_system();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config;
+    use collections::String;
+    use state_machine::StateMachine;
+
+    fn typed(shell: &mut Shell, state_machine: &mut StateMachine, text: &str) -> String {
+        let mut out = String::new();
+        shell.receive(text.as_bytes(), &mut Context { state_machine: state_machine }, &mut out);
+        out
+    }
+
+    fn run(text: &str) -> String {
+        let mut shell = Shell::new();
+        let mut state_machine = StateMachine::new(&config::DEFAULTS);
+        typed(&mut shell, &mut state_machine, text)
+    }
+
+    #[test]
+    fn typing_is_echoed_and_lines_are_run() {
+        let mut shell = Shell::new();
+        let mut state_machine = StateMachine::new(&config::DEFAULTS);
+        assert_eq!("flash_c", typed(&mut shell, &mut state_machine, "flash_c"));
+        assert_eq!("ount\r\n1\r\n> ", typed(&mut shell, &mut state_machine, "ount\r\n"));
+    }
+
+    #[test]
+    fn backspace_removes_the_last_character() {
+        assert_eq!("x\x08 \x08\r\n> ", run("x\x7F\r"));
+        // There's nothing to remove from an empty line.
+        assert_eq!("\r\n> ", run("\x08\r"));
+    }
+
+    #[test]
+    fn unknown_commands_are_reported() {
+        assert_eq!("nope\r\nUnknown command 'nope'. Type 'help' for the commands.\r\n> ", run("nope\n"));
+    }
+
+    #[test]
+    fn errors_show_the_usage() {
+        assert!(run("flash_count x\r").ends_with("\r\nExpected a number\r\nUsage: flash_count [<count>]\r\n> "));
+    }
+
+    #[test]
+    fn help_lists_every_command() {
+        let out = run("help\r");
+        for command in COMMANDS.iter().flat_map(|table| table.iter()) {
+            assert!(out.contains(command.name));
+        }
+    }
+
+    #[test]
+    fn commands_have_unique_names() {
+        for command in COMMANDS.iter().flat_map(|table| table.iter()) {
+            assert_eq!(1, COMMANDS.iter().flat_map(|table| table.iter())
+                .filter(|other| other.name == command.name).count());
+        }
+    }
+
+    #[test]
+    fn long_lines_are_cut_off() {
+        let line: String = (0..LINE_LENGTH + 10).map(|_| 'a').collect();
+        let out = run(&line);
+        assert_eq!(LINE_LENGTH, out.len());
+    }
+}
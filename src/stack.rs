@@ -0,0 +1,150 @@
+/*
+    Stack monitoring.
+
+    At reset the unused part of the stack is painted with a known pattern, by a pre-init hook so
+    that RAM initialization counts too. Whatever the stack has overwritten since is the most it
+    has ever used (the high-water mark).
+
+    Below the stack (between it and the heap) the linker script leaves a small guard region which
+    the MPU makes inaccessible. Growing the stack into it raises a memory management fault,
+    instead of quietly overwriting the heap. See exception::memory_fault.
+*/
+
+use core::fmt::{self, Write};
+use core::ptr;
+use nvic;
+use shell::{Command, Context};
+use tivaware::raw;
+
+extern {
+    // From the linker script.
+    static _stack_top: u32;
+    static _stack_bottom: u32;
+    static _stack_guard: u32;
+    static _stack_guard_size: u8;
+}
+
+const PAINT: u32 = 0xDEADBEEF;
+
+// The MPU region used for the guard.
+const GUARD_REGION: u32 = 0;
+
+fn top() -> usize {
+    unsafe { &_stack_top as *const u32 as usize }
+}
+
+fn bottom() -> usize {
+    unsafe { &_stack_bottom as *const u32 as usize }
+}
+
+// The guard region, as (start, size).
+pub fn guard() -> (usize, usize) {
+    unsafe { (&_stack_guard as *const u32 as usize, &_stack_guard_size as *const u8 as usize) }
+}
+
+pub fn size() -> usize {
+    top() - bottom()
+}
+
+// Turn on the guard. Call this first thing at boot.
+pub fn init() {
+    enable_guard();
+}
+
+// The most stack used since boot, in bytes.
+pub fn high_water_mark() -> usize {
+    let mut address = bottom();
+    while address < top() && unsafe { ptr::read_volatile(address as *const u32) } == PAINT {
+        address += 4;
+    }
+    top() - address
+}
+
+pre_init!(PAINT_STACK, paint);
+
+// Fill everything below the current stack pointer with the paint. This runs before RAM is
+// initialized, so it only uses the linker's symbols.
+#[inline(never)]
+fn paint() {
+    let mut address = bottom();
+    let end = stack_pointer();
+    while address < end {
+        unsafe { ptr::write_volatile(address as *mut u32, PAINT); }
+        address += 4;
+    }
+}
+
+fn enable_guard() {
+    let (start, size) = guard();
+    unsafe {
+        raw::MPURegionSet(GUARD_REGION, start as u32,
+                          region_size(size) | raw::MPU_RGN_PERM_NOEXEC |
+                          raw::MPU_RGN_PERM_PRV_NO_USR_NO | raw::MPU_RGN_ENABLE);
+        raw::MPURegionEnable(GUARD_REGION);
+        // Use the default memory map outside of the regions.
+        raw::MPUEnable(raw::MPU_CONFIG_PRIV_DEFAULT);
+    }
+    nvic::enable_exception(nvic::Exception::MemoryFault);
+}
+
+// The MPU encodes a region of 2^(n + 1) bytes as n in bits 1 to 5.
+fn region_size(size: usize) -> u32 {
+    (size.trailing_zeros() - 1) << 1
+}
+
+#[cfg(target_arch = "arm")]
+fn stack_pointer() -> usize {
+    let sp: usize;
+    unsafe { asm!("mov $0, sp" : "=r"(sp) ::: "volatile"); }
+    sp
+}
+
+#[cfg(not(target_arch = "arm"))]
+fn stack_pointer() -> usize {
+    unimplemented!();
+}
+
+// True when the address is in the region, given as (start, size).
+pub fn contains(region: (usize, usize), address: usize) -> bool {
+    let (start, size) = region;
+    address >= start && address - start < size
+}
+
+pub const COMMANDS: &'static [Command] = &[
+    Command { name: "stack", usage: "", help: "Show stack usage", run: show_stack },
+];
+
+fn show_stack(_: &mut Context, _: &[&str], out: &mut fmt::Write) -> Result<(), &'static str> {
+    let (guard_start, guard_size) = guard();
+    let _ = write!(out, "size: {}\r\nhigh-water mark: {}\r\nguard: {:#010x}, {} bytes\r\n",
+                   size(), high_water_mark(), guard_start, guard_size);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{contains, region_size};
+
+    #[test]
+    fn region_sizes_are_encoded_like_tivaware() {
+        assert_eq!(4 << 1, region_size(32));
+        assert_eq!(7 << 1, region_size(256));
+        assert_eq!(11 << 1, region_size(4096));
+    }
+
+    #[test]
+    fn regions_contain_their_start_but_not_their_end() {
+        let region = (0x2000_1000, 256);
+        assert!(!contains(region, 0x2000_0FFF));
+        assert!(contains(region, 0x2000_1000));
+        assert!(contains(region, 0x2000_10FF));
+        assert!(!contains(region, 0x2000_1100));
+    }
+
+    #[test]
+    fn regions_at_the_top_of_memory_do_not_overflow() {
+        let region = (usize::max_value() - 255, 256);
+        assert!(contains(region, usize::max_value()));
+        assert!(!contains(region, 0));
+    }
+}
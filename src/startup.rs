@@ -0,0 +1,101 @@
+/*
+    The reset handler. This gets RAM and the processor ready before handing over to `start`.
+
+    Until RAM is initialized nothing here may touch a static, since .data still holds whatever was
+    in RAM before reset and .bss isn't zeroed yet. The pre-init hooks run at that point too, so
+    they may only use statics in .noinit.
+
+    The .noinit section is left alone on reset (only a power cycle clears it). Put data there which
+    should survive a reset:
+
+        #[link_section = ".noinit"]
+        static mut RESET_REASON: u32 = 0;
+*/
+
+use core::ptr;
+use tiva_registers::nvic;
+use tiva_registers::fields::nvic::cpac;
+
+extern {
+    // From the linker script. All of these are word aligned.
+    static mut _data: u32;   // The start of the data section in RAM.
+    static mut _edata: u32;  // The end of the data section in RAM.
+    static _ldata: u32;      // The initial values of the data section, in flash.
+    static mut _bss: u32;    // The start of the bss section.
+    static mut _ebss: u32;   // The end of the bss section.
+
+    // The pre-init hooks, from the .pre_init section.
+    static _pre_init: PreInitHook;
+    static _epre_init: PreInitHook;
+}
+
+// A function to run at reset, before RAM is initialized.
+pub type PreInitHook = fn();
+
+// Run a function at reset, before RAM is initialized. It's put in the .pre_init section, which the
+// reset handler runs through in link order.
+//
+//     pre_init!(PAINT_STACK, paint);
+macro_rules! pre_init {
+    ($name:ident, $hook:path) => {
+        #[link_section = ".pre_init"]
+        #[no_mangle]
+        pub static $name: ::startup::PreInitHook = $hook;
+    };
+}
+
+pub fn reset() -> ! {
+    unsafe {
+        // The compiler is free to use floating point registers anywhere, so the FPU goes first.
+        enable_fpu();
+        run_pre_init_hooks();
+        copy_initialized_data();
+        zero_fill_bss();
+    }
+    ::start()
+}
+
+// Give full access to the FPU (coprocessors 10 and 11).
+unsafe fn enable_fpu() {
+    nvic::CPAC.modify(|value| value | cpac::CP10.mask() | cpac::CP11.mask());
+    barrier();
+}
+
+unsafe fn run_pre_init_hooks() {
+    let mut hook = &_pre_init as *const PreInitHook;
+    while hook < &_epre_init as *const PreInitHook {
+        (ptr::read_volatile(hook))();
+        hook = hook.offset(1);
+    }
+}
+
+// Initialize the data section to values stored in flash at _ldata.
+unsafe fn copy_initialized_data() {
+    let mut source = &_ldata as *const u32;
+    let mut destination = &mut _data as *mut u32;
+    while destination < &mut _edata as *mut u32 {
+        ptr::write_volatile(destination, ptr::read_volatile(source));
+        source = source.offset(1);
+        destination = destination.offset(1);
+    }
+}
+
+// Initialize the entire bss section to 0.
+unsafe fn zero_fill_bss() {
+    let mut destination = &mut _bss as *mut u32;
+    while destination < &mut _ebss as *mut u32 {
+        ptr::write_volatile(destination, 0);
+        destination = destination.offset(1);
+    }
+}
+
+// Make sure the FPU is on before any more instructions run.
+#[cfg(target_arch = "arm")]
+unsafe fn barrier() {
+    asm!("dsb\n\tisb" :::: "volatile");
+}
+
+#[cfg(not(target_arch = "arm"))]
+unsafe fn barrier() {
+    unimplemented!();
+}
@@ -1,39 +1,55 @@
 // Implements the main state machine for the system.
 
+use config::{Config, Setting};
+use core::fmt::{self, Write};
 use event::Event;
 use led;
+use shell::{self, Command, Context};
 
 pub struct StateMachine {
-    flash_count: usize,
+    // The flash count and times, which the shell and the host protocol can change.
+    config: Config,
     flash_in_progress: bool,
     pause_time_remaining: usize,
 }
 
 impl StateMachine {
-    pub fn new() -> StateMachine {
+    // Start with the settings saved in the EEPROM (see config.rs).
+    pub fn new(config: &Config) -> StateMachine {
         StateMachine {
-            flash_count: 1,
+            config: *config,
             flash_in_progress: false,
             pause_time_remaining: 0,
         }
     }
-    
+
+    // The settings, to be saved.
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    // Change a setting, if the value is in its range. Changes take effect from the next flash.
+    pub fn set(&mut self, setting: Setting, value: u32) -> Result<(), &'static str> {
+        self.config.set(setting, value)
+    }
+
     pub fn execute(&mut self, event: &Event) -> Option<Event>{
         
-        // All times are in 10 Hz ticks.
-        const LED_ON_TIME: usize = 4;
-        const LED_OFF_TIME: usize = 3;
-        const WAIT_TIME: usize = 20;
-        
         match *event {
             Event::ButtonPress => {
-                self.flash_count += 1;
+                // Past the most flashes, presses do nothing.
+                let flash_count = self.config.flash_count + 1;
+                let _ = self.config.set(Setting::FlashCount, flash_count);
                 None
             },
             Event::TimeTick if (!self.flash_in_progress && self.pause_time_remaining == 0) => {
                 // Start the next flash.
                 self.flash_in_progress = true;
-                Some(Event::FlashLed { count: self.flash_count, on_time: LED_ON_TIME, off_time: LED_OFF_TIME })
+                Some(Event::FlashLed {
+                    count: self.config.flash_count as usize,
+                    on_time: self.config.led_on_time as usize,
+                    off_time: self.config.led_off_time as usize,
+                })
             },
             Event::TimeTick if self.pause_time_remaining > 0 => {
                 //We're waiting to start the next flash.
@@ -42,7 +58,7 @@ impl StateMachine {
             },
             Event::FlashLedDone => {
                 self.flash_in_progress = false;
-                self.pause_time_remaining = WAIT_TIME;
+                self.pause_time_remaining = self.config.wait_time as usize;
                 None
             }
             Event::LedTurnOn => {
@@ -56,4 +72,105 @@ impl StateMachine {
             _ => None,
         }
     }
-}
\ No newline at end of file
+}
+
+pub const COMMANDS: &'static [Command] = &[
+    Command { name: "flash_count", usage: "[<count>]", help: "Show or set the number of flashes", run: flash_count },
+    Command { name: "on_time", usage: "[<ticks>]", help: "Show or set how long the LED is on", run: on_time },
+    Command { name: "off_time", usage: "[<ticks>]", help: "Show or set how long the LED is off", run: off_time },
+    Command { name: "wait_time", usage: "[<ticks>]", help: "Show or set the pause between flashes", run: wait_time },
+];
+
+fn flash_count(context: &mut Context, args: &[&str], out: &mut fmt::Write) -> Result<(), &'static str> {
+    show_or_set(context, Setting::FlashCount, args, out)
+}
+
+fn on_time(context: &mut Context, args: &[&str], out: &mut fmt::Write) -> Result<(), &'static str> {
+    show_or_set(context, Setting::LedOnTime, args, out)
+}
+
+fn off_time(context: &mut Context, args: &[&str], out: &mut fmt::Write) -> Result<(), &'static str> {
+    show_or_set(context, Setting::LedOffTime, args, out)
+}
+
+fn wait_time(context: &mut Context, args: &[&str], out: &mut fmt::Write) -> Result<(), &'static str> {
+    show_or_set(context, Setting::WaitTime, args, out)
+}
+
+// Show the setting with no arguments, or set it to the one argument.
+fn show_or_set(context: &mut Context, setting: Setting, args: &[&str], out: &mut fmt::Write)
+               -> Result<(), &'static str> {
+    match args.len() {
+        0 => {
+            let _ = write!(out, "{}\r\n", context.state_machine.config().get(setting));
+            Ok(())
+        },
+        1 => {
+            let value = shell::number(args[0])?;
+            if value > u32::max_value() as usize {
+                return Err("Out of range");
+            }
+            context.state_machine.set(setting, value as u32)
+        },
+        _ => Err("Too many arguments"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collections::String;
+    use config;
+
+    type Run = fn(&mut Context, &[&str], &mut fmt::Write) -> Result<(), &'static str>;
+
+    fn run(state_machine: &mut StateMachine, command: Run, args: &[&str]) -> Result<String, &'static str> {
+        let mut out = String::new();
+        command(&mut Context { state_machine: state_machine }, args, &mut out).map(|_| out)
+    }
+
+    #[test]
+    fn times_can_be_changed_for_the_next_flash() {
+        let mut state_machine = StateMachine::new(&config::DEFAULTS);
+        assert_eq!(Ok("4\r\n".to_string()), run(&mut state_machine, on_time, &[]));
+        assert!(run(&mut state_machine, on_time, &["7"]).is_ok());
+        assert!(run(&mut state_machine, flash_count, &["3"]).is_ok());
+        assert_eq!(Some(Event::FlashLed { count: 3, on_time: 7, off_time: 3 }),
+                   state_machine.execute(&Event::TimeTick));
+
+        assert!(run(&mut state_machine, wait_time, &["2"]).is_ok());
+        state_machine.execute(&Event::FlashLedDone);
+        state_machine.execute(&Event::TimeTick);
+        state_machine.execute(&Event::TimeTick);
+        assert!(state_machine.execute(&Event::TimeTick).is_some());
+    }
+
+    #[test]
+    fn bad_values_are_rejected() {
+        let mut state_machine = StateMachine::new(&config::DEFAULTS);
+        assert_eq!(Err("Expected a number"), run(&mut state_machine, off_time, &["-1"]));
+        assert_eq!(Err("Too many arguments"), run(&mut state_machine, off_time, &["1", "2"]));
+        assert_eq!(Err("Out of range"), run(&mut state_machine, on_time, &["4294967295"]));
+        assert_eq!(Err("Out of range"), run(&mut state_machine, on_time, &["0"]));
+        assert_eq!(Err("Out of range"), run(&mut state_machine, flash_count, &["0"]));
+        assert_eq!(Ok("3\r\n".to_string()), run(&mut state_machine, off_time, &[]));
+        assert_eq!(config::DEFAULTS, state_machine.config());
+    }
+
+    #[test]
+    fn settings_come_from_the_config_and_button_presses_change_it() {
+        let config = Config { flash_count: 2, led_on_time: 5, led_off_time: 6, wait_time: 7 };
+        let mut state_machine = StateMachine::new(&config);
+        assert_eq!(config, state_machine.config());
+        state_machine.execute(&Event::ButtonPress);
+        assert_eq!(Config { flash_count: 3, ..config }, state_machine.config());
+    }
+
+    #[test]
+    fn button_presses_stop_at_the_most_flashes() {
+        let config = Config { flash_count: config::MAX_FLASH_COUNT, ..config::DEFAULTS };
+        let mut state_machine = StateMachine::new(&config);
+        state_machine.execute(&Event::ButtonPress);
+        assert_eq!(config, state_machine.config());
+    }
+}
@@ -1,40 +1,72 @@
 /*
-    Configure the SysTick to generate a periodic interrupt. This is super hardware dependent
-    because it depends on the clock configuration (including crystal).
+    Configure the SysTick to generate a periodic interrupt. The board sets up the clock (including
+    its crystal), and the tick is counted from whatever frequency that gives.
+
+    The ticks are counted, so uptime_ms gives the time since the tick started (for log
+    timestamps).
 */
 
+use core::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 use super::event;
+use nvic;
+use tivaware::systick;
+
+// The number of ticks since init.
+static TICKS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+// The milliseconds between ticks, or 0 before init.
+static TICK_MS: AtomicUsize = ATOMIC_USIZE_INIT;
 
-const SYSCTL_SYSDIV_1: u32 = 0x07800000;
-const SYSCTL_USE_OSC: u32 = 0x00003800;
-const SYSCTL_OSC_MAIN: u32 = 0x00000000;
-const SYSCTL_XTAL_16MHZ: u32 = 0x00000540;
-
-extern {
-    fn SysCtlClockSet(config: u32);
-    fn SysCtlClockGet() -> u32;
-    fn SysTickPeriodSet(period: u32);
-    fn SysTickIntEnable();
-    fn SysTickEnable();
+// Start the tick, counting from the system clock (see board::init_clock).
+pub fn init (clock_hz: u32, frequency_hz: u32) {
+    TICK_MS.store((1000 / frequency_hz) as usize, Ordering::Relaxed);
+
+    // Set the SysTick to generate an interrupt at the configured rate.
+    systick::set_period(clock_hz/frequency_hz);
+
+    // The tick raises events, so it runs at the event priority.
+    nvic::set_exception_priority(nvic::Exception::SysTick, event::RAISE_PRIORITY);
+    
+    // Enable the system tick interrupt.
+    systick::enable_interrupt();
+    
+    // Enable the system tick.
+    systick::enable();
 }
 
-pub fn init (frequency_hz: u32) {
-    unsafe {
-        // Configure the clock.
-        SysCtlClockSet(SYSCTL_SYSDIV_1 | SYSCTL_USE_OSC | SYSCTL_OSC_MAIN | SYSCTL_XTAL_16MHZ);
-        
-        // Set the SysTick to generate an interrupt at the configured rate.
-        SysTickPeriodSet(SysCtlClockGet()/frequency_hz);
-
-        // Enable the system tick interrupt.
-        SysTickIntEnable();
-        
-        // Enable the system tick.
-        SysTickEnable();
+// The milliseconds since init. Wraps after about 49 days. With the tick masked (in a critical
+// section, or an interrupt at its priority or higher), a tick that's due but not yet handled isn't
+// counted.
+pub fn uptime_ms() -> u32 {
+    let tick_ms = TICK_MS.load(Ordering::Relaxed) as u32;
+    loop {
+        let ticks = TICKS.load(Ordering::Relaxed) as u32;
+        let within = within_tick_ms(tick_ms);
+        // If a tick went off in between, the counter has started on the next one.
+        if TICKS.load(Ordering::Relaxed) as u32 == ticks {
+            return ticks.wrapping_mul(tick_ms).wrapping_add(within);
+        }
     }
 }
 
-#[allow(dead_code)]
+// How far the counter is into the current tick.
+#[cfg(target_os = "none")]
+fn within_tick_ms(tick_ms: u32) -> u32 {
+    if tick_ms == 0 {
+        return 0;
+    }
+    let period = systick::period();
+    let elapsed = period - 1 - systick::value();
+    elapsed / (period / tick_ms)
+}
+
+// The host has no counter.
+#[cfg(not(target_os = "none"))]
+fn within_tick_ms(_tick_ms: u32) -> u32 {
+    0
+}
+
 pub fn handler () {
+    TICKS.fetch_add(1, Ordering::Relaxed);
     event::raise(event::Event::TimeTick);
-}
\ No newline at end of file
+}
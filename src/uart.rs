@@ -0,0 +1,344 @@
+/*
+    Serial I/O on UART0, which the LaunchPads connect to the debugger's virtual COM port.
+
+    Bytes are buffered both ways. The interrupt handler moves received bytes from the UART's FIFO
+    into the receive buffer and raises `SerialReceived`, and keeps the transmit FIFO topped up from
+    the transmit buffer until it's empty. Neither `read` nor `write` ever waits.
+
+    Receive errors (overrun, framing, break, parity, and bytes dropped because the receive buffer
+    was full) are collected until `take_errors` is called, and raise `SerialError`.
+*/
+
+use core::cell::RefCell;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use critical_section_arm::{self, Mutex};
+use board;
+use device::interrupt;
+use event::{self, Event};
+#[cfg(feature = "binary_log")]
+use log::{Encode, Record};
+use nvic;
+use ring_buffer::RingBuffer;
+use tivaware::{gpio, sysctl, uart};
+use tivaware::uart::{Config, FifoLevel, Uart};
+
+const UART: Uart = Uart::Uart0;
+
+// The UART interrupt raises events, so it runs at the event priority. The buffers are shared with
+// it, so they're protected by masking interrupts up to that priority.
+const CEILING: u8 = event::RAISE_CEILING;
+
+static RX_BUFFER: Mutex<RefCell<RingBuffer>> = Mutex::lazy_with_ceiling(CEILING, new_buffer);
+static TX_BUFFER: Mutex<RefCell<RingBuffer>> = Mutex::lazy_with_ceiling(CEILING, new_buffer);
+
+fn new_buffer() -> RefCell<RingBuffer> {
+    RefCell::new(RingBuffer::new())
+}
+
+// The errors seen since they were last taken, as UART_INT_ error flags plus BUFFER_FULL.
+static ERRORS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+// A received byte was dropped because the receive buffer was full. Not a hardware flag.
+const BUFFER_FULL: u32 = 1 << 31;
+
+// Receive errors.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Errors {
+    // The receive FIFO was full when another byte arrived.
+    pub overrun: bool,
+    // A byte didn't have a valid stop bit.
+    pub framing: bool,
+    // The line was held low for longer than a byte.
+    pub line_break: bool,
+    pub parity: bool,
+    // The receive buffer was full when another byte arrived, because it isn't read fast enough.
+    pub buffer_full: bool,
+}
+
+impl Errors {
+    fn from_flags(flags: u32) -> Errors {
+        Errors {
+            overrun: flags & uart::INT_OVERRUN.value() != 0,
+            framing: flags & uart::INT_FRAMING.value() != 0,
+            line_break: flags & uart::INT_BREAK.value() != 0,
+            parity: flags & uart::INT_PARITY.value() != 0,
+            buffer_full: flags & BUFFER_FULL != 0,
+        }
+    }
+}
+
+// Binary logs send the Debug text.
+#[cfg(feature = "binary_log")]
+impl Encode for Errors {
+    fn encode(&self, record: &mut Record) {
+        record.debug(self);
+    }
+}
+
+// Set up UART0 at `baud`, 8N1. The baud rate is worked out from the system clock frequency.
+pub fn init(clock_hz: u32, baud: u32) {
+    sysctl::enable(UART.peripheral());
+    sysctl::enable(board::UART_PORT.peripheral());
+    gpio::configure_pin(board::UART_RX_FUNCTION);
+    gpio::configure_pin(board::UART_TX_FUNCTION);
+    gpio::set_uart(board::UART_PORT, board::UART_RX_PIN | board::UART_TX_PIN);
+
+    uart::set_config(UART, clock_hz, baud, Config::default());
+
+    // Interrupt when the receive FIFO is half full, or when bytes have been sitting in it for a
+    // while, so single bytes aren't held up. Refill the transmit FIFO when it's nearly empty.
+    uart::set_fifo_levels(UART, FifoLevel::OneEighth, FifoLevel::OneHalf);
+    uart::enable_interrupts(UART, uart::INT_RX | uart::INT_RX_TIMEOUT | uart::INT_OVERRUN |
+                                  uart::INT_FRAMING | uart::INT_BREAK | uart::INT_PARITY);
+    uart::enable(UART);
+
+    nvic::set_priority(interrupt::UART0, event::RAISE_PRIORITY);
+    nvic::enable(interrupt::UART0);
+}
+
+// Queue bytes to send. Returns how many were queued, which is less than all of them if the
+// transmit buffer fills up.
+pub fn write(bytes: &[u8]) -> usize {
+    critical_section_arm::with_ceiling(CEILING, |cs| {
+        let mut tx = TX_BUFFER.borrow(cs).borrow_mut();
+        let queued = tx.extend(bytes);
+        send(&UART, &mut tx);
+        queued
+    })
+}
+
+// Queue all the bytes, waiting for room in the transmit buffer when it fills up. Only call this
+// from the main loop, since it relies on the UART interrupt to make room.
+pub fn write_all(bytes: &[u8]) {
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let queued = write(rest);
+        rest = &rest[queued..];
+    }
+}
+
+// Formatted output to the serial port, with write_all.
+pub struct Writer;
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        write_all(text.as_bytes());
+        Ok(())
+    }
+}
+
+// Take received bytes. Returns how many were put in `buffer`.
+pub fn read(buffer: &mut [u8]) -> usize {
+    critical_section_arm::with_ceiling(CEILING, |cs| {
+        let mut rx = RX_BUFFER.borrow(cs).borrow_mut();
+        let mut count = 0;
+        while count < buffer.len() {
+            match rx.pop() {
+                Some(byte) => buffer[count] = byte,
+                None => break,
+            }
+            count += 1;
+        }
+        count
+    })
+}
+
+// The errors since the last call.
+pub fn take_errors() -> Errors {
+    Errors::from_flags(ERRORS.swap(0, Ordering::Relaxed) as u32)
+}
+
+// The UART's FIFOs and transmit interrupt, as far as the buffering goes. The tests stand in a fake
+// for the hardware.
+trait Fifo {
+    fn is_space_available(&self) -> bool;
+    fn write_byte(&self, byte: u8);
+    fn read_byte(&self) -> Option<u8>;
+    fn set_transmit_interrupt(&self, enabled: bool);
+}
+
+impl Fifo for Uart {
+    fn is_space_available(&self) -> bool {
+        uart::is_space_available(*self)
+    }
+
+    fn write_byte(&self, byte: u8) {
+        uart::write_byte(*self, byte);
+    }
+
+    fn read_byte(&self) -> Option<u8> {
+        uart::read_byte(*self)
+    }
+
+    fn set_transmit_interrupt(&self, enabled: bool) {
+        if enabled {
+            uart::enable_interrupts(*self, uart::INT_TX);
+        } else {
+            uart::disable_interrupts(*self, uart::INT_TX);
+        }
+    }
+}
+
+// Move what fits from the transmit buffer into the FIFO. The transmit interrupt is only left on
+// while there's more to send.
+fn send<F: Fifo>(fifo: &F, tx: &mut RingBuffer) {
+    while !tx.is_empty() && fifo.is_space_available() {
+        if let Some(byte) = tx.pop() {
+            fifo.write_byte(byte);
+        }
+    }
+    fifo.set_transmit_interrupt(!tx.is_empty());
+}
+
+// Move everything in the receive FIFO into the receive buffer. Returns whether anything arrived,
+// and BUFFER_FULL if some of it was dropped for want of room.
+fn receive<F: Fifo>(fifo: &F, rx: &mut RingBuffer) -> (bool, u32) {
+    let mut received = false;
+    let mut errors = 0;
+    while let Some(byte) = fifo.read_byte() {
+        received = true;
+        if !rx.push(byte) {
+            errors |= BUFFER_FULL;
+        }
+    }
+    (received, errors)
+}
+
+pub fn handler() {
+    let status = uart::interrupt_status(UART, true);
+    uart::clear_interrupts(UART, status);
+
+    let error_interrupts = uart::INT_OVERRUN | uart::INT_FRAMING | uart::INT_BREAK | uart::INT_PARITY;
+    let (received, dropped) = critical_section_arm::with_ceiling(CEILING, |cs| {
+        let received = receive(&UART, &mut RX_BUFFER.borrow(cs).borrow_mut());
+
+        if status.contains(uart::INT_TX) {
+            send(&UART, &mut TX_BUFFER.borrow(cs).borrow_mut());
+        }
+        received
+    });
+    let errors = (status.value() & error_interrupts.value()) | dropped;
+
+    if errors != 0 {
+        ERRORS.fetch_or(errors as usize, Ordering::Relaxed);
+        event::raise(Event::SerialError);
+    }
+    if received {
+        event::raise(Event::SerialReceived);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::{Cell, RefCell};
+    use collections::Vec;
+    use tivaware::uart;
+
+    // A UART with `space` bytes free in its transmit FIFO and `incoming` in its receive FIFO.
+    struct FakeFifo {
+        space: Cell<usize>,
+        incoming: RefCell<Vec<u8>>,
+        sent: RefCell<Vec<u8>>,
+        transmit_interrupt: Cell<bool>,
+    }
+
+    impl FakeFifo {
+        fn new(space: usize, incoming: &[u8]) -> FakeFifo {
+            FakeFifo {
+                space: Cell::new(space),
+                incoming: RefCell::new(incoming.iter().rev().cloned().collect()),
+                sent: RefCell::new(Vec::new()),
+                transmit_interrupt: Cell::new(false),
+            }
+        }
+    }
+
+    impl Fifo for FakeFifo {
+        fn is_space_available(&self) -> bool {
+            self.space.get() > 0
+        }
+
+        fn write_byte(&self, byte: u8) {
+            self.space.set(self.space.get() - 1);
+            self.sent.borrow_mut().push(byte);
+        }
+
+        fn read_byte(&self) -> Option<u8> {
+            self.incoming.borrow_mut().pop()
+        }
+
+        fn set_transmit_interrupt(&self, enabled: bool) {
+            self.transmit_interrupt.set(enabled);
+        }
+    }
+
+    fn buffer_of(bytes: &[u8]) -> RingBuffer {
+        let mut buffer = RingBuffer::new();
+        buffer.extend(bytes);
+        buffer
+    }
+
+    #[test]
+    fn sending_stops_when_the_fifo_is_full_and_waits_for_the_interrupt() {
+        let fifo = FakeFifo::new(2, &[]);
+        let mut tx = buffer_of(&[1, 2, 3]);
+        send(&fifo, &mut tx);
+        assert_eq!(vec![1, 2], *fifo.sent.borrow());
+        assert!(fifo.transmit_interrupt.get());
+
+        fifo.space.set(16);
+        send(&fifo, &mut tx);
+        assert_eq!(vec![1, 2, 3], *fifo.sent.borrow());
+        assert!(tx.is_empty());
+        assert!(!fifo.transmit_interrupt.get());
+    }
+
+    #[test]
+    fn everything_received_is_buffered() {
+        let fifo = FakeFifo::new(0, &[1, 2, 3]);
+        let mut rx = RingBuffer::new();
+        assert_eq!((true, 0), receive(&fifo, &mut rx));
+        assert_eq!(Some(1), rx.pop());
+        assert_eq!(Some(2), rx.pop());
+        assert_eq!(Some(3), rx.pop());
+
+        assert_eq!((false, 0), receive(&fifo, &mut rx));
+    }
+
+    #[test]
+    fn bytes_received_into_a_full_buffer_are_dropped_and_reported() {
+        let mut rx = RingBuffer::new();
+        while rx.push(0) {}
+        let fifo = FakeFifo::new(0, &[1, 2]);
+        let (received, errors) = receive(&fifo, &mut rx);
+        assert!(received);
+        assert!(Errors::from_flags(errors).buffer_full);
+        assert!(fifo.incoming.borrow().is_empty());
+    }
+
+    #[test]
+    fn reads_take_what_has_been_received() {
+        critical_section_arm::with_ceiling(CEILING, |cs| RX_BUFFER.borrow(cs).borrow_mut().extend(b"abc"));
+        let mut buffer = [0; 2];
+        assert_eq!(2, read(&mut buffer));
+        assert_eq!(b"ab", &buffer);
+        assert_eq!(1, read(&mut buffer));
+        assert_eq!(b'c', buffer[0]);
+        assert_eq!(0, read(&mut buffer));
+    }
+
+    #[test]
+    fn errors_are_read_from_the_interrupt_flags() {
+        let errors = Errors::from_flags(uart::INT_OVERRUN.value() | uart::INT_BREAK.value());
+        assert!(errors.overrun && errors.line_break);
+        assert!(!errors.framing && !errors.parity && !errors.buffer_full);
+    }
+
+    #[test]
+    fn a_full_receive_buffer_is_an_error() {
+        assert_eq!(Errors { buffer_full: true, ..Errors::default() }, Errors::from_flags(BUFFER_FULL));
+        assert_eq!(Errors::default(), Errors::from_flags(0));
+    }
+}
@@ -1,61 +1,43 @@
-// We need to put the reset and exception vectors at the right spot in memory, but they're never
-// called directly, so the compiler thinks this is dead code.
-#[allow(dead_code)]
+/*
+    The reset and exception vectors. The interrupt vectors follow these and are generated from the
+    device's interrupt list (see the device module). Handlers are bound to interrupts by name below.
+*/
 
+use device::Handler;
+use exception;
+
+// The vectors are only ever read by the processor, so they're exported to keep the compiler from
+// dropping them. The linker script keeps their sections when unused sections are collected.
 #[link_section = ".reset"]
-static RESET: fn() -> ! = ::start;
+#[no_mangle]
+pub static RESET_VECTOR: fn() -> ! = ::startup::reset;
 
+// Exceptions 2 through 15, from NMI to SysTick.
 #[link_section = ".exceptions"]
-static EXCEPTIONS: [Option<fn() -> !>; 12] = [
-    Some(::exception::nmi),  // NMI
-    Some(::exception::hard_fault),  // Hard fault
-    Some(::exception::memory_fault),  // Memory management fault
-    Some(::exception::bus_fault),  // Bus fault
-    Some(::exception::usage_fault),  // Usage fault
-    None, // Reserved
-    None, // Reserved
-    None, // Reserved
-    None, // Reserved
-    Some(::exception::default_handler),  // SVCall
-    None, // Reserved for Debug
-    None, // Reserved
+#[no_mangle]
+pub static EXCEPTIONS: [Option<Handler>; 14] = [
+    Some(exception::nmi as Handler),              // NMI
+    Some(exception::hard_fault as Handler),       // Hard fault
+    Some(exception::memory_fault as Handler),     // Memory management fault
+    Some(exception::bus_fault as Handler),        // Bus fault
+    Some(exception::usage_fault as Handler),      // Usage fault
+    None,                                         // Reserved
+    None,                                         // Reserved
+    None,                                         // Reserved
+    None,                                         // Reserved
+    Some(exception::default_handler as Handler),  // SVCall
+    Some(exception::default_handler as Handler),  // Debug monitor
+    None,                                         // Reserved
+    Some(exception::default_handler as Handler),  // PendSV
+    Some(systick as Handler),                     // SysTick
 ];
 
-#[link_section = ".interrupts"]
-static INTERRUPT_HANDLERS: [Option<fn()>; 35] = [
-    None, // PendSV
-    Some(::systick::handler), // Systick
-    Some(::button::handler), // GPIO A
-    Some(::button::handler), // GPIO B
-    Some(::button::handler), // GPIO C
-    Some(::button::handler), // GPIO D
-    Some(::button::handler), // GPIO E
-    None, // UART0 Rx and Tx
-    None, // UART1 Rx and Tx
-    None, // SSI0 Rx and Tx
-    None, // I2C0 Master and Slave
-    None, // PWM Fault
-    None, // PWM Generator 0
-    None, // PWM Generator 1
-    None, // PWM Generator 2
-    None, // Quadrature Encoder 0
-    None, // ADC Sequence 0
-    None, // ADC Sequence 1
-    None, // ADC Sequence 2
-    None, // ADC Sequence 3
-    None, // Watchdog timer
-    None, // Timer 0 subtimer A
-    None, // Timer 0 subtimer B
-    None, // Timer 1 subtimer A
-    None, // Timer 1 subtimer B
-    None, // Timer 2 subtimer A
-    None, // Timer 2 subtimer B
-    None, // Analog Comparator 0
-    None, // Analog Comparator 1
-    None, // Analog Comparator 2
-    None, // System Control (PLL, OSC, BO)
-    None, // FLASH Control
-    Some(::button::handler), // GPIO Port F
-    Some(::button::handler), // GPIO Port G
-    Some(::button::handler), // GPIO Port H
-];
\ No newline at end of file
+extern "C" fn systick() {
+    ::systick::handler();
+}
+
+// Interrupt handlers. Anything not bound here goes to exception::default_handler.
+
+interrupt!(UART0, ::uart::handler);
+
+// The buttons' interrupt depends on the board, so each board binds it (see button_interrupt!).
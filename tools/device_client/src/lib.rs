@@ -0,0 +1,330 @@
+/*
+    The host's end of the device protocol (see lib/protocol), for test rigs which drive the
+    firmware over its serial port.
+
+    A Client wraps anything it can read and write, normally the serial port opened with a read
+    timeout. Each request waits for the response with the same id. Anything else the device sends,
+    the shell and log text, is kept for `take_text`.
+
+        let mut client = Client::new(port);
+        client.set_parameter(Parameter::FlashCount, 3)?;
+        client.raise_event(Event::ButtonPress)?;
+*/
+
+extern crate protocol;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use protocol::frame::{self, Deframer, Status, MAX_ENCODED};
+use protocol::message;
+use protocol::{ErrorCode, Event, Parameter, Request, Response, VERSION};
+
+#[derive(Debug)]
+pub enum Error {
+    // Reading or writing the port failed, or timed out waiting for a response.
+    Io(io::Error),
+    // The request couldn't be encoded, so it wasn't sent.
+    BadRequest(message::Error),
+    // The device refused the request.
+    Device(ErrorCode),
+    // The device answered with a protocol version this client doesn't speak.
+    Version(u8),
+    // The response couldn't be read, or wasn't one the request can have.
+    BadResponse,
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref error) => write!(f, "{}", error),
+            Error::BadRequest(ref error) => write!(f, "the request couldn't be encoded: {:?}", error),
+            Error::Device(code) => write!(f, "the device refused the request: {:?}", code),
+            Error::Version(version) => write!(f, "the device speaks protocol version {}", version),
+            Error::BadResponse => write!(f, "the device's response didn't make sense"),
+        }
+    }
+}
+
+pub struct Client<T> {
+    port: T,
+    deframer: Deframer,
+    // The id of the next request.
+    id: u8,
+    // Received bytes which haven't been looked at yet.
+    received: Vec<u8>,
+    // The bytes received outside frames.
+    text: Vec<u8>,
+}
+
+impl<T: Read + Write> Client<T> {
+    pub fn new(port: T) -> Client<T> {
+        Client { port, deframer: Deframer::new(), id: 0, received: Vec::new(), text: Vec::new() }
+    }
+
+    // Send a request and wait for its response. Device errors are returned as responses.
+    pub fn request(&mut self, request: Request) -> Result<Response, Error> {
+        self.id = self.id.wrapping_add(1);
+        let mut out = [0; MAX_ENCODED];
+        let length = frame::encode(VERSION, self.id, &request, &mut out).map_err(Error::BadRequest)?;
+        self.port.write_all(&out[..length])?;
+        self.port.flush()?;
+
+        loop {
+            if self.received.is_empty() {
+                let mut buffer = [0; 64];
+                let count = self.port.read(&mut buffer)?;
+                if count == 0 {
+                    return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "the port closed")));
+                }
+                self.received.extend_from_slice(&buffer[..count]);
+            }
+
+            let mut response = None;
+            let mut used = 0;
+            for &byte in &self.received {
+                used += 1;
+                match self.deframer.push(byte) {
+                    Status::Unframed => self.text.push(byte),
+                    // Frames with other ids are late responses to earlier requests.
+                    Status::Frame(frame) if frame.id == self.id => {
+                        response = Some(if frame.version != VERSION {
+                            Err(Error::Version(frame.version))
+                        } else {
+                            message::read(frame.message).map_err(|_| Error::BadResponse)
+                        });
+                        break;
+                    },
+                    _ => {},
+                }
+            }
+            self.received.drain(..used);
+            if let Some(response) = response {
+                return response;
+            }
+        }
+    }
+
+    pub fn ping(&mut self) -> Result<(), Error> {
+        match self.request(Request::Ping)? {
+            Response::Pong => Ok(()),
+            other => unexpected(other),
+        }
+    }
+
+    pub fn raise_event(&mut self, event: Event) -> Result<(), Error> {
+        match self.request(Request::RaiseEvent(event))? {
+            Response::Done => Ok(()),
+            other => unexpected(other),
+        }
+    }
+
+    pub fn parameter(&mut self, parameter: Parameter) -> Result<u32, Error> {
+        match self.request(Request::GetParameter(parameter))? {
+            Response::Value(value) => Ok(value),
+            other => unexpected(other),
+        }
+    }
+
+    pub fn set_parameter(&mut self, parameter: Parameter, value: u32) -> Result<(), Error> {
+        match self.request(Request::SetParameter(parameter, value))? {
+            Response::Done => Ok(()),
+            other => unexpected(other),
+        }
+    }
+
+    // The shell and log text received since the last call.
+    pub fn take_text(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.text)
+    }
+
+    // Give back the port.
+    pub fn into_inner(self) -> T {
+        self.port
+    }
+}
+
+fn unexpected<V>(response: Response) -> Result<V, Error> {
+    match response {
+        Response::Error(code) => Err(Error::Device(code)),
+        _ => Err(Error::BadResponse),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::{Handler, Server};
+    use std::cmp;
+    use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+    use std::thread;
+    use std::time::Duration;
+
+    // One end of an in-memory serial line.
+    struct Pipe {
+        tx: Sender<Vec<u8>>,
+        rx: Receiver<Vec<u8>>,
+        pending: Vec<u8>,
+    }
+
+    fn pipe() -> (Pipe, Pipe) {
+        let (a_tx, a_rx) = channel();
+        let (b_tx, b_rx) = channel();
+        (Pipe { tx: a_tx, rx: b_rx, pending: Vec::new() }, Pipe { tx: b_tx, rx: a_rx, pending: Vec::new() })
+    }
+
+    impl Read for Pipe {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            if self.pending.is_empty() {
+                match self.rx.recv_timeout(Duration::from_millis(200)) {
+                    Ok(bytes) => self.pending = bytes,
+                    Err(RecvTimeoutError::Timeout) => return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out")),
+                    Err(RecvTimeoutError::Disconnected) => return Ok(0),
+                }
+            }
+            let count = cmp::min(buffer.len(), self.pending.len());
+            buffer[..count].copy_from_slice(&self.pending[..count]);
+            self.pending.drain(..count);
+            Ok(count)
+        }
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.tx.send(bytes.to_vec()).map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "closed"))?;
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Stands in for the firmware: keeps the parameters and the events raised.
+    struct Device {
+        parameters: [u32; 4],
+        events: Sender<Event>,
+    }
+
+    impl Handler for Device {
+        fn handle(&mut self, request: Request) -> Response {
+            match request {
+                Request::Ping => Response::Pong,
+                Request::RaiseEvent(event) => {
+                    self.events.send(event).unwrap();
+                    Response::Done
+                },
+                Request::GetParameter(parameter) => Response::Value(self.parameters[parameter as usize]),
+                Request::SetParameter(_, 0) => Response::Error(ErrorCode::BadValue),
+                Request::SetParameter(parameter, value) => {
+                    self.parameters[parameter as usize] = value;
+                    Response::Done
+                },
+            }
+        }
+    }
+
+    // A client talking to a device on another thread, which logs a line before each response and
+    // echoes the bytes outside frames like the shell. Also returns the events the device gets.
+    fn connect() -> (Client<Pipe>, Receiver<Event>) {
+        let (host, mut port) = pipe();
+        let (events, events_rx) = channel();
+        thread::spawn(move || {
+            let mut server = Server::new();
+            let mut device = Device { parameters: [1, 4, 3, 20], events };
+            let mut buffer = [0; 16];
+            loop {
+                let count = match port.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(count) => count,
+                    // Nothing from the host yet.
+                    Err(_) => continue,
+                };
+                let mut sent = Vec::new();
+                let mut echo = Vec::new();
+                for &byte in &buffer[..count] {
+                    if !server.receive(byte, &mut device, &mut |frame| sent.extend_from_slice(frame)) {
+                        echo.push(byte);
+                    }
+                }
+                let mut out = echo;
+                if !sent.is_empty() {
+                    out.extend_from_slice(b"[    1.000] INFO  main: handled\r\n");
+                    out.extend(sent);
+                }
+                if !out.is_empty() && port.write_all(&out).is_err() {
+                    break;
+                }
+            }
+        });
+        (Client::new(host), events_rx)
+    }
+
+    #[test]
+    fn requests_are_answered_over_the_pipe() {
+        let (mut client, events) = connect();
+        client.ping().unwrap();
+        assert_eq!(4, client.parameter(Parameter::LedOnTime).unwrap());
+        client.set_parameter(Parameter::LedOnTime, 9).unwrap();
+        assert_eq!(9, client.parameter(Parameter::LedOnTime).unwrap());
+
+        let event = Event::FlashLed { count: 2, on_time: 5, off_time: 6 };
+        client.raise_event(event).unwrap();
+        assert_eq!(event, events.recv().unwrap());
+    }
+
+    #[test]
+    fn device_errors_are_returned() {
+        let (mut client, _events) = connect();
+        match client.set_parameter(Parameter::WaitTime, 0) {
+            Err(Error::Device(ErrorCode::BadValue)) => {},
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(20, client.parameter(Parameter::WaitTime).unwrap());
+    }
+
+    #[test]
+    fn text_from_the_device_is_kept() {
+        let (mut client, _events) = connect();
+        client.ping().unwrap();
+        client.ping().unwrap();
+        assert_eq!(b"[    1.000] INFO  main: handled\r\n[    1.000] INFO  main: handled\r\n".to_vec(),
+                   client.take_text());
+        assert!(client.take_text().is_empty());
+    }
+
+    #[test]
+    fn late_and_corrupted_frames_are_skipped() {
+        let (host, mut device) = pipe();
+        let mut client = Client::new(host);
+
+        let mut out = [0; MAX_ENCODED];
+        // A late response to an earlier request, a corrupted response, then the right one.
+        let mut bytes = Vec::new();
+        let length = frame::encode(VERSION, 0, &Response::Value(1), &mut out).unwrap();
+        bytes.extend_from_slice(&out[..length]);
+        let length = frame::encode(VERSION, 1, &Response::Value(2), &mut out).unwrap();
+        out[4] ^= 0x01;
+        bytes.extend_from_slice(&out[..length]);
+        let length = frame::encode(VERSION, 1, &Response::Value(3), &mut out).unwrap();
+        bytes.extend_from_slice(&out[..length]);
+        device.write_all(&bytes).unwrap();
+
+        assert_eq!(3, client.parameter(Parameter::FlashCount).unwrap());
+    }
+
+    #[test]
+    fn requests_time_out_without_a_device() {
+        let (host, _device) = pipe();
+        let mut client = Client::new(host);
+        match client.ping() {
+            Err(Error::Io(ref error)) if error.kind() == io::ErrorKind::TimedOut => {},
+            other => panic!("{:?}", other),
+        }
+    }
+}
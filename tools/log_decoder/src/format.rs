@@ -0,0 +1,222 @@
+/*
+    Formats arguments the way `format!` would, from a format string only known at run time. Covers
+    what log messages use: `{}` and `{:?}`, hex, binary and octal, `#`, zero padding, width and
+    alignment. Anything else in a placeholder (positions, names, precision) is ignored.
+*/
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+// An argument from a binary log record.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Unsigned(u32),
+    Signed(i32),
+    Bool(bool),
+    Char(char),
+    Str(String),
+    // Text the target already formatted, which is shown as it is.
+    Text(String),
+}
+
+// The format string with each placeholder replaced by the next value.
+pub fn format(format: &str, values: &[Value]) -> String {
+    let mut out = String::new();
+    let mut values = values.iter();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let spec = Spec::parse(&placeholder(&mut chars));
+                match values.next() {
+                    Some(value) => out.push_str(&argument(value, &spec)),
+                    None => out.push_str("<missing>"),
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// The text of a placeholder, up to its closing brace.
+fn placeholder(chars: &mut Peekable<Chars>) -> String {
+    let mut text = String::new();
+    for c in chars.by_ref() {
+        if c == '}' {
+            break;
+        }
+        text.push(c);
+    }
+    text
+}
+
+#[derive(Default, PartialEq, Debug)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<char>,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    // "", "?", "x", "X", "b" or "o".
+    kind: String,
+}
+
+impl Spec {
+    // `[position][:[[fill]align][#][0][width][.precision][kind]]`
+    fn parse(placeholder: &str) -> Spec {
+        let mut spec = Spec::default();
+        let text = match placeholder.find(':') {
+            Some(at) => &placeholder[at + 1..],
+            None => return spec,
+        };
+        let mut chars: Vec<char> = text.chars().collect();
+        if chars.len() >= 2 && is_align(chars[1]) {
+            spec.fill = Some(chars[0]);
+            spec.align = Some(chars[1]);
+            chars.drain(..2);
+        } else if !chars.is_empty() && is_align(chars[0]) {
+            spec.align = Some(chars[0]);
+            chars.remove(0);
+        }
+        let mut chars = chars.into_iter().peekable();
+        if chars.peek() == Some(&'#') {
+            spec.alternate = true;
+            chars.next();
+        }
+        if chars.peek() == Some(&'0') {
+            spec.zero = true;
+            chars.next();
+        }
+        while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            spec.width = spec.width * 10 + digit as usize;
+            chars.next();
+        }
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                chars.next();
+            }
+        }
+        spec.kind = chars.collect();
+        spec
+    }
+}
+
+fn is_align(c: char) -> bool {
+    c == '<' || c == '^' || c == '>'
+}
+
+fn argument(value: &Value, spec: &Spec) -> String {
+    match *value {
+        Value::Unsigned(n) => number(integer(n as u64, n, spec), spec),
+        Value::Signed(n) if n < 0 && !is_radix(spec) => {
+            number(("-".to_string(), integer((n as i64).unsigned_abs(), n as u32, spec).1), spec)
+        }
+        Value::Signed(n) => number(integer(n as u64, n as u32, spec), spec),
+        Value::Bool(b) => pad(b.to_string(), spec, '<'),
+        Value::Char(c) if spec.kind == "?" => pad(format!("{:?}", c), spec, '<'),
+        Value::Char(c) => pad(c.to_string(), spec, '<'),
+        Value::Str(ref s) if spec.kind == "?" => pad(format!("{:?}", s), spec, '<'),
+        Value::Str(ref s) | Value::Text(ref s) => pad(s.clone(), spec, '<'),
+    }
+}
+
+fn is_radix(spec: &Spec) -> bool {
+    spec.kind == "x" || spec.kind == "X" || spec.kind == "b" || spec.kind == "o"
+}
+
+// The prefix and digits of an integer. Hex, binary and octal show the bits, like Rust does for
+// negative numbers.
+fn integer(magnitude: u64, bits: u32, spec: &Spec) -> (String, String) {
+    let prefix = |p: &str| if spec.alternate { p.to_string() } else { String::new() };
+    match spec.kind.as_str() {
+        "x" => (prefix("0x"), format!("{:x}", bits)),
+        "X" => (prefix("0x"), format!("{:X}", bits)),
+        "b" => (prefix("0b"), format!("{:b}", bits)),
+        "o" => (prefix("0o"), format!("{:o}", bits)),
+        _ => (String::new(), magnitude.to_string()),
+    }
+}
+
+// Numbers are zero padded after the sign and prefix, or aligned right by default.
+fn number((prefix, digits): (String, String), spec: &Spec) -> String {
+    if spec.zero {
+        let zeros = spec.width.saturating_sub(prefix.len() + digits.len());
+        format!("{}{}{}", prefix, repeat('0', zeros), digits)
+    } else {
+        pad(prefix + &digits, spec, '>')
+    }
+}
+
+fn pad(text: String, spec: &Spec, default_align: char) -> String {
+    let length = text.chars().count();
+    if length >= spec.width {
+        return text;
+    }
+    let fill = spec.fill.unwrap_or(' ');
+    let padding = spec.width - length;
+    let (before, after) = match spec.align.unwrap_or(default_align) {
+        '<' => (0, padding),
+        '^' => (padding / 2, padding - padding / 2),
+        _ => (padding, 0),
+    };
+    format!("{}{}{}", repeat(fill, before), text, repeat(fill, after))
+}
+
+fn repeat(c: char, count: usize) -> String {
+    std::iter::repeat_n(c, count).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one(format_string: &str, value: Value) -> String {
+        format(format_string, &[value])
+    }
+
+    #[test]
+    fn placeholders_are_replaced_in_order() {
+        let values = [Value::Unsigned(3), Value::Str("on".to_string()), Value::Bool(true)];
+        assert_eq!("3 LEDs on: true", format("{} LEDs {}: {}", &values));
+    }
+
+    #[test]
+    fn integers_have_radixes_and_padding() {
+        assert_eq!("0x002a", one("{:#06x}", Value::Unsigned(42)));
+        assert_eq!("2A", one("{:X}", Value::Unsigned(42)));
+        assert_eq!("0b101", one("{:#b}", Value::Unsigned(5)));
+        assert_eq!("   42", one("{:5}", Value::Unsigned(42)));
+        assert_eq!("42   ", one("{:<5}", Value::Unsigned(42)));
+        assert_eq!("-0042", one("{:05}", Value::Signed(-42)));
+        assert_eq!("fffffffe", one("{:x}", Value::Signed(-2)));
+    }
+
+    #[test]
+    fn strings_are_aligned_left_and_quoted_for_debug() {
+        assert_eq!("ab   |", one("{:5}|", Value::Str("ab".to_string())));
+        assert_eq!("*ab**", one("{:*^5}", Value::Str("ab".to_string())));
+        assert_eq!("\"a\\n\"", one("{:?}", Value::Str("a\n".to_string())));
+        assert_eq!("'c'", one("{:?}", Value::Char('c')));
+    }
+
+    #[test]
+    fn preformatted_text_is_left_as_it_is() {
+        assert_eq!("Errors { overrun: true }",
+                   one("{:?}", Value::Text("Errors { overrun: true }".to_string())));
+    }
+
+    #[test]
+    fn escaped_braces_and_missing_values() {
+        assert_eq!("{} 1 <missing>", format("{{}} {} {}", &[Value::Unsigned(1)]));
+    }
+}
@@ -0,0 +1,393 @@
+/*
+    Turns the firmware's binary log records (built with the binary_log feature, see
+    src/log/binary.rs) back into text. The format strings aren't on the target: each message has
+    a static in the ELF's .log_strings section, holding the address and length of its module path,
+    which is in flash, and then its format string, ending with a zero. The static's address
+    divided by four, less one, is the index the records use.
+
+    Records come in frames like the host protocol's (see lib/protocol/src/frame.rs), mixed in with
+    the shell's text and protocol frames, which are skipped.
+*/
+
+extern crate protocol;
+extern crate size_report;
+
+use std::collections::BTreeMap;
+use protocol::{cobs, crc};
+use protocol::frame::LOG_RECORD;
+use size_report::elf::Elf;
+use format::Value;
+
+pub mod format;
+
+const SECTION: &str = ".log_strings";
+
+// The alignment of each message's static, and where the section starts.
+const STATIC_ALIGN: u64 = 4;
+
+// The size of the module path's address and length, before the format string.
+const MODULE_SIZE: usize = 8;
+
+// A log message's module, named like the firmware's text logs do, and its format string.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Message {
+    pub module: String,
+    pub format: String,
+}
+
+// The messages by index.
+pub type Strings = BTreeMap<u16, Message>;
+
+// The messages in a firmware ELF, whose file is `data`.
+pub fn strings(elf: &Elf, data: &[u8]) -> Result<Strings, String> {
+    let index = match elf.sections.iter().position(|s| s.name == SECTION) {
+        Some(index) => index,
+        None => return Err(format!("no {} section; was the firmware built with binary_log?", SECTION)),
+    };
+    let section = &elf.sections[index];
+    let statics = section.contents(data).ok_or_else(|| format!("the {} section is empty", SECTION))?;
+
+    let mut strings = Strings::new();
+    for symbol in elf.symbols.iter().filter(|s| s.section == Some(index) && s.sized_object && s.address >= STATIC_ALIGN) {
+        let fields = symbol.address.checked_sub(section.address)
+            .and_then(|at| statics.get(at as usize..(at + symbol.size) as usize))
+            .filter(|fields| fields.len() > MODULE_SIZE)
+            .ok_or_else(|| format!("the message at {:#x} isn't in the {} section", symbol.address, SECTION))?;
+        let module_path = text(elf, data, u32_at(fields), u32_at(&fields[4..]))
+            .ok_or_else(|| format!("the module of the message at {:#x} isn't in the ELF", symbol.address))?;
+        let format = &fields[MODULE_SIZE..];
+        let format = format.iter().position(|&b| b == 0)
+            .and_then(|end| String::from_utf8(format[..end].to_vec()).ok())
+            .ok_or_else(|| format!("the format string of the message at {:#x} is bad", symbol.address))?;
+        let message = Message { module: module_name(&module_path).to_string(), format };
+        strings.insert((symbol.address / STATIC_ALIGN - 1) as u16, message);
+    }
+    Ok(strings)
+}
+
+// The text at `address` in a section which is loaded onto the target.
+fn text(elf: &Elf, data: &[u8], address: u32, length: u32) -> Option<String> {
+    let (address, length) = (address as u64, length as u64);
+    let section = elf.sections.iter()
+        .find(|s| s.loaded && address >= s.address && address + length <= s.address + s.size)?;
+    let start = (address - section.address) as usize;
+    let bytes = section.contents(data)?.get(start..start + length as usize)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+// The module path without the crate, or "main" for the crate itself, as in src/log/mod.rs.
+fn module_name(module_path: &str) -> &str {
+    match module_path.find("::") {
+        Some(at) => &module_path[at + 2..],
+        None => "main",
+    }
+}
+
+// Decodes records as they arrive, keeping any partial record until the rest of it comes.
+pub struct Decoder {
+    strings: Strings,
+    // The bytes since the last zero.
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new(strings: Strings) -> Decoder {
+        Decoder { strings, pending: Vec::new() }
+    }
+
+    // The lines of every record completed by `bytes`.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &byte in bytes {
+            if byte != 0 {
+                self.pending.push(byte);
+                continue;
+            }
+            if let Some(line) = self.frame() {
+                lines.push(line);
+            }
+            self.pending.clear();
+        }
+        lines
+    }
+
+    // Whether a partial record is waiting for the rest of its bytes.
+    pub fn is_partial(&self) -> bool {
+        is_record(&self.pending)
+    }
+
+    // The line for the bytes before a zero, if they were a log record.
+    fn frame(&self) -> Option<String> {
+        if !is_record(&self.pending) {
+            return None;
+        }
+        let mut frame = vec![0; self.pending.len()];
+        let length = match cobs::decode(&self.pending, &mut frame) {
+            Some(length) if length >= 10 => length,
+            _ => return Some(format!("<bad record: {:?}>", self.pending)),
+        };
+        let crc = frame[length - 2] as u16 | (frame[length - 1] as u16) << 8;
+        if crc::crc16(0, &frame[..length - 2]) != crc {
+            return Some("<corrupted record>".to_string());
+        }
+        Some(self.line(&frame[1..length - 2]))
+    }
+
+    // One record, without its tag and CRC.
+    fn line(&self, record: &[u8]) -> String {
+        if record.len() < 7 {
+            return format!("<record too short: {:?}>", record);
+        }
+        let level = match record[0] {
+            0 => "ERROR",
+            1 => "WARN ",
+            2 => "INFO ",
+            3 => "DEBUG",
+            _ => "?????",
+        };
+        let index = record[1] as u16 | (record[2] as u16) << 8;
+        let time_ms = u32_at(&record[3..]);
+
+        let (values, complete) = values(&record[7..]);
+        let mut text = match self.strings.get(&index) {
+            Some(message) => format!("{}: {}", message.module, format::format(&message.format, &values)),
+            None => format!("<unknown format string {}> {:?}", index, values),
+        };
+        if !complete {
+            text.push_str(" <bad argument>");
+        }
+        format!("[{:5}.{:03}] {} {}", time_ms / 1000, time_ms % 1000, level, text)
+    }
+}
+
+// Whether COBS encoded bytes start with a log record's tag. The first byte is a length, unless
+// it's 1, which stands for a zero.
+fn is_record(encoded: &[u8]) -> bool {
+    encoded.len() >= 2 && encoded[0] != 1 && encoded[1] == LOG_RECORD
+}
+
+// The arguments of a record, and whether they all made sense.
+fn values(mut bytes: &[u8]) -> (Vec<Value>, bool) {
+    let mut values = Vec::new();
+    while !bytes.is_empty() {
+        let (value, size) = match value(bytes) {
+            Some(value) => value,
+            None => return (values, false),
+        };
+        values.push(value);
+        bytes = &bytes[size..];
+    }
+    (values, true)
+}
+
+// One argument, and the bytes it took up.
+fn value(bytes: &[u8]) -> Option<(Value, usize)> {
+    let tag = bytes[0];
+    let rest = &bytes[1..];
+    match tag {
+        0 | 1 | 3 if rest.len() >= 4 => {
+            let n = u32_at(rest);
+            let value = match tag {
+                0 => Value::Unsigned(n),
+                1 => Value::Signed(n as i32),
+                _ => match ::std::char::from_u32(n) {
+                    Some(c) => Value::Char(c),
+                    None => return None,
+                },
+            };
+            Some((value, 5))
+        }
+        2 if !rest.is_empty() => Some((Value::Bool(rest[0] != 0), 2)),
+        4 | 5 if !rest.is_empty() && rest.len() > rest[0] as usize => {
+            let text = String::from_utf8_lossy(&rest[1..1 + rest[0] as usize]).into_owned();
+            let value = if tag == 4 { Value::Str(text) } else { Value::Text(text) };
+            Some((value, 2 + rest[0] as usize))
+        }
+        _ => None,
+    }
+}
+
+fn u32_at(bytes: &[u8]) -> u32 {
+    bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::frame;
+    use size_report::elf::{Elf, Section, Symbol};
+
+    fn section(name: &str, address: u64, size: usize, offset: usize, loaded: bool) -> Section {
+        Section {
+            name: name.to_string(),
+            address,
+            load_address: address,
+            size: size as u64,
+            allocated: loaded,
+            loaded,
+            offset: Some(offset as u64),
+        }
+    }
+
+    fn push_u32(out: &mut Vec<u8>, value: u32) {
+        out.extend_from_slice(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]);
+    }
+
+    // A firmware ELF and its file, with a static in .log_strings for each message (module path,
+    // format string) and the module paths in .rodata, as the firmware lays them out.
+    fn elf(messages: &[(&str, &str)]) -> (Elf, Vec<u8>) {
+        const RODATA: u32 = 0x1000;
+        let mut statics = Vec::new();
+        let mut rodata = Vec::new();
+        let mut symbols = Vec::new();
+        for &(module_path, format) in messages {
+            let start = statics.len();
+            push_u32(&mut statics, RODATA + rodata.len() as u32);
+            push_u32(&mut statics, module_path.len() as u32);
+            statics.extend_from_slice(format.as_bytes());
+            statics.push(0);
+            rodata.extend_from_slice(module_path.as_bytes());
+            symbols.push(Symbol {
+                name: "_ZN16rust_tiva_blinky7MESSAGE17h0123456789abcdefE".to_string(),
+                address: 4 + start as u64,
+                size: (statics.len() - start) as u64,
+                section: Some(1),
+                sized_object: true,
+            });
+            // The next static is four byte aligned.
+            while statics.len() % 4 != 0 {
+                statics.push(0);
+            }
+        }
+        symbols.push(Symbol { name: "start".to_string(), address: 0, size: 2, section: Some(3), sized_object: true });
+
+        let mut data = statics.clone();
+        data.extend_from_slice(&rodata);
+        let sections = vec![section("", 0, 0, 0, false),
+                            section(".log_strings", 4, statics.len(), 0, false),
+                            section(".rodata", RODATA as u64, rodata.len(), statics.len(), true),
+                            section(".text", 0, 0, data.len(), true)];
+        (Elf { sections, symbols }, data)
+    }
+
+    fn message(module: &str, format: &str) -> Message {
+        Message { module: module.to_string(), format: format.to_string() }
+    }
+
+    fn test_strings() -> Strings {
+        let mut strings = Strings::new();
+        strings.insert(0, message("main", "Started, system clock {} Hz"));
+        strings.insert(1, message("uart", "Serial errors: {:?}"));
+        strings
+    }
+
+    // A record in its frame, as the firmware sends it.
+    fn sealed(record: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![LOG_RECORD];
+        bytes.extend_from_slice(record);
+        let length = bytes.len();
+        bytes.extend_from_slice(&[0, 0]);
+        let mut out = vec![0; frame::encoded_length(length)];
+        let sealed = frame::seal(&mut bytes, length, &mut out).unwrap();
+        out.truncate(sealed);
+        out
+    }
+
+    #[test]
+    fn messages_are_the_statics_in_their_section() {
+        let (elf, data) = elf(&[("rust_tiva_blinky", "Started, system clock {} Hz"),
+                                ("rust_tiva_blinky::uart", "Serial errors: {:?}")]);
+        let mut expected = test_strings();
+        // The first static takes 36 bytes, so the second is at 40.
+        let uart = expected.remove(&1).unwrap();
+        expected.insert(9, uart);
+        assert_eq!(expected, strings(&elf, &data).unwrap());
+    }
+
+    #[test]
+    fn messages_can_share_a_format_string() {
+        let (elf, data) = elf(&[("rust_tiva_blinky::led_flash_controller", "{:?}"),
+                                ("rust_tiva_blinky::uart", "{:?}")]);
+        let strings = strings(&elf, &data).unwrap();
+        assert_eq!(vec![message("led_flash_controller", "{:?}"), message("uart", "{:?}")],
+                   strings.values().cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn statics_without_the_end_of_their_format_string_are_rejected() {
+        let (mut elf, data) = elf(&[("rust_tiva_blinky::uart", "Serial errors: {:?}")]);
+        // Cut the static off before its zero.
+        elf.symbols[0].size -= 1;
+        assert!(strings(&elf, &data).is_err());
+    }
+
+    #[test]
+    fn statics_whose_module_is_not_in_flash_are_rejected() {
+        let (elf, mut data) = elf(&[("rust_tiva_blinky::uart", "Serial errors: {:?}")]);
+        // Move the module path past the end of .rodata.
+        data[0] = 0x10;
+        assert!(strings(&elf, &data).is_err());
+    }
+
+    #[test]
+    fn firmware_without_binary_logs_has_no_strings() {
+        let elf = Elf { sections: vec![section(".text", 0, 0, 0, true)], symbols: Vec::new() };
+        assert!(strings(&elf, &[]).is_err());
+    }
+
+    #[test]
+    fn records_are_decoded_into_lines() {
+        let mut decoder = Decoder::new(test_strings());
+        let mut bytes = sealed(&[2, 0, 0, 0x39, 0x30, 0, 0, 0, 0x00, 0x24, 0xF4, 0x00]);
+        bytes.extend(sealed(&[1, 1, 0, 5, 0, 0, 0, 5, 5, b'E', b'{', b' ', b'}', b'!']));
+        assert_eq!(vec!["[   12.345] INFO  main: Started, system clock 16000000 Hz".to_string(),
+                        "[    0.005] WARN  uart: Serial errors: E{ }!".to_string()],
+                   decoder.push(&bytes));
+        assert!(!decoder.is_partial());
+    }
+
+    #[test]
+    fn records_can_arrive_in_pieces() {
+        let record = sealed(&[0, 0, 0, 1, 0, 0, 0, 2, 1]);
+        let mut decoder = Decoder::new(test_strings());
+        assert!(decoder.push(&record[..4]).is_empty());
+        assert!(decoder.is_partial());
+        assert_eq!(vec!["[    0.001] ERROR main: Started, system clock true Hz".to_string()],
+                   decoder.push(&record[4..]));
+    }
+
+    #[test]
+    fn text_and_protocol_frames_are_skipped() {
+        let record = sealed(&[0, 0, 0, 1, 0, 0, 0, 2, 1]);
+        let mut bytes = b"> help\r\n".to_vec();
+        bytes.extend(&record);
+        // A protocol frame: a Ping with id 0.
+        bytes.extend_from_slice(&[0x00, 0x02, 0x01, 0x03, 0x01, 0x90, 0x01, 0x00]);
+        bytes.extend_from_slice(b"[    0.002] INFO  main: text\r\n");
+        bytes.extend(&record);
+        bytes.extend_from_slice(b"> ");
+        let mut decoder = Decoder::new(test_strings());
+        assert_eq!(2, decoder.push(&bytes).len());
+        assert!(!decoder.is_partial());
+    }
+
+    #[test]
+    fn corrupted_records_are_shown() {
+        let mut record = sealed(&[0, 0, 0, 1, 0, 0, 0, 2, 1]);
+        // The last byte before the closing zero is part of the CRC.
+        let crc = record.len() - 2;
+        record[crc] ^= 0x40;
+        let mut decoder = Decoder::new(test_strings());
+        assert_eq!(vec!["<corrupted record>".to_string()], decoder.push(&record));
+    }
+
+    #[test]
+    fn unknown_strings_and_bad_arguments_are_shown() {
+        let mut decoder = Decoder::new(test_strings());
+        let mut bytes = sealed(&[3, 9, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0]);
+        bytes.extend(sealed(&[2, 0, 0, 0, 0, 0, 0, 4, 9]));
+        assert_eq!(vec!["[    0.000] DEBUG <unknown format string 9> [Unsigned(7)]".to_string(),
+                        "[    0.000] INFO  main: Started, system clock <missing> Hz <bad argument>".to_string()],
+                   decoder.push(&bytes));
+    }
+}
@@ -0,0 +1,78 @@
+/*
+    log_decoder <elf> [<capture>]
+
+    Prints the binary log records in the capture file as text, using the format strings in the
+    firmware's ELF. Shell text and protocol frames in the capture are skipped. Without a capture it reads from stdin, and prints each line as soon as its
+    record arrives, so it can read straight from the serial port:
+
+        log_decoder target/cortex-m4f/debug/rust-tiva-blinky < /dev/ttyACM0
+*/
+
+extern crate log_decoder;
+extern crate size_report;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+use log_decoder::Decoder;
+use size_report::elf;
+
+const USAGE: &str = "usage: log_decoder <elf> [<capture>]";
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut data))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    Ok(data)
+}
+
+fn print(lines: Vec<String>) -> Result<(), String> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in lines {
+        writeln!(out, "{}", line).map_err(|e| e.to_string())?;
+    }
+    out.flush().map_err(|e| e.to_string())
+}
+
+fn run(elf_path: &str, capture: Option<&String>) -> Result<(), String> {
+    let data = read(elf_path)?;
+    let elf = elf::parse(&data).map_err(|e| e.to_string())?;
+    let mut decoder = Decoder::new(log_decoder::strings(&elf, &data)?);
+
+    match capture {
+        Some(path) => print(decoder.push(&read(path)?))?,
+        None => {
+            let mut buffer = [0; 256];
+            let stdin = io::stdin();
+            let mut input = stdin.lock();
+            loop {
+                let count = input.read(&mut buffer).map_err(|e| e.to_string())?;
+                if count == 0 {
+                    break;
+                }
+                print(decoder.push(&buffer[..count]))?;
+            }
+        }
+    }
+
+    if decoder.is_partial() {
+        eprintln!("log_decoder: the capture ends part way through a record");
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() || args.len() > 2 {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    if let Err(e) = run(&args[0], args.get(1)) {
+        eprintln!("log_decoder: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,300 @@
+/*
+    Just enough of a 32-bit little endian ELF reader to size up the firmware: the section headers,
+    the loadable segments (to find where each section is loaded from) and the symbol table.
+*/
+
+use Error;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u32 = 0x2;
+const PT_LOAD: u32 = 1;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+const HEADER_SIZE: usize = 52;
+const SECTION_HEADER_SIZE: usize = 40;
+const PROGRAM_HEADER_SIZE: usize = 32;
+const SYMBOL_SIZE: usize = 16;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Section {
+    pub name: String,
+    // The run time address.
+    pub address: u64,
+    // Where the contents are stored in the image. The same as the address unless the section is
+    // copied at startup, like .data.
+    pub load_address: u64,
+    pub size: u64,
+    // Whether the section takes up memory on the target (rather than being debug information).
+    pub allocated: bool,
+    // Whether the section has contents in the image. Zeroed sections like .bss don't.
+    pub loaded: bool,
+    // Where the contents are in the file. Zeroed sections have none.
+    pub offset: Option<u64>,
+}
+
+impl Section {
+    // The section's contents in the file `data`, if it has any.
+    pub fn contents<'a>(&self, data: &'a [u8]) -> Option<&'a [u8]> {
+        let start = self.offset? as usize;
+        data.get(start..start.checked_add(self.size as usize)?)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    // The index into Elf::sections of the section the symbol is in, if any.
+    pub section: Option<usize>,
+    // True for functions and data objects, as opposed to labels, files and sections.
+    pub sized_object: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Elf {
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+}
+
+struct Segment {
+    offset: u64,
+    file_size: u64,
+    physical_address: u64,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, length: usize) -> Result<&'a [u8], Error> {
+        match offset.checked_add(length) {
+            Some(end) if end <= self.data.len() => Ok(&self.data[offset..end]),
+            _ => Err(Error::Elf(format!("truncated at offset {:#x}", offset))),
+        }
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, Error> {
+        Ok(self.bytes(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, Error> {
+        let b = self.bytes(offset, 2)?;
+        Ok(b[0] as u16 | (b[1] as u16) << 8)
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, Error> {
+        let b = self.bytes(offset, 4)?;
+        Ok(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+    }
+
+    // A null terminated string starting at `offset`.
+    fn string(&self, offset: usize) -> Result<String, Error> {
+        let rest = self.bytes(offset, self.data.len().saturating_sub(offset))?;
+        let end = rest.iter().position(|&b| b == 0)
+            .ok_or_else(|| Error::Elf(format!("unterminated string at offset {:#x}", offset)))?;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+}
+
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u32,
+    address: u32,
+    offset: u32,
+    size: u32,
+    link: u32,
+}
+
+pub fn parse(data: &[u8]) -> Result<Elf, Error> {
+    let r = Reader { data };
+
+    if r.bytes(0, 4)? != b"\x7fELF" {
+        return Err(Error::Elf("not an ELF file".to_string()));
+    }
+    if r.u8(4)? != 1 || r.u8(5)? != 1 {
+        return Err(Error::Elf("only 32-bit little endian files are supported".to_string()));
+    }
+    r.bytes(0, HEADER_SIZE)?;
+
+    let program_headers = r.u32(28)? as usize;
+    let section_headers = r.u32(32)? as usize;
+    let program_header_size = r.u16(42)? as usize;
+    let program_header_count = r.u16(44)? as usize;
+    let section_header_size = r.u16(46)? as usize;
+    let section_header_count = r.u16(48)? as usize;
+    let section_names = r.u16(50)? as usize;
+
+    if section_header_count > 0 && section_header_size < SECTION_HEADER_SIZE {
+        return Err(Error::Elf("section headers are too small".to_string()));
+    }
+    if program_header_count > 0 && program_header_size < PROGRAM_HEADER_SIZE {
+        return Err(Error::Elf("program headers are too small".to_string()));
+    }
+
+    let mut segments = Vec::new();
+    for i in 0..program_header_count {
+        let at = program_headers + i * program_header_size;
+        if r.u32(at)? == PT_LOAD {
+            segments.push(Segment {
+                offset: r.u32(at + 4)? as u64,
+                physical_address: r.u32(at + 12)? as u64,
+                file_size: r.u32(at + 16)? as u64,
+            });
+        }
+    }
+
+    let mut headers = Vec::new();
+    for i in 0..section_header_count {
+        let at = section_headers + i * section_header_size;
+        headers.push(SectionHeader {
+            name: r.u32(at)?,
+            kind: r.u32(at + 4)?,
+            flags: r.u32(at + 8)?,
+            address: r.u32(at + 12)?,
+            offset: r.u32(at + 16)?,
+            size: r.u32(at + 20)?,
+            link: r.u32(at + 24)?,
+        });
+    }
+
+    let names_offset = match headers.get(section_names) {
+        Some(header) => header.offset as usize,
+        None => return Err(Error::Elf("missing section name table".to_string())),
+    };
+
+    let mut sections = Vec::new();
+    for header in &headers {
+        let loaded = header.kind != SHT_NOBITS && header.flags & SHF_ALLOC != 0;
+        sections.push(Section {
+            name: r.string(names_offset + header.name as usize)?,
+            address: header.address as u64,
+            load_address: if loaded {
+                load_address(&segments, header)
+            } else {
+                header.address as u64
+            },
+            size: header.size as u64,
+            allocated: header.flags & SHF_ALLOC != 0,
+            loaded,
+            offset: if header.kind != SHT_NOBITS { Some(header.offset as u64) } else { None },
+        });
+    }
+
+    let mut symbols = Vec::new();
+    for header in headers.iter().filter(|h| h.kind == SHT_SYMTAB) {
+        let strings = match headers.get(header.link as usize) {
+            Some(strings) => strings.offset as usize,
+            None => return Err(Error::Elf("missing symbol name table".to_string())),
+        };
+        // The first symbol is always the null symbol.
+        for i in 1..header.size as usize / SYMBOL_SIZE {
+            let at = header.offset as usize + i * SYMBOL_SIZE;
+            let kind = r.u8(at + 12)? & 0xf;
+            let index = r.u16(at + 14)? as usize;
+            let mut address = r.u32(at + 4)? as u64;
+            // Thumb function addresses have the lowest bit set.
+            if kind == STT_FUNC {
+                address &= !1;
+            }
+            symbols.push(Symbol {
+                name: r.string(strings + r.u32(at)? as usize)?,
+                address,
+                size: r.u32(at + 8)? as u64,
+                section: if index > 0 && index < sections.len() { Some(index) } else { None },
+                sized_object: kind == STT_FUNC || kind == STT_OBJECT,
+            });
+        }
+    }
+
+    Ok(Elf { sections, symbols })
+}
+
+// The load address of a section is found through the segment holding its contents.
+fn load_address(segments: &[Segment], header: &SectionHeader) -> u64 {
+    let offset = header.offset as u64;
+    segments.iter()
+        .find(|s| offset >= s.offset && offset < s.offset + s.file_size)
+        .map(|s| s.physical_address + (offset - s.offset))
+        .unwrap_or(header.address as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_elf::{TestElf, FUNC, OBJECT};
+
+    #[test]
+    fn sections_are_read_with_their_addresses_and_sizes() {
+        let data = TestElf::new()
+            .text(".text", 0x0, 0x100)
+            .bss(".bss", 0x2000_0000, 0x40)
+            .build();
+        let elf = parse(&data).unwrap();
+
+        let text = elf.sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!((0x0, 0x100, true, true), (text.address, text.size, text.allocated, text.loaded));
+
+        let bss = elf.sections.iter().find(|s| s.name == ".bss").unwrap();
+        assert_eq!((0x2000_0000, 0x40, true, false), (bss.address, bss.size, bss.allocated, bss.loaded));
+    }
+
+    #[test]
+    fn copied_sections_have_a_load_address_in_the_image() {
+        let data = TestElf::new()
+            .text(".text", 0x0, 0x100)
+            .data(".data", 0x2000_0000, 0x100, 0x10)
+            .build();
+        let elf = parse(&data).unwrap();
+
+        let data = elf.sections.iter().find(|s| s.name == ".data").unwrap();
+        assert_eq!(0x2000_0000, data.address);
+        assert_eq!(0x100, data.load_address);
+    }
+
+    #[test]
+    fn contents_are_read_from_the_file() {
+        let data = TestElf::new()
+            .text(".text", 0x0, 0x100)
+            .bss(".bss", 0x2000_0000, 0x40)
+            .build();
+        let elf = parse(&data).unwrap();
+
+        let text = elf.sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(Some(&[0xAA; 0x100][..]), text.contents(&data));
+        let bss = elf.sections.iter().find(|s| s.name == ".bss").unwrap();
+        assert_eq!(None, bss.contents(&data));
+    }
+
+    #[test]
+    fn symbols_are_read_with_thumb_bits_cleared() {
+        let data = TestElf::new()
+            .text(".text", 0x0, 0x100)
+            .symbol("reset", 0x41, 0x20, FUNC, ".text")
+            .symbol("TABLE", 0x80, 0x10, OBJECT, ".text")
+            .build();
+        let elf = parse(&data).unwrap();
+
+        let reset = elf.symbols.iter().find(|s| s.name == "reset").unwrap();
+        assert_eq!((0x40, 0x20, true), (reset.address, reset.size, reset.sized_object));
+        assert_eq!(".text", elf.sections[reset.section.unwrap()].name);
+
+        let table = elf.symbols.iter().find(|s| s.name == "TABLE").unwrap();
+        assert_eq!(0x80, table.address);
+    }
+
+    #[test]
+    fn files_which_are_not_elf_are_rejected() {
+        assert!(parse(b"not an elf file at all").is_err());
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let data = TestElf::new().text(".text", 0x0, 0x100).build();
+        assert!(parse(&data[..60]).is_err());
+    }
+}
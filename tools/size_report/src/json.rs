@@ -0,0 +1,332 @@
+/*
+    A small JSON value, enough to write the report and read a saved one back as a baseline. Sizes
+    are whole numbers, so numbers are integers. Objects keep their keys in order so the report
+    reads the same way every time.
+*/
+
+use std::fmt::{self, Write};
+use Error;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref members) => members.iter().find(|m| m.0 == key).map(|m| &m.1),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Json::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match *self {
+            Json::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match *self {
+            Json::Object(ref members) => Some(members),
+            _ => None,
+        }
+    }
+
+    // Pretty print with two space indents.
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0).unwrap();
+        out.push('\n');
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize) -> fmt::Result {
+        match *self {
+            Json::Null => out.write_str("null"),
+            Json::Bool(b) => write!(out, "{}", b),
+            Json::Number(n) => write!(out, "{}", n),
+            Json::String(ref s) => write_string(out, s),
+            Json::Array(ref items) if items.is_empty() => out.write_str("[]"),
+            Json::Array(ref items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    item.write(out, indent + 1)?;
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.write_str("]")
+            }
+            Json::Object(ref members) if members.is_empty() => out.write_str("{}"),
+            Json::Object(ref members) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in members.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    write_string(out, key)?;
+                    out.push_str(": ");
+                    value.write(out, indent + 1)?;
+                    out.push_str(if i + 1 < members.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.write_str("}")
+            }
+        }
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn write_string(out: &mut String, s: &str) -> fmt::Result {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.push(c),
+        }
+    }
+    out.write_str("\"")
+}
+
+pub fn parse(text: &str) -> Result<Json, Error> {
+    let mut parser = Parser { chars: text.chars().collect(), position: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position < parser.chars.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> Error {
+        Error::Json(format!("{} at character {}", message, self.position))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        self.skip_whitespace();
+        if self.next() == Some(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", expected)))
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, Error> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(self.error("unknown keyword"));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, Error> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if self.peek().is_some_and(|c| c == '.' || c == 'e' || c == 'E') {
+            return Err(self.error("only whole numbers are supported"));
+        }
+        let text: String = self.chars[start..self.position].iter().cloned().collect();
+        text.parse().map(Json::Number).map_err(|_| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.next().and_then(|c| c.to_digit(16));
+            code = code * 16 + digit.ok_or_else(|| self.error("invalid unicode escape"))?;
+        }
+        Ok(::std::char::from_u32(code).unwrap_or('\u{fffd}'))
+    }
+
+    fn array(&mut self) -> Result<Json, Error> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, Error> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Json {
+        Json::Object(vec![
+            ("name".to_string(), Json::String("FLASH \"main\"\n".to_string())),
+            ("used".to_string(), Json::Number(1234)),
+            ("change".to_string(), Json::Number(-16)),
+            ("sections".to_string(), Json::Array(vec![Json::Null, Json::Bool(true)])),
+            ("empty".to_string(), Json::Object(vec![])),
+        ])
+    }
+
+    #[test]
+    fn values_survive_a_round_trip() {
+        assert_eq!(sample(), parse(&sample().pretty()).unwrap());
+    }
+
+    #[test]
+    fn objects_keep_their_key_order() {
+        let text = sample().pretty();
+        assert!(text.find("\"name\"").unwrap() < text.find("\"used\"").unwrap());
+        assert!(text.find("\"used\"").unwrap() < text.find("\"change\"").unwrap());
+    }
+
+    #[test]
+    fn members_are_found_by_key() {
+        let value = sample();
+        assert_eq!(Some(1234), value.get("used").and_then(Json::as_i64));
+        assert_eq!(None, value.get("missing"));
+    }
+
+    #[test]
+    fn compact_json_can_be_read() {
+        let value = parse(r#"{"a":[1,2,{"b":"A"}],"c":false}"#).unwrap();
+        assert_eq!(Some("A"), value.get("a").unwrap().as_array().unwrap()[2].get("b").unwrap().as_str());
+    }
+
+    #[test]
+    fn fractions_are_rejected() {
+        assert!(parse("1.5").is_err());
+    }
+
+    #[test]
+    fn malformed_json_is_rejected() {
+        assert!(parse("{\"a\": 1,}").is_err());
+        assert!(parse("[1 2]").is_err());
+        assert!(parse("\"open").is_err());
+        assert!(parse("{} {}").is_err());
+    }
+}
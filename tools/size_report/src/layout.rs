@@ -0,0 +1,179 @@
+/*
+    Reads the memory regions from the MEMORY command of a linker script:
+
+        MEMORY
+        {
+            FLASH (rx) : ORIGIN = 0x00000000, LENGTH = 256K
+            RAM (rwx) : ORIGIN = 0x20000000, LENGTH = 32K
+        }
+
+    Only literal origins and lengths are understood, in decimal or hex with an optional K or M.
+*/
+
+use Error;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Region {
+    pub name: String,
+    pub origin: u64,
+    pub length: u64,
+}
+
+impl Region {
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.origin && address < self.origin + self.length
+    }
+}
+
+pub fn parse_memory(script: &str) -> Result<Vec<Region>, Error> {
+    let script = strip_comments(script);
+
+    let start = match find_word(&script, "MEMORY") {
+        Some(start) => start,
+        None => return Err(Error::Layout("no MEMORY command".to_string())),
+    };
+    let rest = &script[start..];
+    let body = match (rest.find('{'), rest.find('}')) {
+        (Some(open), Some(close)) if open < close => &rest[open + 1..close],
+        _ => return Err(Error::Layout("MEMORY has no {} block".to_string())),
+    };
+
+    body.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_region)
+        .collect()
+}
+
+// One region, like `FLASH (rx) : ORIGIN = 0x00000000, LENGTH = 256K`.
+fn parse_region(line: &str) -> Result<Region, Error> {
+    let invalid = || Error::Layout(format!("can't read memory region `{}`", line));
+
+    let colon = line.find(':').ok_or_else(&invalid)?;
+    let name = line[..colon].split(|c: char| c == '(' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_string();
+    if name.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut origin = None;
+    let mut length = None;
+    for setting in line[colon + 1..].split(',') {
+        let mut parts = setting.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parse_number(parts.next().unwrap_or("").trim()).ok_or_else(&invalid)?;
+        match key {
+            "ORIGIN" | "org" | "o" => origin = Some(value),
+            "LENGTH" | "len" | "l" => length = Some(value),
+            _ => return Err(invalid()),
+        }
+    }
+
+    match (origin, length) {
+        (Some(origin), Some(length)) => Ok(Region { name, origin, length }),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    let (digits, multiplier) = if text.ends_with('K') || text.ends_with('k') {
+        (&text[..text.len() - 1], 1024)
+    } else if text.ends_with('M') || text.ends_with('m') {
+        (&text[..text.len() - 1], 1024 * 1024)
+    } else {
+        (text, 1)
+    };
+
+    let value = if digits.starts_with("0x") || digits.starts_with("0X") {
+        u64::from_str_radix(&digits[2..], 16).ok()
+    } else {
+        digits.parse().ok()
+    };
+    value.map(|v| v * multiplier)
+}
+
+fn strip_comments(script: &str) -> String {
+    let mut out = String::new();
+    let mut rest = script;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = match rest[start..].find("*/") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out
+}
+
+// The position of `word` where it isn't part of a longer name.
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word)
+        .map(|(i, _)| i)
+        .find(|&i| {
+            !text[..i].chars().next_back().is_some_and(&is_name) &&
+            !text[i + word.len()..].chars().next().is_some_and(&is_name)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &str = "
+        MEMORY
+        {
+            FLASH (rx) : ORIGIN = 0x00000000, LENGTH = 256K
+            RAM (rwx) : ORIGIN = 0x20000000, LENGTH = 32K
+        }
+
+        /* The stack size, generated by build.rs. */
+        INCLUDE memory.ld
+    ";
+
+    #[test]
+    fn regions_are_read_in_order() {
+        assert_eq!(vec![
+            Region { name: "FLASH".to_string(), origin: 0, length: 256 * 1024 },
+            Region { name: "RAM".to_string(), origin: 0x2000_0000, length: 32 * 1024 },
+        ], parse_memory(LAYOUT).unwrap());
+    }
+
+    #[test]
+    fn lengths_can_be_in_megabytes_or_plain_numbers() {
+        let regions = parse_memory("MEMORY { \n FLASH : ORIGIN = 0, LENGTH = 1M \n RAM : org = 536870912, len = 4096 \n }")
+            .unwrap();
+        assert_eq!(1024 * 1024, regions[0].length);
+        assert_eq!((0x2000_0000, 4096), (regions[1].origin, regions[1].length));
+    }
+
+    #[test]
+    fn comments_are_ignored() {
+        let regions = parse_memory("/* MEMORY { BOGUS : ORIGIN = 0 } */ MEMORY\n{\n  RAM : ORIGIN = 0x100, LENGTH = 0x10 /* small */\n}")
+            .unwrap();
+        assert_eq!(1, regions.len());
+        assert_eq!("RAM", regions[0].name);
+    }
+
+    #[test]
+    fn a_script_without_memory_is_an_error() {
+        assert!(parse_memory("SECTIONS { }").is_err());
+    }
+
+    #[test]
+    fn a_region_without_a_length_is_an_error() {
+        assert!(parse_memory("MEMORY { \n RAM : ORIGIN = 0x100 \n }").is_err());
+    }
+
+    #[test]
+    fn regions_contain_addresses_from_their_origin_up_to_their_length() {
+        let ram = Region { name: "RAM".to_string(), origin: 0x100, length: 0x10 };
+        assert!(!ram.contains(0xFF));
+        assert!(ram.contains(0x100));
+        assert!(ram.contains(0x10F));
+        assert!(!ram.contains(0x110));
+    }
+}
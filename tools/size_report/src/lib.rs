@@ -0,0 +1,48 @@
+/*
+    Reports what fills FLASH and RAM, from the linked firmware ELF and the MEMORY regions of the
+    linker script. The report lists each region's usage, each section and the size of each crate,
+    and can be compared against a saved report to see what changed.
+*/
+
+use std::fmt;
+use std::io;
+
+pub mod elf;
+pub mod json;
+pub mod layout;
+pub mod report;
+
+#[cfg(test)]
+mod test_elf;
+
+pub use report::Report;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Elf(String),
+    Layout(String),
+    Json(String),
+    // A region grew past its budget: its name, what it uses and what it's allowed.
+    OverBudget(String, u64, u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::Elf(ref message) => write!(f, "bad ELF file: {}", message),
+            Error::Layout(ref message) => write!(f, "bad linker script: {}", message),
+            Error::Json(ref message) => write!(f, "bad JSON: {}", message),
+            Error::OverBudget(ref region, used, allowed) => {
+                write!(f, "{} is over budget, using {} bytes of the {} allowed", region, used, allowed)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,137 @@
+/*
+    size_report <elf> <linker script> [--baseline <report.json>] [--json <report.json>]
+                [--crates <count>] [--margin <bytes>]
+
+    Prints a summary of the firmware's memory usage. With --baseline, the summary and the JSON
+    report include the changes since that report was saved. A baseline which doesn't exist yet is
+    skipped with a warning, so the first build can create it.
+
+    With --margin, the baseline is a budget: it fails if a region uses more than the baseline
+    did plus the margin, or if there's no baseline to check against.
+*/
+
+extern crate size_report;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+use size_report::{elf, json, layout, Error, Report};
+
+const USAGE: &str =
+    "usage: size_report <elf> <linker script> [--baseline <report.json>] [--json <report.json>] \
+     [--crates <count>] [--margin <bytes>]";
+
+struct Options {
+    elf: String,
+    layout: String,
+    baseline: Option<String>,
+    json: Option<String>,
+    crates: usize,
+    margin: Option<u64>,
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut positional = Vec::new();
+    let mut options = Options {
+        elf: String::new(),
+        layout: String::new(),
+        baseline: None,
+        json: None,
+        crates: 15,
+        margin: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg.clone());
+            continue;
+        }
+        let value = match args.next() {
+            Some(value) => value.clone(),
+            None => return None,
+        };
+        match arg.as_str() {
+            "--baseline" => options.baseline = Some(value),
+            "--json" => options.json = Some(value),
+            "--crates" => match value.parse() {
+                Ok(count) => options.crates = count,
+                Err(_) => return None,
+            },
+            "--margin" => match value.parse() {
+                Ok(bytes) => options.margin = Some(bytes),
+                Err(_) => return None,
+            },
+            _ => return None,
+        }
+    }
+
+    if positional.len() != 2 || (options.margin.is_some() && options.baseline.is_none()) {
+        return None;
+    }
+    options.layout = positional.pop().unwrap();
+    options.elf = positional.pop().unwrap();
+    Some(options)
+}
+
+fn read(path: &str) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn read_text(path: &str) -> Result<String, Error> {
+    String::from_utf8(read(path)?).map_err(|_| Error::Layout(format!("{} isn't text", path)))
+}
+
+fn run(options: &Options) -> Result<(), Error> {
+    let elf = elf::parse(&read(&options.elf)?)?;
+    let regions = layout::parse_memory(&read_text(&options.layout)?)?;
+    let report = Report::new(&elf, &regions);
+
+    let baseline = match options.baseline {
+        Some(ref path) if Path::new(path).exists() => {
+            Some(Report::from_json(&json::parse(&read_text(path)?)?)?)
+        }
+        Some(ref path) if options.margin.is_some() => {
+            let message = format!("no baseline at {} to budget against", path);
+            return Err(Error::Io(io::Error::new(io::ErrorKind::NotFound, message)));
+        }
+        Some(ref path) => {
+            eprintln!("warning: no baseline at {}, not comparing", path);
+            None
+        }
+        None => None,
+    };
+
+    print!("{}", report.text(baseline.as_ref(), options.crates));
+
+    if let Some(ref path) = options.json {
+        File::create(path)?.write_all(report.to_json(baseline.as_ref()).pretty().as_bytes())?;
+    }
+
+    if let (Some(margin), Some(ref baseline)) = (options.margin, baseline) {
+        if let Some((region, used, allowed)) = report.over_budget(baseline, margin).into_iter().next() {
+            return Err(Error::OverBudget(region, used, allowed));
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let options = match parse_options(&args) {
+        Some(options) => options,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(&options) {
+        eprintln!("size_report: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,599 @@
+/*
+    Sizes up the sections and crates of the firmware against the memory regions.
+
+    A section counts against the region it runs from, and also against the region it's loaded
+    from when that's different (.data runs from RAM but its initial values are stored in FLASH).
+    Zeroed sections like .bss only take up room where they run.
+
+    Symbols are grouped by the crate in their mangled name. Symbols without a Rust mangled name
+    (the C libraries and `#[no_mangle]` functions) are grouped together as UNMANGLED, and whatever
+    part of a section isn't covered by a symbol (padding, literal pools, the vector table) is
+    counted as NO_SYMBOL, so the crates of a region add up to its sections.
+*/
+
+use std::collections::{BTreeMap, BTreeSet};
+use elf::Elf;
+use json::Json;
+use layout::Region;
+use Error;
+
+pub const UNMANGLED: &str = "[unmangled]";
+pub const GENERIC: &str = "[generic]";
+pub const NO_SYMBOL: &str = "[no symbol]";
+pub const UNMAPPED: &str = "[unmapped]";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RegionUsage {
+    pub name: String,
+    pub origin: u64,
+    pub length: u64,
+    pub used: u64,
+}
+
+impl RegionUsage {
+    pub fn free(&self) -> i64 {
+        self.length as i64 - self.used as i64
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SectionUsage {
+    pub name: String,
+    pub address: u64,
+    pub load_address: u64,
+    pub size: u64,
+    // The region the section runs from, if it's in one.
+    pub region: Option<String>,
+    // The region the section is stored in, when it's copied into `region` at startup.
+    pub load_region: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CrateUsage {
+    pub name: String,
+    // Bytes used in each region, by region name.
+    pub sizes: BTreeMap<String, u64>,
+}
+
+impl CrateUsage {
+    pub fn total(&self) -> u64 {
+        self.sizes.values().sum()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Report {
+    pub regions: Vec<RegionUsage>,
+    pub sections: Vec<SectionUsage>,
+    pub crates: Vec<CrateUsage>,
+}
+
+// What grew or shrank since the baseline, leaving out anything which stayed the same. Sections
+// and crates which are new count from zero, ones which are gone count down to zero.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Changes {
+    pub regions: Vec<(String, i64)>,
+    pub sections: Vec<(String, i64)>,
+    pub crates: Vec<(String, i64)>,
+}
+
+impl Report {
+    pub fn new(elf: &Elf, regions: &[Region]) -> Report {
+        let region_of = |address: u64| regions.iter().find(|r| r.contains(address)).map(|r| r.name.clone());
+
+        let mut sections = Vec::new();
+        let mut section_places = BTreeMap::new();
+        for (index, section) in elf.sections.iter().enumerate() {
+            if !section.allocated || section.size == 0 {
+                continue;
+            }
+            let region = region_of(section.address);
+            let load_region = if section.loaded && section.load_address != section.address {
+                region_of(section.load_address)
+            } else {
+                None
+            };
+            section_places.insert(index, places(&region, &load_region));
+            sections.push(SectionUsage {
+                name: section.name.clone(),
+                address: section.address,
+                load_address: section.load_address,
+                size: section.size,
+                region,
+                load_region,
+            });
+        }
+
+        let mut usage = regions.iter()
+            .map(|r| RegionUsage { name: r.name.clone(), origin: r.origin, length: r.length, used: 0 })
+            .collect::<Vec<_>>();
+        for section in &sections {
+            for region in usage.iter_mut() {
+                if section.region.as_ref() == Some(&region.name) ||
+                   section.load_region.as_ref() == Some(&region.name) {
+                    region.used += section.size;
+                }
+            }
+        }
+
+        // Add up the symbols by crate. Aliases share an address, so each address only counts once.
+        let mut crates: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+        let mut covered: BTreeMap<usize, u64> = BTreeMap::new();
+        let mut seen = BTreeSet::new();
+        for symbol in &elf.symbols {
+            let index = match symbol.section {
+                Some(index) if symbol.sized_object && symbol.size > 0 => index,
+                _ => continue,
+            };
+            let places = match section_places.get(&index) {
+                Some(places) => places,
+                None => continue,
+            };
+            if !seen.insert((index, symbol.address)) {
+                continue;
+            }
+            let name = crate_name(&symbol.name);
+            for place in places {
+                *crates.entry(name.clone()).or_default()
+                    .entry(place.clone()).or_insert(0) += symbol.size;
+            }
+            *covered.entry(index).or_insert(0) += symbol.size;
+        }
+        for (index, places) in &section_places {
+            let size = elf.sections[*index].size;
+            let uncovered = size.saturating_sub(covered.get(index).cloned().unwrap_or(0));
+            if uncovered == 0 {
+                continue;
+            }
+            for place in places {
+                *crates.entry(NO_SYMBOL.to_string()).or_default()
+                    .entry(place.clone()).or_insert(0) += uncovered;
+            }
+        }
+
+        let mut crates = crates.into_iter()
+            .map(|(name, sizes)| CrateUsage { name, sizes })
+            .collect::<Vec<_>>();
+        crates.sort_by(|a, b| (b.total(), &a.name).cmp(&(a.total(), &b.name)));
+
+        Report { regions: usage, sections, crates }
+    }
+
+    pub fn changes(&self, baseline: &Report) -> Changes {
+        Changes {
+            regions: differences(
+                self.regions.iter().map(|r| (r.name.clone(), r.used)),
+                baseline.regions.iter().map(|r| (r.name.clone(), r.used))),
+            sections: differences(
+                self.sections.iter().map(|s| (s.name.clone(), s.size)),
+                baseline.sections.iter().map(|s| (s.name.clone(), s.size))),
+            crates: differences(
+                self.crates.iter().map(|c| (c.name.clone(), c.total())),
+                baseline.crates.iter().map(|c| (c.name.clone(), c.total()))),
+        }
+    }
+
+    // The regions which grew past what the baseline used plus `margin` bytes, with what they use
+    // now and what they were allowed.
+    pub fn over_budget(&self, baseline: &Report, margin: u64) -> Vec<(String, u64, u64)> {
+        let mut over = Vec::new();
+        for region in &self.regions {
+            let allowed = match baseline.regions.iter().find(|r| r.name == region.name) {
+                Some(before) => before.used + margin,
+                None => continue,
+            };
+            if region.used > allowed {
+                over.push((region.name.clone(), region.used, allowed));
+            }
+        }
+        over
+    }
+
+    pub fn to_json(&self, baseline: Option<&Report>) -> Json {
+        let regions = self.regions.iter().map(|r| object(vec![
+            ("name", string(&r.name)),
+            ("origin", number(r.origin)),
+            ("length", number(r.length)),
+            ("used", number(r.used)),
+            ("free", Json::Number(r.free())),
+        ])).collect();
+
+        let sections = self.sections.iter().map(|s| object(vec![
+            ("name", string(&s.name)),
+            ("address", number(s.address)),
+            ("load_address", number(s.load_address)),
+            ("size", number(s.size)),
+            ("region", s.region.as_ref().map_or(Json::Null, |r| string(r))),
+            ("load_region", s.load_region.as_ref().map_or(Json::Null, |r| string(r))),
+        ])).collect();
+
+        let crates = self.crates.iter().map(|c| object(vec![
+            ("name", string(&c.name)),
+            ("sizes", Json::Object(c.sizes.iter().map(|(r, &n)| (r.clone(), number(n))).collect())),
+            ("total", number(c.total())),
+        ])).collect();
+
+        let mut members = vec![
+            ("regions", Json::Array(regions)),
+            ("sections", Json::Array(sections)),
+            ("crates", Json::Array(crates)),
+        ];
+        if let Some(baseline) = baseline {
+            let changes = self.changes(baseline);
+            let to_object = |list: &[(String, i64)]| {
+                Json::Object(list.iter().map(|&(ref name, n)| (name.clone(), Json::Number(n))).collect())
+            };
+            members.push(("changes", object(vec![
+                ("regions", to_object(&changes.regions)),
+                ("sections", to_object(&changes.sections)),
+                ("crates", to_object(&changes.crates)),
+            ])));
+        }
+        object(members)
+    }
+
+    // Read a report saved with to_json, to use as a baseline.
+    pub fn from_json(json: &Json) -> Result<Report, Error> {
+        let invalid = |what: &str| Error::Json(format!("not a size report: bad {}", what));
+        let list = |key: &str| json.get(key).and_then(Json::as_array).ok_or_else(|| invalid(key));
+        let text = |item: &Json, key: &str| {
+            item.get(key).and_then(Json::as_str).map(|s| s.to_string()).ok_or_else(|| invalid(key))
+        };
+        let size = |item: &Json, key: &str| {
+            item.get(key).and_then(Json::as_i64).map(|n| n as u64).ok_or_else(|| invalid(key))
+        };
+        let optional_text = |item: &Json, key: &str| item.get(key).and_then(Json::as_str).map(|s| s.to_string());
+
+        let mut regions = Vec::new();
+        for item in list("regions")? {
+            regions.push(RegionUsage {
+                name: text(item, "name")?,
+                origin: size(item, "origin")?,
+                length: size(item, "length")?,
+                used: size(item, "used")?,
+            });
+        }
+
+        let mut sections = Vec::new();
+        for item in list("sections")? {
+            sections.push(SectionUsage {
+                name: text(item, "name")?,
+                address: size(item, "address")?,
+                load_address: size(item, "load_address")?,
+                size: size(item, "size")?,
+                region: optional_text(item, "region"),
+                load_region: optional_text(item, "load_region"),
+            });
+        }
+
+        let mut crates = Vec::new();
+        for item in list("crates")? {
+            let mut sizes = BTreeMap::new();
+            for (region, n) in item.get("sizes").and_then(Json::as_object).ok_or_else(|| invalid("sizes"))? {
+                sizes.insert(region.clone(), n.as_i64().ok_or_else(|| invalid("sizes"))? as u64);
+            }
+            crates.push(CrateUsage { name: text(item, "name")?, sizes });
+        }
+
+        Ok(Report { regions, sections, crates })
+    }
+
+    // A summary for people: the regions, the sections and the biggest crates.
+    pub fn text(&self, baseline: Option<&Report>, max_crates: usize) -> String {
+        let changes = baseline.map(|b| self.changes(b));
+        let change = |list: Option<&Vec<(String, i64)>>, name: &str| {
+            match list.and_then(|l| l.iter().find(|c| c.0 == name)) {
+                Some(&(_, n)) => format!("{:+}", n),
+                None if list.is_some() => "0".to_string(),
+                None => String::new(),
+            }
+        };
+
+        let mut out = String::new();
+        out.push_str(&format!("{:<12} {:>10} {:>10} {:>10} {:>7} {:>9}\n",
+                              "Region", "Used", "Size", "Free", "Used%", "Change"));
+        for r in &self.regions {
+            let percent = if r.length > 0 { r.used as f64 * 100.0 / r.length as f64 } else { 0.0 };
+            out.push_str(&format!("{:<12} {:>10} {:>10} {:>10} {:>6.1}% {:>9}\n", r.name, r.used,
+                                  r.length, r.free(), percent,
+                                  change(changes.as_ref().map(|c| &c.regions), &r.name)));
+        }
+
+        out.push_str(&format!("\n{:<24} {:>10} {:>10} {:>12} {:>9}\n",
+                              "Section", "Address", "Size", "Region", "Change"));
+        for s in &self.sections {
+            let region = match (&s.region, &s.load_region) {
+                (Some(run), Some(load)) => format!("{}>{}", load, run),
+                (Some(run), None) => run.clone(),
+                _ => UNMAPPED.to_string(),
+            };
+            out.push_str(&format!("{:<24} {:>#10x} {:>10} {:>12} {:>9}\n", s.name, s.address, s.size,
+                                  region, change(changes.as_ref().map(|c| &c.sections), &s.name)));
+        }
+
+        out.push_str(&format!("\n{:<24} {:>10} {:>9}  {}\n", "Crate", "Total", "Change", "By region"));
+        for c in self.crates.iter().take(max_crates) {
+            let by_region = c.sizes.iter()
+                .map(|(r, n)| format!("{} {}", r, n))
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!("{:<24} {:>10} {:>9}  {}\n", c.name, c.total(),
+                                  change(changes.as_ref().map(|c| &c.crates), &c.name), by_region));
+        }
+        if self.crates.len() > max_crates {
+            out.push_str(&format!("... and {} more\n", self.crates.len() - max_crates));
+        }
+
+        if let Some(ref changes) = changes {
+            let removed = baseline.unwrap().sections.iter()
+                .filter(|b| !self.sections.iter().any(|s| s.name == b.name))
+                .map(|b| b.name.clone())
+                .collect::<Vec<_>>();
+            if !removed.is_empty() {
+                out.push_str(&format!("\nSections no longer present: {}\n", removed.join(", ")));
+            }
+            if changes.regions.is_empty() && changes.sections.is_empty() && changes.crates.is_empty() {
+                out.push_str("\nNo change from the baseline.\n");
+            }
+        }
+        out
+    }
+}
+
+// Where a section's bytes count: where it runs and, if different, where it's stored.
+fn places(region: &Option<String>, load_region: &Option<String>) -> Vec<String> {
+    let mut places = vec![region.clone().unwrap_or_else(|| UNMAPPED.to_string())];
+    if let Some(ref load) = *load_region {
+        places.push(load.clone());
+    }
+    places
+}
+
+// The crate a symbol comes from, from its mangled name. `_ZN4core3fmt5write17h…E` is from core.
+// Trait implementations are named after the type, like `<core::fmt::Arguments as Display>`.
+pub fn crate_name(symbol: &str) -> String {
+    if !symbol.starts_with("_ZN") {
+        return UNMANGLED.to_string();
+    }
+
+    let rest = &symbol[3..];
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    let length: usize = match rest[..digits].parse() {
+        Ok(length) => length,
+        Err(_) => return UNMANGLED.to_string(),
+    };
+    if digits + length > rest.len() || !rest.is_char_boundary(digits + length) {
+        return UNMANGLED.to_string();
+    }
+    let name = &rest[digits..digits + length];
+
+    if !name.starts_with("_$LT$") {
+        return name.to_string();
+    }
+
+    // The first path in `<Type as Trait>` which names a crate.
+    let inner = &name[5..];
+    for path in inner.split("$u20$as$u20$") {
+        let mut path = path;
+        for prefix in &["$RF$", "$BP$", "mut$u20$", "$u5b$"] {
+            while path.starts_with(prefix) {
+                path = &path[prefix.len()..];
+            }
+        }
+        let end = path.find("..").unwrap_or(0);
+        let krate = &path[..end];
+        if end > 0 && krate.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return krate.to_string();
+        }
+    }
+    GENERIC.to_string()
+}
+
+fn differences<I, J>(current: I, baseline: J) -> Vec<(String, i64)>
+    where I: Iterator<Item = (String, u64)>, J: Iterator<Item = (String, u64)>
+{
+    let mut sizes: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for (name, size) in current {
+        sizes.entry(name).or_insert((0, 0)).0 += size;
+    }
+    for (name, size) in baseline {
+        sizes.entry(name).or_insert((0, 0)).1 += size;
+    }
+    sizes.into_iter()
+        .filter(|&(_, (now, before))| now != before)
+        .map(|(name, (now, before))| (name, now as i64 - before as i64))
+        .collect()
+}
+
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(members.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn number(n: u64) -> Json {
+    Json::Number(n as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elf;
+    use layout::Region;
+    use test_elf::{TestElf, FUNC, OBJECT};
+
+    fn regions() -> Vec<Region> {
+        vec![
+            Region { name: "FLASH".to_string(), origin: 0, length: 0x1000 },
+            Region { name: "RAM".to_string(), origin: 0x2000_0000, length: 0x400 },
+        ]
+    }
+
+    fn firmware() -> TestElf {
+        TestElf::new()
+            .text(".vector_table", 0x0, 0x40)
+            .text(".text", 0x40, 0x200)
+            .data(".data", 0x2000_0000, 0x240, 0x20)
+            .bss(".bss", 0x2000_0020, 0x100)
+            .debug(".debug_info", 0x800)
+            .symbol("_ZN4core3fmt5write17h0123456789abcdefE", 0x40, 0x100, FUNC, ".text")
+            .symbol("_ZN16rust_tiva_blinky5start17h0123456789abcdefE", 0x140, 0x80, FUNC, ".text")
+            .symbol("GPIOPinWrite", 0x1C0, 0x20, FUNC, ".text")
+            .symbol("_ZN16rust_tiva_blinky5event11EVENT_QUEUE17h0123456789abcdefE", 0x2000_0000, 0x20, OBJECT, ".data")
+            .symbol("_ZN15block_allocator4HEAP17h0123456789abcdefE", 0x2000_0020, 0x80, OBJECT, ".bss")
+    }
+
+    fn report(elf: TestElf) -> Report {
+        Report::new(&elf::parse(&elf.build()).unwrap(), &regions())
+    }
+
+    fn crate_size(report: &Report, name: &str, region: &str) -> u64 {
+        report.crates.iter().find(|c| c.name == name)
+            .and_then(|c| c.sizes.get(region).cloned())
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn regions_count_what_runs_and_what_is_stored_in_them() {
+        let report = report(firmware());
+        // The vector table, text and the initial values of data.
+        assert_eq!(0x40 + 0x200 + 0x20, report.regions[0].used);
+        // Data and bss.
+        assert_eq!(0x20 + 0x100, report.regions[1].used);
+        assert_eq!(0x400 - 0x120, report.regions[1].free());
+    }
+
+    #[test]
+    fn sections_which_are_not_loaded_onto_the_target_are_left_out() {
+        let report = report(firmware());
+        let names = report.sections.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec![".vector_table", ".text", ".data", ".bss"], names);
+    }
+
+    #[test]
+    fn copied_sections_know_where_they_are_stored() {
+        let report = report(firmware());
+        let data = report.sections.iter().find(|s| s.name == ".data").unwrap();
+        assert_eq!(Some("RAM".to_string()), data.region);
+        assert_eq!(Some("FLASH".to_string()), data.load_region);
+        let bss = report.sections.iter().find(|s| s.name == ".bss").unwrap();
+        assert_eq!(None, bss.load_region);
+    }
+
+    #[test]
+    fn symbols_are_counted_by_crate_and_region() {
+        let report = report(firmware());
+        assert_eq!(0x100, crate_size(&report, "core", "FLASH"));
+        assert_eq!(0x80 + 0x20, crate_size(&report, "rust_tiva_blinky", "FLASH"));
+        assert_eq!(0x20, crate_size(&report, "rust_tiva_blinky", "RAM"));
+        assert_eq!(0x80, crate_size(&report, "block_allocator", "RAM"));
+        assert_eq!(0x20, crate_size(&report, UNMANGLED, "FLASH"));
+    }
+
+    #[test]
+    fn crates_add_up_to_the_regions() {
+        let report = report(firmware());
+        for region in &report.regions {
+            let total: u64 = report.crates.iter().map(|c| c.sizes.get(&region.name).cloned().unwrap_or(0)).sum();
+            assert_eq!(region.used, total);
+        }
+        // The vector table has no sized symbols, and neither does the end of .text.
+        assert_eq!(0x40 + 0x60, crate_size(&report, NO_SYMBOL, "FLASH"));
+    }
+
+    #[test]
+    fn crates_are_sorted_biggest_first() {
+        let report = report(firmware());
+        let totals = report.crates.iter().map(|c| c.total()).collect::<Vec<_>>();
+        let mut sorted = totals.clone();
+        sorted.sort_by(|a, b| b.cmp(a));
+        assert_eq!(sorted, totals);
+    }
+
+    #[test]
+    fn aliases_are_only_counted_once() {
+        let report = report(firmware().symbol("memcpy_alias", 0x1C0, 0x20, FUNC, ".text"));
+        assert_eq!(0x20, crate_size(&report, UNMANGLED, "FLASH"));
+    }
+
+    #[test]
+    fn sections_outside_every_region_are_unmapped() {
+        let report = report(firmware().text(".stray", 0x9000_0000, 0x10));
+        let stray = report.sections.iter().find(|s| s.name == ".stray").unwrap();
+        assert_eq!(None, stray.region);
+        assert_eq!(0x10, crate_size(&report, NO_SYMBOL, UNMAPPED));
+    }
+
+    #[test]
+    fn crate_names_come_from_the_first_path_component() {
+        assert_eq!("core", crate_name("_ZN4core3fmt5write17h0123456789abcdefE"));
+        assert_eq!("collections", crate_name("_ZN11collections3vec8{{impl}}4push17h0123456789abcdefE"));
+        assert_eq!("core", crate_name(
+            "_ZN62_$LT$core..fmt..Arguments$LT$$u27$a$GT$$u20$as$u20$core..fmt..Display$GT$3fmt17h0123456789abcdefE"));
+        assert_eq!("core", crate_name("_ZN47_$LT$$RF$$u27$a$u20$T$u20$as$u20$core..fmt..Debug$GT$3fmt17hE"));
+        assert_eq!(UNMANGLED, crate_name("GPIOPinWrite"));
+        assert_eq!(UNMANGLED, crate_name("_ZN99tooshort"));
+    }
+
+    #[test]
+    fn a_report_survives_a_round_trip_through_json() {
+        let report = report(firmware());
+        let json = ::json::parse(&report.to_json(None).pretty()).unwrap();
+        assert_eq!(report, Report::from_json(&json).unwrap());
+    }
+
+    #[test]
+    fn there_are_no_changes_from_an_identical_baseline() {
+        let report = report(firmware());
+        let changes = report.changes(&report.clone());
+        assert!(changes.regions.is_empty() && changes.sections.is_empty() && changes.crates.is_empty());
+        assert!(report.text(Some(&report), 10).contains("No change from the baseline."));
+    }
+
+    #[test]
+    fn changes_show_growth_new_and_removed_items() {
+        let baseline = report(firmware().text(".old", 0x300, 0x10));
+        let current = report(firmware()
+            .symbol("_ZN8heapless3Vec4push17h0123456789abcdefE", 0x40 + 0x1E0, 0x10, FUNC, ".text")
+            .bss(".noinit", 0x2000_0200, 0x8));
+        let changes = current.changes(&baseline);
+
+        assert_eq!(vec![("FLASH".to_string(), -0x10), ("RAM".to_string(), 0x8)], changes.regions);
+        assert_eq!(vec![(".noinit".to_string(), 0x8), (".old".to_string(), -0x10)], changes.sections);
+        assert!(changes.crates.contains(&("heapless".to_string(), 0x10)));
+        assert!(current.text(Some(&baseline), 10).contains("Sections no longer present: .old"));
+    }
+
+    #[test]
+    fn regions_may_grow_by_the_margin() {
+        let baseline = report(firmware());
+        let current = report(firmware().text(".more", 0x260, 0x10));
+        assert!(current.over_budget(&baseline, 0x10).is_empty());
+        assert_eq!(vec![("FLASH".to_string(), 0x270, 0x26F)], current.over_budget(&baseline, 0xF));
+        // Shrinking is always fine.
+        assert!(baseline.over_budget(&current, 0).is_empty());
+    }
+
+    #[test]
+    fn the_json_report_includes_changes_against_a_baseline() {
+        let baseline = report(firmware());
+        let current = report(firmware().bss(".noinit", 0x2000_0200, 0x8));
+        let json = current.to_json(Some(&baseline));
+        let changes = json.get("changes").unwrap();
+        assert_eq!(Some(8), changes.get("regions").unwrap().get("RAM").and_then(Json::as_i64));
+        assert_eq!(None, changes.get("regions").unwrap().get("FLASH"));
+        assert_eq!(Some(8), changes.get("sections").unwrap().get(".noinit").and_then(Json::as_i64));
+    }
+
+    #[test]
+    fn the_text_summary_lists_regions_sections_and_crates() {
+        let text = report(firmware()).text(None, 2);
+        assert!(text.contains("FLASH"));
+        assert!(text.contains(".data"));
+        assert!(text.contains("FLASH>RAM"));
+        assert!(text.contains("core"));
+        assert!(text.contains("more"));
+    }
+}
@@ -0,0 +1,211 @@
+/*
+    Builds small ELF files for the tests, laid out the way the linker lays out the firmware: each
+    section with contents gets its own loadable segment, and the symbol table comes last.
+*/
+
+pub const OBJECT: u8 = 1;
+pub const FUNC: u8 = 2;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u32 = 0x1;
+const SHF_ALLOC: u32 = 0x2;
+const SHF_EXECINSTR: u32 = 0x4;
+
+struct TestSection {
+    name: String,
+    kind: u32,
+    flags: u32,
+    address: u32,
+    load_address: u32,
+    size: u32,
+}
+
+struct TestSymbol {
+    name: String,
+    address: u32,
+    size: u32,
+    kind: u8,
+    section: String,
+}
+
+pub struct TestElf {
+    sections: Vec<TestSection>,
+    symbols: Vec<TestSymbol>,
+}
+
+impl TestElf {
+    pub fn new() -> TestElf {
+        TestElf { sections: Vec::new(), symbols: Vec::new() }
+    }
+
+    // Code or read-only data, run from where it's loaded.
+    pub fn text(self, name: &str, address: u32, size: u32) -> TestElf {
+        self.section(name, SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, address, address, size)
+    }
+
+    // Initialized data, copied from `load_address` to `address` at startup.
+    pub fn data(self, name: &str, address: u32, load_address: u32, size: u32) -> TestElf {
+        self.section(name, SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, address, load_address, size)
+    }
+
+    // Zeroed data, with no contents in the file.
+    pub fn bss(self, name: &str, address: u32, size: u32) -> TestElf {
+        self.section(name, SHT_NOBITS, SHF_ALLOC | SHF_WRITE, address, address, size)
+    }
+
+    // Information which isn't loaded onto the target.
+    pub fn debug(self, name: &str, size: u32) -> TestElf {
+        self.section(name, SHT_PROGBITS, 0, 0, 0, size)
+    }
+
+    pub fn symbol(mut self, name: &str, address: u32, size: u32, kind: u8, section: &str) -> TestElf {
+        self.symbols.push(TestSymbol {
+            name: name.to_string(),
+            address,
+            size,
+            kind,
+            section: section.to_string(),
+        });
+        self
+    }
+
+    fn section(mut self, name: &str, kind: u32, flags: u32, address: u32, load_address: u32,
+               size: u32) -> TestElf {
+        self.sections.push(TestSection {
+            name: name.to_string(),
+            kind,
+            flags,
+            address,
+            load_address,
+            size,
+        });
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let loadable: Vec<&TestSection> = self.sections.iter()
+            .filter(|s| s.kind == SHT_PROGBITS && s.flags & SHF_ALLOC != 0)
+            .collect();
+
+        // Section numbers: the null section, the test sections, then the string and symbol tables.
+        let section_names_index = self.sections.len() + 1;
+        let symbols_index = section_names_index + 1;
+        let strings_index = symbols_index + 1;
+
+        let mut out = vec![0u8; 52 + 32 * loadable.len()];
+
+        // Section contents.
+        let mut offsets = Vec::new();
+        for section in &self.sections {
+            offsets.push(out.len() as u32);
+            if section.kind != SHT_NOBITS {
+                let size = out.len() + section.size as usize;
+                out.resize(size, 0xAA);
+            }
+        }
+
+        // Section names.
+        let section_names_offset = out.len() as u32;
+        let mut section_names = vec![0u8];
+        let mut name_offsets = Vec::new();
+        for name in self.sections.iter().map(|s| s.name.as_str())
+            .chain(vec![".shstrtab", ".symtab", ".strtab"])
+        {
+            name_offsets.push(section_names.len() as u32);
+            section_names.extend_from_slice(name.as_bytes());
+            section_names.push(0);
+        }
+        out.extend_from_slice(&section_names);
+
+        // Symbols, starting with the null symbol, and their names.
+        let mut strings = vec![0u8];
+        let mut symbols = vec![0u8; 16];
+        for symbol in &self.symbols {
+            let section = self.sections.iter().position(|s| s.name == symbol.section)
+                .expect("symbol in an unknown section") + 1;
+            push_u32(&mut symbols, strings.len() as u32);
+            let thumb = if symbol.kind == FUNC { 1 } else { 0 };
+            push_u32(&mut symbols, symbol.address | thumb);
+            push_u32(&mut symbols, symbol.size);
+            symbols.push(0x10 | symbol.kind); // Global binding.
+            symbols.push(0);
+            push_u16(&mut symbols, section as u16);
+            strings.extend_from_slice(symbol.name.as_bytes());
+            strings.push(0);
+        }
+        let symbols_offset = out.len() as u32;
+        out.extend_from_slice(&symbols);
+        let strings_offset = out.len() as u32;
+        out.extend_from_slice(&strings);
+
+        // Section headers.
+        let section_headers = out.len() as u32;
+        out.extend_from_slice(&[0u8; 40]);
+        for (i, section) in self.sections.iter().enumerate() {
+            section_header(&mut out, name_offsets[i], section.kind, section.flags, section.address,
+                           offsets[i], section.size, 0);
+        }
+        let n = self.sections.len();
+        section_header(&mut out, name_offsets[n], SHT_STRTAB, 0, 0, section_names_offset,
+                       section_names.len() as u32, 0);
+        section_header(&mut out, name_offsets[n + 1], SHT_SYMTAB, 0, 0, symbols_offset,
+                       symbols.len() as u32, strings_index as u32);
+        section_header(&mut out, name_offsets[n + 2], SHT_STRTAB, 0, 0, strings_offset,
+                       strings.len() as u32, 0);
+
+        // The file header.
+        let mut header = Vec::new();
+        header.extend_from_slice(b"\x7fELF\x01\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00");
+        push_u16(&mut header, 2); // Executable.
+        push_u16(&mut header, 40); // ARM.
+        push_u32(&mut header, 1); // Version.
+        push_u32(&mut header, 0); // Entry point.
+        push_u32(&mut header, 52); // Program headers follow the file header.
+        push_u32(&mut header, section_headers);
+        push_u32(&mut header, 0); // Flags.
+        push_u16(&mut header, 52);
+        push_u16(&mut header, 32);
+        push_u16(&mut header, loadable.len() as u16);
+        push_u16(&mut header, 40);
+        push_u16(&mut header, strings_index as u16 + 1);
+        push_u16(&mut header, section_names_index as u16);
+
+        // A loadable segment for each section with contents.
+        for section in loadable {
+            let i = self.sections.iter().position(|s| s.name == section.name).unwrap();
+            push_u32(&mut header, 1); // PT_LOAD.
+            push_u32(&mut header, offsets[i]);
+            push_u32(&mut header, section.address);
+            push_u32(&mut header, section.load_address);
+            push_u32(&mut header, section.size);
+            push_u32(&mut header, section.size);
+            push_u32(&mut header, 0);
+            push_u32(&mut header, 4);
+        }
+        out[..header.len()].copy_from_slice(&header);
+        out
+    }
+}
+
+// Takes the header's fields in the order they're laid out.
+#[allow(clippy::too_many_arguments)]
+fn section_header(out: &mut Vec<u8>, name: u32, kind: u32, flags: u32, address: u32, offset: u32,
+                  size: u32, link: u32) {
+    for &field in &[name, kind, flags, address, offset, size, link, 0, 4, 0] {
+        push_u32(out, field);
+    }
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.push(value as u8);
+    out.push((value >> 8) as u8);
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    for i in 0..4 {
+        out.push((value >> (8 * i)) as u8);
+    }
+}